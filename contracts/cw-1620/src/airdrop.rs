@@ -0,0 +1,233 @@
+// Merkle airdrops which vest into streams
+// A campaign commits to every (address, amount) pair with a merkle root, each eligible address proves its leaf
+// and gets its own stream following the schedule of the campaign. A campaign with an expiration stops accepting
+// claims then and its creator can take back what nobody claimed
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128};
+use cw_asset::AssetInfo;
+use cw_utils::Expiration;
+use sha2::{Digest, Sha256};
+use wynd_utils::Curve;
+
+use crate::contract::{payout_msg, try_create_stream};
+use crate::curve_helpers;
+use crate::msg::AirdropResponse;
+use crate::state::{
+    Airdrop, StreamData, StreamTime, StreamType, AIRDROPS, AIRDROP_CLAIMS, LAST_AIRDROP_IDX,
+    LAST_STREAM_IDX,
+};
+use crate::ContractError;
+
+/// The schedule every stream of a campaign follows and until when it can be claimed
+pub struct AirdropSchedule {
+    pub start_time: StreamTime,
+    pub stop_time: StreamTime,
+    pub curve: Option<Curve>,
+    pub expiration: Option<Expiration>,
+}
+
+// Registers a funded campaign, `sender` becomes the sender of every stream claimed from it
+pub fn register_airdrop(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    merkle_root: String,
+    asset: AssetInfo,
+    total: Uint128,
+    schedule: AirdropSchedule,
+) -> Result<Response, ContractError> {
+    let mut root = [0u8; 32];
+    hex::decode_to_slice(&merkle_root, &mut root)
+        .map_err(|_| ContractError::InvalidMerkleRoot {})?;
+    if total.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    if !schedule.start_time.same_unit(&schedule.stop_time)
+        || schedule.stop_time.value() <= schedule.start_time.value()
+    {
+        return Err(ContractError::InvalidSchedule {});
+    }
+    if let Some(expiration) = &schedule.expiration {
+        if expiration.is_expired(&env.block) {
+            return Err(ContractError::InvalidSchedule {});
+        }
+    }
+    // The template is scaled to each claim so it has to end somewhere above zero and only ever increase
+    if let Some(curve) = &schedule.curve {
+        curve.validate_monotonic_increasing()?;
+        curve_helpers::validate_curve(template_stream_type(curve), curve)?;
+        if curve_helpers::curve_total(curve)
+            .unwrap_or_default()
+            .is_zero()
+        {
+            return Err(ContractError::InvalidSchedule {});
+        }
+    }
+
+    let airdrop_id = LAST_AIRDROP_IDX.may_load(deps.storage)?.unwrap_or_default() + 1;
+    let airdrop = Airdrop {
+        airdrop_id,
+        sender,
+        merkle_root,
+        asset,
+        total,
+        claimed: Uint128::zero(),
+        start_time: schedule.start_time,
+        stop_time: schedule.stop_time,
+        curve: schedule.curve,
+        expiration: schedule.expiration,
+        reclaimed: Uint128::zero(),
+    };
+    AIRDROPS.save(deps.storage, airdrop_id, &airdrop)?;
+    LAST_AIRDROP_IDX.save(deps.storage, &airdrop_id)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "register_airdrop")
+        .add_attribute("airdrop_id", airdrop_id.to_string())
+        .add_attribute("total", total))
+}
+
+// Checks the proof of the caller and creates their stream out of the campaign funds
+pub fn claim_airdrop_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    airdrop_id: u64,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut airdrop = AIRDROPS.load(deps.storage, airdrop_id)?;
+    if is_expired(&airdrop, &env) {
+        return Err(ContractError::AirdropExpired { airdrop_id });
+    }
+    if AIRDROP_CLAIMS.has(deps.storage, (airdrop_id, &info.sender)) {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+    verify_proof(&airdrop.merkle_root, &info.sender, amount, &proof)?;
+    airdrop.claimed = airdrop.claimed.checked_add(amount)?;
+    if airdrop.claimed > airdrop.total {
+        return Err(ContractError::NotEnoughAvailableFunds {});
+    }
+
+    let (stream_type, curve) = match &airdrop.curve {
+        None => (StreamType::Basic, None),
+        Some(template) => (
+            template_stream_type(template),
+            Some(curve_helpers::scale_curve(template, amount)?),
+        ),
+    };
+    let stream_idx = LAST_STREAM_IDX.load(deps.storage)? + 1;
+    AIRDROPS.save(deps.storage, airdrop_id, &airdrop)?;
+    AIRDROP_CLAIMS.save(deps.storage, (airdrop_id, &info.sender), &stream_idx)?;
+
+    // The stream is created as if the campaign sender funded it directly
+    let recipient = info.sender.to_string();
+    let sender_info = MessageInfo {
+        sender: airdrop.sender.clone(),
+        funds: vec![],
+    };
+    let response = try_create_stream(
+        deps,
+        env,
+        sender_info,
+        recipient,
+        amount,
+        airdrop.asset,
+        StreamData {
+            start_time: Some(airdrop.start_time),
+            stop_time: Some(airdrop.stop_time),
+            duration: None,
+            stream_type: Some(stream_type),
+            curve,
+            options: None,
+        },
+    )?;
+
+    Ok(response
+        .add_attribute("airdrop_id", airdrop_id.to_string())
+        .add_attribute("stream_idx", stream_idx.to_string()))
+}
+
+// Returns what is left of an expired campaign to its creator, only the creator can do this
+pub fn reclaim_airdrop(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    airdrop_id: u64,
+) -> Result<Response, ContractError> {
+    let mut airdrop = AIRDROPS.load(deps.storage, airdrop_id)?;
+    if info.sender != airdrop.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !is_expired(&airdrop, &env) {
+        return Err(ContractError::AirdropNotExpired { airdrop_id });
+    }
+    let amount = airdrop
+        .total
+        .checked_sub(airdrop.claimed)?
+        .checked_sub(airdrop.reclaimed)?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    airdrop.reclaimed = airdrop.reclaimed.checked_add(amount)?;
+    AIRDROPS.save(deps.storage, airdrop_id, &airdrop)?;
+
+    Ok(Response::new()
+        .add_message(payout_msg(&airdrop.asset, &airdrop.sender, amount)?)
+        .add_attribute("method", "reclaim_airdrop")
+        .add_attribute("airdrop_id", airdrop_id.to_string())
+        .add_attribute("amount", amount))
+}
+
+// A campaign without an expiration can be claimed from for good
+fn is_expired(airdrop: &Airdrop, env: &Env) -> bool {
+    airdrop
+        .expiration
+        .as_ref()
+        .map_or(false, |expiration| expiration.is_expired(&env.block))
+}
+
+// The stream type the streams of a campaign are created with, following the kind of curve of its template
+fn template_stream_type(curve: &Curve) -> StreamType {
+    match curve {
+        Curve::PiecewiseLinear(_) => StreamType::CliffCurveBased,
+        _ => StreamType::LinearCurveBased,
+    }
+}
+
+// Hashes the leaf of `address` and walks the proof up to the root, pairs are hashed in sorted order
+fn verify_proof(
+    merkle_root: &str,
+    address: &Addr,
+    amount: Uint128,
+    proof: &[String],
+) -> Result<(), ContractError> {
+    let leaf: [u8; 32] = Sha256::digest(format!("{}{}", address, amount).as_bytes()).into();
+    let hash = proof.iter().try_fold(leaf, |hash, sibling| {
+        let mut sibling_hash = [0u8; 32];
+        hex::decode_to_slice(sibling, &mut sibling_hash)
+            .map_err(|_| ContractError::InvalidProof {})?;
+        let (first, second) = if hash <= sibling_hash {
+            (hash, sibling_hash)
+        } else {
+            (sibling_hash, hash)
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(first);
+        hasher.update(second);
+        Ok::<[u8; 32], ContractError>(hasher.finalize().into())
+    })?;
+
+    let mut root = [0u8; 32];
+    hex::decode_to_slice(merkle_root, &mut root)
+        .map_err(|_| ContractError::InvalidMerkleRoot {})?;
+    if hash != root {
+        return Err(ContractError::InvalidProof {});
+    }
+    Ok(())
+}
+
+pub fn query_airdrop(deps: Deps, airdrop_id: u64) -> StdResult<AirdropResponse> {
+    Ok(AirdropResponse {
+        airdrop: AIRDROPS.load(deps.storage, airdrop_id)?,
+    })
+}
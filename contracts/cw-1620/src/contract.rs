@@ -1,26 +1,40 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env,
-    MessageInfo, Order, Response, StdResult, Timestamp, Uint128,
+    from_binary, to_binary, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut,
+    Empty, Env, MessageInfo, Order, Reply, Response, StdResult, Storage, SubMsg, Uint128, Uint256,
+    WasmMsg,
 };
-use cw2::set_contract_version;
-use cw20::Cw20ReceiveMsg;
-use cw_utils::may_pay;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_utils::{may_pay, must_pay, nonpayable, Duration, Expiration};
+use paystreams::{StreamEvent, StreamReceiveMsg};
 use serde::de;
 use wynd_utils::Curve;
 
+use crate::airdrop::{self, AirdropSchedule};
 use crate::curve_helpers;
 use crate::error::ContractError;
+use crate::fiat;
+use crate::ibc;
+use crate::ibc_hooks::{self, SudoMsg};
+use crate::milestones;
 use crate::msg::{
-    CountResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, LookupStreamResponse, QueryMsg,
+    AmountOrRatio, ConfigResponse, CountResponse, Cw20HookMsg, ExecuteMsg, InfoResponse,
+    InstantiateMsg, LookupStreamResponse, MigrateMsg, QueryMsg, SenderBalanceResponse,
     StreamClaimableAmtResponse, StreamsResponse,
 };
+use crate::offers;
+use crate::staking;
 use crate::state::{
-    payment_streams, ConfigState, PaymentStream, StreamData, StreamType, LAST_STREAM_IDX, STATE,
-    STREAMS,
+    payment_streams, CancelableBy, ClaimOperator, ConfigState, PaymentStream, RecipientShare,
+    SenderBalance, StreamData, StreamStatus, StreamTime, StreamType, GOVERNANCE_ASSET,
+    LAST_STREAM_IDX, SENDER_BALANCES, SHARED_STREAMS, STATE, STREAMS, UNBONDING_PERIOD,
 };
+use crate::vault;
+use crate::voting;
 use cw_asset::{Asset, AssetInfo, AssetInfoBase, AssetInfoKey};
+use white_whale::pool_network::asset::Asset as WwAsset;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw-1620";
@@ -28,6 +42,8 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_LIMIT_FOR_QUERY: Uint128 = Uint128::new(10);
 #[allow(unused)]
 const DEFAULT_ORDER_FOR_QUERY: Order = Order::Ascending;
+// Reply id of the StreamReceiveMsg sent on cancel, a failing recipient hook must not block a cancel
+const CANCEL_HOOK_REPLY_ID: u64 = 1;
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -44,6 +60,9 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
     LAST_STREAM_IDX.save(deps.storage, &0u64)?;
+    if let Some(governance_asset) = msg.governance_asset {
+        GOVERNANCE_ASSET.save(deps.storage, &governance_asset)?;
+    }
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -52,8 +71,18 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: Empty) -> Result<Response, ContractError> {
-    Ok(Response::new())
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    match msg.governance_asset {
+        Some(governance_asset) => GOVERNANCE_ASSET.save(deps.storage, &governance_asset)?,
+        None => GOVERNANCE_ASSET.remove(deps.storage),
+    }
+    // Streams created before voting power was tracked, or under another governance asset, get their
+    // snapshots from here on. Going through every stream may not fit in a block, so this only starts the
+    // rebuild and `RebuildVotingPower` does it page by page
+    voting::start_rebuild(deps.storage, env.block.height)?;
+
+    Ok(Response::new().add_attribute("method", "migrate"))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -70,345 +99,1648 @@ pub fn execute(
             asset,
             start_time,
             stop_time,
+            duration,
             stream_type,
             curve,
-        } => match asset.info.clone() {
-            AssetInfo::Native(denom) => {
-                let deposit_amount = may_pay(&info, &denom).unwrap();
-                if deposit_amount < asset.amount {
-                    return Err(ContractError::NotEnoughAvailableFunds {});
+            options,
+        } => {
+            let from_balance = options
+                .as_ref()
+                .and_then(|options| options.from_balance)
+                .unwrap_or(false);
+            match asset.info.clone() {
+                // Streams drawing from the prepaid balance of the sender take no funds, anything sent along would
+                // be left in the contract unaccounted
+                _ if from_balance => {
+                    nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+                }
+                AssetInfo::Native(denom) => {
+                    let deposit_amount = may_pay(&info, &denom).unwrap();
+                    if deposit_amount < asset.amount {
+                        return Err(ContractError::NotEnoughAvailableFunds {});
+                    }
                 }
-                try_create_stream(
-                    deps,
-                    info,
-                    recipient,
-                    asset.amount,
-                    asset.info,
-                    StreamData {
-                        start_time: Timestamp::from_seconds(start_time),
-                        stop_time: Timestamp::from_seconds(stop_time),
-                        stream_type: stream_type,
-                        curve: curve,
-                    },
-                )
+                _ => unimplemented!(),
             }
-            _ => unimplemented!(),
-        },
+            try_create_stream(
+                deps,
+                env,
+                info,
+                recipient,
+                asset.amount,
+                asset.info,
+                StreamData {
+                    start_time,
+                    stop_time,
+                    duration,
+                    stream_type: stream_type,
+                    curve: curve,
+                    options,
+                },
+            )
+        }
         ExecuteMsg::ClaimFromStream {
-            recipient,
             amount,
-            denom,
             stream_idx,
-        } => claim_from_stream(deps, info, env, recipient, amount, denom, stream_idx),
-        ExecuteMsg::CancelStream { stream_idx } => {
-            // Load stream, verify sender is the sender of stream, and then delete stream
+            to,
+            msg,
+            ..
+        } => claim_from_stream(deps, info, env, amount, stream_idx, to, msg),
+        ExecuteMsg::ActivateStream { stream_idx } => activate_stream(deps, env, info, stream_idx),
+        ExecuteMsg::CancelStream { stream_idx } => cancel_stream(deps, env, info, stream_idx),
+        ExecuteMsg::TransferStream {
+            stream_idx,
+            new_recipient,
+        } => transfer_stream(deps, env, info, stream_idx, new_recipient),
+        ExecuteMsg::SetClaimOperator {
+            stream_idx,
+            operator,
+            expires,
+        } => set_claim_operator(deps, info, stream_idx, operator, expires),
+        ExecuteMsg::SetWithdrawalAddress {
+            stream_idx,
+            address,
+        } => set_withdrawal_address(deps, info, stream_idx, address),
+        ExecuteMsg::SplitStream {
+            stream_idx,
+            amount_or_ratio,
+            new_recipient,
+        } => split_stream(deps, env, info, stream_idx, amount_or_ratio, new_recipient),
+        ExecuteMsg::MergeStreams { stream_idxs } => merge_streams(deps, env, info, stream_idxs),
+        ExecuteMsg::AcceptStream { stream_idx } => accept_stream(deps, env, info, stream_idx),
+        ExecuteMsg::ReclaimStream { stream_idx } => reclaim_stream(deps, env, info, stream_idx),
+        ExecuteMsg::SweepExpired { stream_idx } => sweep_expired(deps, env, info, stream_idx),
+        ExecuteMsg::RegisterAirdrop {
+            merkle_root,
+            asset,
+            start_time,
+            stop_time,
+            curve,
+            expiration,
+        } => {
+            // The campaign is funded with exactly what was sent along
+            match &asset.info {
+                AssetInfo::Native(denom) => {
+                    let paid = must_pay(&info, denom)
+                        .map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+                    if paid != asset.amount {
+                        return Err(ContractError::InvalidAmount {});
+                    }
+                }
+                _ => return Err(ContractError::UnsupportedAsset {}),
+            }
+            airdrop::register_airdrop(
+                deps,
+                env,
+                info.sender,
+                merkle_root,
+                asset.info,
+                asset.amount,
+                AirdropSchedule {
+                    start_time,
+                    stop_time,
+                    curve,
+                    expiration,
+                },
+            )
+        }
+        ExecuteMsg::RegisterOfferKey { public_key } => {
+            offers::register_offer_key(deps, info, public_key)
+        }
+        ExecuteMsg::SubmitSignedOffer { offer, signature } => {
+            // The stream is paid from the sender's prepaid balance
+            nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+            offers::submit_signed_offer(deps, env, offer, signature)
+        }
+        ExecuteMsg::RevokeOffer { nonce } => offers::revoke_offer(deps, info, nonce),
+        ExecuteMsg::ClaimAirdropStream {
+            airdrop_id,
+            amount,
+            proof,
+        } => airdrop::claim_airdrop_stream(deps, env, info, airdrop_id, amount, proof),
+        ExecuteMsg::ReclaimAirdrop { airdrop_id } => {
+            nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+            airdrop::reclaim_airdrop(deps, env, info, airdrop_id)
+        }
+        ExecuteMsg::Deposit {} => {
+            if info.funds.is_empty() {
+                return Err(ContractError::InvalidAmount {});
+            }
+            let mut response = Response::new().add_attribute("method", "deposit");
+            for coin in &info.funds {
+                let asset = AssetInfo::Native(coin.denom.clone());
+                deposit_balance(deps.storage, &info.sender, &asset, coin.amount)?;
+                response = response.add_attribute("amount", coin.to_string());
+            }
+            Ok(response)
+        }
+        ExecuteMsg::WithdrawBalance { asset } => withdraw_balance(deps, info, asset),
+        ExecuteMsg::UpdateConfig {
+            owner,
+            fee_asset,
+            fees,
+            unbonding_period,
+        } => update_config(deps, info, owner, fee_asset, fees, unbonding_period),
+        ExecuteMsg::CreateStreamFromIbc {
+            channel_id,
+            original_sender,
+            sender,
+            recipient,
+            start_time,
+            stop_time,
+            duration,
+            stream_type,
+            curve,
+            options,
+        } => ibc_hooks::create_stream_from_ibc(
+            deps,
+            env,
+            info,
+            channel_id,
+            original_sender,
+            sender,
+            recipient,
+            StreamData {
+                start_time,
+                stop_time,
+                duration,
+                stream_type,
+                curve,
+                options,
+            },
+        ),
+        ExecuteMsg::OfferChannelLiquidity { channel_id, offer } => {
+            ibc::offer_channel_liquidity(deps, info, channel_id, offer)
+        }
+        ExecuteMsg::ReceiveStreamPacket {
+            channel_id,
+            provider,
+            packet,
+        } => ibc::receive_stream_packet(deps, env, info, channel_id, provider, packet),
+        ExecuteMsg::ReturnIbcBalance { sender, denom } => {
+            ibc_hooks::return_ibc_balance(deps, env, sender, denom)
+        }
+        ExecuteMsg::UndelegateVested { stream_idx } => {
+            staking::undelegate_vested(deps, env, stream_idx)
+        }
+        ExecuteMsg::ClaimStakingRewards { stream_idx } => {
+            staking::claim_staking_rewards(deps, env, stream_idx)
+        }
+        ExecuteMsg::SettleUnbonded { stream_idx } => {
+            staking::settle_unbonded(deps, env, stream_idx)
+        }
+        ExecuteMsg::RebuildVotingPower { limit } => {
+            let done = voting::rebuild_voting_power(deps.storage, env.block.height, limit)?;
+            Ok(Response::new()
+                .add_attribute("method", "rebuild_voting_power")
+                .add_attribute("done", done.to_string()))
+        }
+        ExecuteMsg::HarvestYield { stream_idx } => vault::harvest_yield(deps, env, stream_idx),
+        ExecuteMsg::ApproveMilestone {
+            stream_idx,
+            milestone,
+        } => milestones::approve_milestone(deps, env, info, stream_idx, milestone),
+        ExecuteMsg::ReclaimMilestone {
+            stream_idx,
+            milestone,
+        } => milestones::reclaim_milestone(deps, env, info, stream_idx, milestone),
+        ExecuteMsg::TopUpStream { stream_idx, amount } => {
             let stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
-            // Only the stream sender or recipient can cancel a stream
-            // also the sender cannot cancel a stream that has already started
-            if info.sender != stream.sender && info.sender != stream.recipient {
-                return Err(ContractError::Unauthorized {});
+            let amount = match (&stream.token_addr, amount) {
+                // Funds of a stream drawing from the prepaid balance come from there as well
+                (_, Some(amount)) if stream.funded_from_balance => {
+                    nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+                    amount
+                }
+                (AssetInfo::Native(denom), None) if !stream.funded_from_balance => {
+                    must_pay(&info, denom).map_err(|_| ContractError::InvalidAmount {})?
+                }
+                _ => return Err(ContractError::InvalidAmount {}),
+            };
+            top_up_stream(deps, env, &info.sender, stream, amount)
+        }
+        ExecuteMsg::AddCollateral { stream_idx } => {
+            fiat::add_collateral(deps, env, info, stream_idx)
+        }
+        ExecuteMsg::WithdrawCollateral { stream_idx } => {
+            nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+            fiat::withdraw_collateral(deps, env, info, stream_idx)
+        }
+        ExecuteMsg::UpdateVaultAllowlist { add, remove } => {
+            vault::update_vault_allowlist(deps, info, add, remove)
+        }
+    }
+}
+
+// Owner only, fields left empty keep their current value
+fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
+    fee_asset: Option<WwAsset>,
+    fees: Option<Vec<Decimal>>,
+    unbonding_period: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(owner) = owner {
+        state.owner = deps.api.addr_validate(&owner)?;
+    }
+    if let Some(fee_asset) = fee_asset {
+        state.fee_asset = Some(fee_asset);
+    }
+    if let Some(fees) = fees {
+        state.fees = Some(fees);
+    }
+    if let Some(unbonding_period) = unbonding_period {
+        UNBONDING_PERIOD.save(deps.storage, &unbonding_period)?;
+    }
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_attribute("owner", state.owner))
+}
+
+// receive_cw20 routes a cw20 token to the proper handler in this case stake and unstake
+fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::CreateStream {
+            recipient,
+            start_time,
+            stop_time,
+            duration,
+            stream_type,
+            curve,
+            options,
+        } => {
+            // The tokens sent along fund the stream, it can't draw from the prepaid balance as well
+            if cw20_msg.amount.is_zero()
+                || options
+                    .as_ref()
+                    .and_then(|options| options.from_balance)
+                    .unwrap_or(false)
+            {
+                return Err(ContractError::InvalidAmount {});
+            }
+            let asset = AssetInfo::Cw20(info.sender.clone());
+            try_create_stream(
+                deps,
+                env,
+                info,
+                recipient,
+                cw20_msg.amount,
+                asset,
+                StreamData {
+                    start_time,
+                    stop_time,
+                    duration,
+                    stream_type: stream_type,
+                    curve: curve,
+                    options,
+                },
+            )
+        }
+        Cw20HookMsg::RegisterAirdrop {
+            merkle_root,
+            start_time,
+            stop_time,
+            curve,
+            expiration,
+        } => airdrop::register_airdrop(
+            deps,
+            env,
+            sender,
+            merkle_root,
+            AssetInfo::Cw20(info.sender),
+            cw20_msg.amount,
+            AirdropSchedule {
+                start_time,
+                stop_time,
+                curve,
+                expiration,
+            },
+        ),
+        Cw20HookMsg::TopUpStream { stream_idx } => {
+            let stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+            if stream.funded_from_balance || stream.token_addr != AssetInfo::Cw20(info.sender) {
+                return Err(ContractError::InvalidAmount {});
+            }
+            top_up_stream(deps, env, &sender, stream, cw20_msg.amount)
+        }
+        Cw20HookMsg::Deposit {} => {
+            if cw20_msg.amount.is_zero() {
+                return Err(ContractError::InvalidAmount {});
+            }
+            let asset = AssetInfo::Cw20(info.sender);
+            deposit_balance(deps.storage, &sender, &asset, cw20_msg.amount)?;
+            Ok(Response::new()
+                .add_attribute("method", "deposit")
+                .add_attribute("asset", asset.to_string())
+                .add_attribute("amount", cw20_msg.amount))
+        }
+    }
+}
+
+// Credits the free part of the prepaid balance of a sender
+pub(crate) fn deposit_balance(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    asset: &AssetInfo,
+    amount: Uint128,
+) -> Result<SenderBalance, ContractError> {
+    update_sender_balance(storage, sender, asset, |mut balance| {
+        balance.free = balance.free.checked_add(amount)?;
+        Ok(balance)
+    })
+}
+
+pub(crate) fn update_sender_balance<F>(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    asset: &AssetInfo,
+    update: F,
+) -> Result<SenderBalance, ContractError>
+where
+    F: FnOnce(SenderBalance) -> Result<SenderBalance, ContractError>,
+{
+    SENDER_BALANCES.update(storage, (sender, &asset.to_string()), |balance| {
+        update(balance.unwrap_or_default())
+    })
+}
+
+// Accounts for funds leaving a stream, `paid_out` went to recipients and `refund` goes back to the sender.
+// For a stream funded from the prepaid balance of the sender both stop being committed and the refund returns
+// to the free balance, otherwise the refund is sent to the sender
+pub(crate) fn release_stream_funds(
+    storage: &mut dyn Storage,
+    stream: &PaymentStream,
+    paid_out: Uint128,
+    refund: Uint128,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if stream.funded_from_balance {
+        update_sender_balance(
+            storage,
+            &stream.sender,
+            &stream.token_addr,
+            |mut balance| {
+                balance.committed = balance
+                    .committed
+                    .checked_sub(paid_out.checked_add(refund)?)?;
+                balance.free = balance.free.checked_add(refund)?;
+                Ok(balance)
+            },
+        )?;
+        return Ok(None);
+    }
+    if refund.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(payout_msg(
+        &stream.token_addr,
+        &stream.sender,
+        refund,
+    )?))
+}
+
+// Pays out part of the free prepaid balance of the sender, what is committed to streams stays locked
+pub fn withdraw_balance(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: Asset,
+) -> Result<Response, ContractError> {
+    if asset.amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    update_sender_balance(deps.storage, &info.sender, &asset.info, |mut balance| {
+        balance.free = balance
+            .free
+            .checked_sub(asset.amount)
+            .map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+        Ok(balance)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "withdraw_balance")
+        .add_attribute("asset", asset.to_string())
+        .add_message(payout_msg(&asset.info, &info.sender, asset.amount)?))
+}
+
+// To create a stream we want verify a number of things before saving the stream info and starting some accrual process
+// 1. The recipient is a valid address
+// 2. The deposit is a valid amount
+// 3. The first entry in funds is a native token and the amount is non zero
+// 4. The start time is before the stop time
+// A stream without a start time is created as Pending and only starts once activated
+pub fn try_create_stream(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    deposit: Uint128,
+    token_addr: AssetInfoBase<Addr>,
+    mut stream_data: StreamData,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    // A recurring stream can be bounded by its number of periods instead of a stop time
+    if let Some(StreamType::Recurring(recurrence)) = &stream_data.stream_type {
+        if stream_data.stop_time.is_none() && stream_data.duration.is_none() {
+            stream_data.duration = recurrence.max_duration();
+        }
+    }
+    let (start_time, stop_time, pending_duration) = resolve_schedule(&env, &stream_data)?;
+
+    // Both ends of a stream have to be measured in the same unit, either block time or block height
+    if !start_time.same_unit(&stop_time) {
+        return Err(ContractError::MismatchedStreamUnits {});
+    }
+
+    // Get the time delta, for a pending stream this is how long it will run once activated
+    let duration = match pending_duration {
+        Some(Duration::Height(blocks)) => blocks,
+        Some(Duration::Time(seconds)) => seconds,
+        None => stop_time.value().saturating_sub(start_time.value()),
+    };
+    if duration == 0 {
+        return Err(ContractError::DeltaIssue {
+            start_time: start_time.value(),
+            stop_time: stop_time.value(),
+        });
+    }
+
+    // Unless stream_type is provided we will assume it is StreamType::Basic
+    let stream_type = stream_data.stream_type.unwrap_or(StreamType::Basic);
+
+    let stream_idx = LAST_STREAM_IDX.load(deps.storage)? + 1;
+    // Every stream records its average rate, for basic streams this also drives the accrual
+    let rate_per_second = curve_helpers::calc_rate_per_second(duration, Uint256::from(deposit))?;
+
+    let milestones = match &stream_type {
+        StreamType::Milestones(terms) => {
+            milestones::validate_milestones(deps.api, &start_time, deposit, terms)?
+        }
+        _ => vec![],
+    };
+    let recurrence = match &stream_type {
+        StreamType::Recurring(recurrence) => Some(recurrence.clone()),
+        _ => None,
+    };
+    // Only some stream types are supported right now
+    let curve = match stream_type {
+        StreamType::Basic => None,
+        StreamType::Recurring(recurrence) => {
+            if stream_data.curve.is_some() || recurrence.max_periods == Some(0) {
+                return Err(ContractError::InvalidSchedule {});
+            }
+            // Periods run on the same clock as the stream
+            if start_time.after(&recurrence.period).is_none() {
+                return Err(ContractError::MismatchedStreamUnits {});
+            }
+            if recurrence.period_length() == 0 {
+                return Err(ContractError::DivisionByZero {});
+            }
+            // The deposit has to pay for at least one period
+            if recurrence.amount_per_period.is_zero() || deposit < recurrence.amount_per_period {
+                return Err(ContractError::InvalidAmount {});
+            }
+            None
+        }
+        StreamType::LinearCurveBased => {
+            // Verify the provided curve is valid, in this case we want to make sure its the right curve type and its monotonically increasing
+            let curve = stream_data.curve.ok_or(ContractError::MissingCurve {})?;
+            curve.validate_monotonic_increasing()?;
+            curve_helpers::validate_curve(StreamType::LinearCurveBased, &curve)?;
+            match curve {
+                Curve::Constant { .. } | Curve::SaturatingLinear(_) => Some(curve),
+                _ => return Err(ContractError::Unauthorized {}),
+            }
+        }
+        StreamType::CliffCurveBased => {
+            let curve = stream_data.curve.ok_or(ContractError::MissingCurve {})?;
+            curve.validate_monotonic_increasing()?;
+            curve_helpers::validate_curve(StreamType::CliffCurveBased, &curve)?;
+            match curve {
+                Curve::PiecewiseLinear(_) => Some(curve),
+                _ => return Err(ContractError::Unauthorized {}),
+            }
+        }
+        StreamType::Milestones(_) => {
+            if stream_data.curve.is_some() {
+                return Err(ContractError::InvalidSchedule {});
+            }
+            None
+        }
+        _ => return Err(ContractError::Unauthorized {}),
+    };
+
+    let options = stream_data.options.unwrap_or_default();
+    let activator = options
+        .activator
+        .map(|activator| deps.api.addr_validate(&activator))
+        .transpose()?;
+    let cancel_policy = options.cancel_policy.unwrap_or_default();
+    if let CancelableBy::Admin(admin) = &cancel_policy.cancelable_by {
+        deps.api.addr_validate(admin.as_str())?;
+    }
+    // The notice period runs on the same clock as the stream
+    if let Some(notice_period) = &cancel_policy.notice_period {
+        start_time
+            .after(notice_period)
+            .ok_or(ContractError::MismatchedStreamUnits {})?;
+    }
+    let shares = validate_shares(deps.api, &recipient, options.shares)?;
+    // Fiat streams vest linearly and convert every payout on its own, which rules out anything moving the funds
+    let fiat = match options.fiat {
+        Some(fiat) => {
+            if curve.is_some()
+                || recurrence.is_some()
+                || !milestones.is_empty()
+                || !shares.is_empty()
+                || options.stake.is_some()
+                || options.vault.is_some()
+            {
+                return Err(ContractError::InvalidFiatStream {});
+            }
+            Some(fiat::validate_terms(
+                deps.as_ref(),
+                &env,
+                &token_addr,
+                fiat,
+            )?)
+        }
+        None => None,
+    };
+    let funded_from_balance = options.from_balance.unwrap_or(false);
+    let claim_deadline = options.claim_deadline;
+    // Without a deadline in the future the funds of a stream nobody accepts could never be reclaimed
+    let awaiting_acceptance = match options.requires_acceptance {
+        Some(true) => match options.acceptance_deadline {
+            Some(deadline)
+                if deadline != Expiration::Never {} && !deadline.is_expired(&env.block) =>
+            {
+                Some(deadline)
             }
+            _ => return Err(ContractError::InvalidAcceptanceDeadline {}),
+        },
+        _ => None,
+    };
+    if funded_from_balance {
+        update_sender_balance(deps.storage, &info.sender, &token_addr, |mut balance| {
+            balance.free = balance
+                .free
+                .checked_sub(deposit)
+                .map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+            balance.committed = balance.committed.checked_add(deposit)?;
+            Ok(balance)
+        })?;
+    }
+
+    let mut stream_data = PaymentStream {
+        stream_idx,
+        recipient: recipient.clone(),
+        deposit: deposit.into(),
+        token_addr,
+        start_time,
+        stop_time,
+        is_closed: false,
+        rate_per_second,
+        remaining_balance: deposit.into(),
+        sender: info.sender.clone(),
+        curve,
+        status: if pending_duration.is_some() || awaiting_acceptance.is_some() {
+            StreamStatus::Pending
+        } else {
+            StreamStatus::Active
+        },
+        activator,
+        pending_duration,
+        cancel_policy,
+        cancels_at: None,
+        sender_can_transfer: options.sender_can_transfer.unwrap_or(false),
+        claim_operator: None,
+        withdrawal_address: None,
+        notify_recipient: options.notify_recipient.unwrap_or(false),
+        recurrence,
+        awaiting_acceptance,
+        claim_deadline,
+        funded_from_balance,
+        shares,
+        staking: None,
+        vault: None,
+        fiat,
+        milestones,
+    };
+    let delegation = match options.stake {
+        Some(stake) => staking::delegate_stream(deps.branch(), &env, &mut stream_data, stake)?,
+        None => vec![],
+    };
+    let vault_deposit = match options.vault {
+        Some(vault) => vault::deposit_stream(deps.branch(), &env, &mut stream_data, vault)?,
+        None => vec![],
+    };
+
+    // Increment the stream count
+    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
+        state.count += 1;
+        Ok(state)
+    })?;
+    // Save the stream
+    save_stream(deps.storage, env.block.height, &stream_data)?;
+    for share in &stream_data.shares {
+        if share.recipient != stream_data.recipient {
+            SHARED_STREAMS.save(deps.storage, (&share.recipient, stream_idx), &Empty {})?;
+        }
+    }
+    // payment_streams().load(deps.storage, &stream_data.stream_idx.to_string())?;
+    // STREAMS.save(deps.storage, (&recipient, &info.sender), &stream_data)?;
+    LAST_STREAM_IDX.save(deps.storage, &stream_idx)?;
+
+    let hook = stream_hook(&stream_data, StreamEvent::Created { deposit })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "try_create_stream")
+        .add_messages(delegation)
+        .add_messages(vault_deposit)
+        .add_messages(hook))
+}
+
+// Saves a stream and moves the voting power of its holders along with what it still locks
+pub(crate) fn save_stream(
+    storage: &mut dyn Storage,
+    height: u64,
+    stream: &PaymentStream,
+) -> Result<(), ContractError> {
+    let key = stream.stream_idx.to_string();
+    let before = payment_streams().may_load(storage, &key)?;
+    payment_streams().save(storage, &key, stream)?;
+    voting::sync_voting_power(storage, height, before.as_ref(), Some(stream))
+}
+
+// Validates the recipients of a split stream, every share is positive, each recipient appears once,
+// the stream recipient is one of them and together they add up to the whole stream
+fn validate_shares(
+    api: &dyn Api,
+    recipient: &Addr,
+    shares: Option<Vec<(String, Decimal)>>,
+) -> Result<Vec<RecipientShare>, ContractError> {
+    let shares = match shares {
+        Some(shares) => shares,
+        None => return Ok(vec![]),
+    };
+    let mut validated: Vec<RecipientShare> = Vec::with_capacity(shares.len());
+    let mut total = Decimal::zero();
+    for (address, share) in shares {
+        let address = api.addr_validate(&address)?;
+        if share.is_zero() || validated.iter().any(|other| other.recipient == address) {
+            return Err(ContractError::InvalidShares {});
+        }
+        total = total.checked_add(share)?;
+        validated.push(RecipientShare {
+            recipient: address,
+            share,
+            claimed: Uint128::zero(),
+        });
+    }
+    if total != Decimal::one() || !validated.iter().any(|share| share.recipient == *recipient) {
+        return Err(ContractError::InvalidShares {});
+    }
+    Ok(validated)
+}
+
+// Works out the start and stop of a new stream from what was provided
+// Either both ends are given, a start and a duration, or only a duration in which case the stream is Pending
+// and its start and stop hold the creation block until it is activated
+fn resolve_schedule(
+    env: &Env,
+    stream_data: &StreamData,
+) -> Result<(StreamTime, StreamTime, Option<Duration>), ContractError> {
+    match (
+        stream_data.start_time,
+        stream_data.stop_time,
+        stream_data.duration,
+    ) {
+        (Some(start_time), Some(stop_time), None) => Ok((start_time, stop_time, None)),
+        (Some(start_time), None, Some(duration)) => {
+            let stop_time = start_time
+                .after(&duration)
+                .ok_or(ContractError::MismatchedStreamUnits {})?;
+            Ok((start_time, stop_time, None))
+        }
+        (None, None, Some(duration)) => {
+            let now = StreamTime::now(&duration, &env.block);
+            Ok((now, now, Some(duration)))
+        }
+        _ => Err(ContractError::InvalidSchedule {}),
+    }
+}
+
+// Starts a Pending stream, fixing its start and stop time as well as any curve which was given relative to the start
+pub fn activate_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    // Only the stream sender or the designated activator, which may be a contract hooking in, can activate a stream
+    if info.sender != stream.sender && Some(&info.sender) != stream.activator.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+    if stream.awaiting_acceptance.is_some() {
+        return Err(ContractError::AwaitingAcceptance { stream_idx });
+    }
+
+    let duration = match (&stream.status, stream.pending_duration) {
+        (StreamStatus::Pending, Some(duration)) => duration,
+        _ => return Err(ContractError::StreamNotPending {}),
+    };
+
+    let start_time = StreamTime::now(&duration, &env.block);
+    let stop_time = start_time
+        .after(&duration)
+        .ok_or(ContractError::MismatchedStreamUnits {})?;
+    // Curves of deferred streams are relative to the start, anchor them now that it is known
+    stream.curve = stream
+        .curve
+        .map(|curve| curve_helpers::shift_curve(curve, start_time.value()));
+    stream.start_time = start_time;
+    stream.stop_time = stop_time;
+    stream.status = StreamStatus::Active;
+    stream.pending_duration = None;
+
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "activate_stream")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("start_time", start_time.value().to_string())
+        .add_attribute("stop_time", stop_time.value().to_string()))
+}
+
+// The recipient agrees to receive a stream which required acceptance
+// A stream with a schedule starts right away following it, a deferred stream still has to be activated
+pub fn accept_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.recipient {
+        return Err(ContractError::Unauthorized {});
+    }
+    let deadline = stream
+        .awaiting_acceptance
+        .ok_or(ContractError::NotAwaitingAcceptance { stream_idx })?;
+    if deadline.is_expired(&env.block) {
+        return Err(ContractError::AcceptanceExpired {});
+    }
+
+    stream.awaiting_acceptance = None;
+    if stream.pending_duration.is_none() {
+        stream.status = StreamStatus::Active;
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_stream")
+        .add_attribute("stream_idx", stream_idx.to_string()))
+}
+
+// Returns the whole deposit of a stream nobody accepted in time to the sender and closes the stream
+pub fn reclaim_stream(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let deadline = stream
+        .awaiting_acceptance
+        .ok_or(ContractError::NotAwaitingAcceptance { stream_idx })?;
+    if !deadline.is_expired(&env.block) {
+        return Err(ContractError::AcceptanceOpen {});
+    }
+
+    let mut messages = vault::release(deps.branch(), &env, &mut stream)?;
+    let refund = Uint128::try_from(stream.remaining_balance)?;
+    messages.extend(release_stream_funds(
+        deps.storage,
+        &stream,
+        Uint128::zero(),
+        refund,
+    )?);
+    stream.remaining_balance = Uint256::zero();
+    stream.is_closed = true;
+    stream.status = StreamStatus::Cancelled {
+        cancelled_at: env.block.time,
+        recipient_amount: Uint128::zero(),
+        sender_amount: refund,
+    };
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "reclaim_stream")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("sender_amount", refund))
+}
+
+// Once the claim deadline of a stream passed the sender takes back everything the recipient did not claim,
+// vested or not, and the stream is closed
+pub fn sweep_expired(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !stream.claim_expired(&env.block) {
+        return Err(ContractError::ClaimDeadlineNotPassed { stream_idx });
+    }
 
-            if env.block.time > stream.start_time && info.sender == stream.sender {
+    let mut messages = vault::release(deps.branch(), &env, &mut stream)?;
+    let swept = Uint128::try_from(stream.remaining_balance)?;
+    messages.extend(release_stream_funds(
+        deps.storage,
+        &stream,
+        Uint128::zero(),
+        swept,
+    )?);
+    stream.remaining_balance = Uint256::zero();
+    stream.is_closed = true;
+    stream.status = StreamStatus::Cancelled {
+        cancelled_at: env.block.time,
+        recipient_amount: Uint128::zero(),
+        sender_amount: swept,
+    };
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "sweep_expired")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("sender_amount", swept))
+}
+
+// Cancels a stream following the cancel policy chosen when it was created
+// If the policy has a notice period the first call only schedules the cancel, accrual continues until the
+// notice period is over and the stream is settled by calling CancelStream again
+pub fn cancel_stream(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+
+    match stream.cancels_at {
+        None => {
+            if !stream.cancel_policy.can_cancel(&info.sender, &stream) {
                 return Err(ContractError::Unauthorized {});
             }
+            // With a notice period the stream keeps accruing until the notice is over, pending streams accrue nothing
+            // so they are settled right away
+            let notice_period = match stream.status {
+                StreamStatus::Active => stream.cancel_policy.notice_period,
+                _ => None,
+            };
+            if let Some(notice_period) = notice_period {
+                let now = StreamTime::now(&notice_period, &env.block);
+                let cancels_at = now
+                    .after(&notice_period)
+                    .ok_or(ContractError::MismatchedStreamUnits {})?;
+                stream.cancels_at = Some(cancels_at);
+                save_stream(deps.storage, env.block.height, &stream)?;
+
+                return Ok(Response::new()
+                    .add_attribute("method", "cancel_stream")
+                    .add_attribute("stream_idx", stream_idx.to_string())
+                    .add_attribute("cancels_at", cancels_at.value().to_string()));
+            }
+        }
+        Some(cancels_at) => {
+            // Once scheduled, either party or anyone allowed to cancel can settle the stream
+            if info.sender != stream.sender
+                && !stream.is_recipient(&info.sender)
+                && !stream.cancel_policy.can_cancel(&info.sender, &stream)
+            {
+                return Err(ContractError::Unauthorized {});
+            }
+            if cancels_at.current(&env.block) < cancels_at.value() {
+                return Err(ContractError::NoticePeriodNotOver {
+                    cancels_at: cancels_at.value(),
+                });
+            }
+        }
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let (recipient_amount, sender_amount) = if stream.staking.is_some() {
+        // Staked funds have to unbond before anyone is paid
+        let (settlement, recipient_amount, sender_amount) =
+            staking::defer_settlement(&mut deps, &env, &mut stream)?;
+        messages.extend(settlement);
+        (recipient_amount, sender_amount)
+    } else {
+        // Check it doesn't exceed available, a fiat stream settles what it owes at the oracle price or, with the
+        // oracle down past the grace period, at the last price seen
+        let available: Uint128 = match &stream.fiat {
+            Some(_) => {
+                let price = fiat::settlement_price(&deps.querier, &env, &stream)?;
+                let due = fiat::amount_due(&stream, &env, price)?;
+                fiat::record_payment(&mut stream, &env, due, price)?;
+                due
+            }
+            None => Uint128::try_from(
+                curve_helpers::avail_balance_of(stream.clone(), env.clone())
+                    .unwrap_or_else(|_| Uint256::zero()),
+            )?,
+        };
+        // Funds in a vault have to be back before anything is paid out, what the vault lost comes out of the part
+        // of the sender first
+        messages.extend(vault::release(deps.branch(), &env, &mut stream)?);
+        let available_bal_for_stream = stream.balance_up_to(available);
+        let refund = Uint128::try_from(
+            stream
+                .remaining_balance
+                .checked_sub(available_bal_for_stream.into())?,
+        )?;
+
+        if stream.shares.is_empty() {
+            if !available_bal_for_stream.is_zero() {
+                // Pay the available to the receipient
+                messages.push(payout_msg(
+                    &stream.token_addr,
+                    &stream.recipient,
+                    available_bal_for_stream,
+                )?);
+            }
+        } else {
+            // Every recipient of a split stream is paid what their share vested and they have not claimed yet
+            let mut left = available_bal_for_stream;
+            for position in 0..stream.shares.len() {
+                let owed = curve_helpers::share_available(&stream, &env, position)?.min(left);
+                left = left.checked_sub(owed)?;
+                if !owed.is_zero() {
+                    let share = &mut stream.shares[position];
+                    messages.push(payout_msg(&stream.token_addr, &share.recipient, owed)?);
+                    share.claimed = share.claimed.checked_add(owed)?;
+                }
+            }
+        }
+        // Pay the remaining to the sender
+        messages.extend(release_stream_funds(
+            deps.storage,
+            &stream,
+            available_bal_for_stream,
+            refund,
+        )?);
+        (available_bal_for_stream, refund)
+    };
+
+    // The stream is settled, record the outcome so nothing can be paid out of it a second time
+    stream.remaining_balance = Uint256::zero();
+    stream.is_closed = true;
+    stream.status = StreamStatus::Cancelled {
+        cancelled_at: env.block.time,
+        recipient_amount,
+        sender_amount,
+    };
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    let hook = stream_hook(
+        &stream,
+        StreamEvent::Cancelled {
+            recipient_amount,
+            sender_amount,
+        },
+    )?
+    .map(|hook| SubMsg::reply_on_error(hook, CANCEL_HOOK_REPLY_ID));
+
+    // Return response with messages
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hook)
+        .add_attribute("method", "cancel_stream")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("recipient_amount", recipient_amount)
+        .add_attribute("sender_amount", sender_amount))
+}
+
+// Adds `amount` to the deposit of a recurring stream, paying for more of its periods. It has to come before a
+// period starts without funds, a lapsed stream stays lapsed
+fn top_up_stream(
+    deps: DepsMut,
+    env: Env,
+    sender: &Addr,
+    mut stream: PaymentStream,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    ensure_open(&stream)?;
+    if *sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let recurrence = match &stream.recurrence {
+        // Delegated or vaulted funds are placed once at creation
+        Some(recurrence)
+            if stream.cancels_at.is_none()
+                && stream.staking.is_none()
+                && stream.vault.is_none() =>
+        {
+            recurrence.clone()
+        }
+        _ => return Err(ContractError::InvalidSchedule {}),
+    };
+    if curve_helpers::has_lapsed(&stream, &recurrence, &env) {
+        return Err(ContractError::StreamLapsed {
+            stream_idx: stream.stream_idx,
+        });
+    }
+    // Nothing can be added past what all scheduled periods cost
+    let scheduled = recurrence
+        .amount_per_period
+        .checked_mul(curve_helpers::scheduled_periods(&stream, &recurrence).into())?;
+    stream.deposit = stream.deposit.checked_add(amount.into())?;
+    if amount.is_zero() || stream.deposit > scheduled.into() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    stream.remaining_balance = stream.remaining_balance.checked_add(amount.into())?;
+    if stream.funded_from_balance {
+        update_sender_balance(
+            deps.storage,
+            &stream.sender,
+            &stream.token_addr,
+            |mut balance| {
+                balance.free = balance
+                    .free
+                    .checked_sub(amount)
+                    .map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+                balance.committed = balance.committed.checked_add(amount)?;
+                Ok(balance)
+            },
+        )?;
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "top_up_stream")
+        .add_attribute("stream_idx", stream.stream_idx.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute(
+            "funded_periods",
+            curve_helpers::funded_periods(&stream, &recurrence).to_string(),
+        ))
+}
+
+// Any operation on a cancelled stream is rejected
+pub fn ensure_open(stream: &PaymentStream) -> Result<(), ContractError> {
+    match stream.status {
+        StreamStatus::Cancelled { .. } => Err(ContractError::StreamClosed {
+            stream_idx: stream.stream_idx,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Builds the StreamReceiveMsg telling the recipient contract about an event, if the stream was set up to notify it
+fn stream_hook(
+    stream: &PaymentStream,
+    event: StreamEvent,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if !stream.notify_recipient {
+        return Ok(None);
+    }
+    let msg = StreamReceiveMsg {
+        stream_idx: stream.stream_idx,
+        sender: stream.sender.to_string(),
+        asset: stream.token_addr.clone(),
+        event,
+    };
+    Ok(Some(msg.into_cosmos_msg(stream.recipient.clone())?))
+}
+
+// Builds the message paying out `amount` of a streamed asset into a contract along with a callback message.
+// Native funds are attached to a call to the contract, CW20 tokens use the CW20 Send hook
+pub fn send_msg(
+    asset: &AssetInfo,
+    contract: &Addr,
+    amount: Uint128,
+    msg: Binary,
+) -> Result<CosmosMsg, ContractError> {
+    match asset {
+        AssetInfo::Native(denom) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg,
+            funds: vec![Coin {
+                denom: denom.clone(),
+                amount,
+            }],
+        })),
+        AssetInfo::Cw20(contract_addr) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: contract.to_string(),
+                amount,
+                msg,
+            })?,
+            funds: vec![],
+        })),
+        _ => Err(ContractError::UnsupportedAsset {}),
+    }
+}
+
+// Builds the message paying out `amount` of a streamed asset, native or CW20
+pub fn payout_msg(
+    asset: &AssetInfo,
+    to: &Addr,
+    amount: Uint128,
+) -> Result<CosmosMsg, ContractError> {
+    match asset {
+        AssetInfo::Native(denom) => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: to.to_string(),
+            amount: vec![Coin {
+                denom: denom.clone(),
+                amount,
+            }],
+        })),
+        AssetInfo::Cw20(contract_addr) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+        _ => Err(ContractError::UnsupportedAsset {}),
+    }
+}
+
+pub fn claim_from_stream(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    amount: Uint128,
+    stream_idx: Option<u64>,
+    to: Option<String>,
+    msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    // Check amount is valid
+    if amount == Uint128::zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let mut paystream: PaymentStream = match stream_idx {
+        Some(stream_idx) => payment_streams().load(deps.storage, &stream_idx.to_string())?,
+        // Without an index claim from the first open stream of the caller
+        None => payment_streams()
+            .idx
+            .recipient
+            .prefix(info.sender.to_string())
+            .range(deps.storage, None, None, Order::Ascending)
+            .flat_map(|vc| Ok::<PaymentStream, ContractError>(vc?.1))
+            .find(|stream| ensure_open(stream).is_ok() && !stream.remaining_balance.is_zero())
+            .ok_or(ContractError::StreamNotFound {})?,
+    };
+    ensure_open(&paystream)?;
+    if paystream.claim_expired(&env.block) {
+        return Err(ContractError::ClaimDeadlinePassed {
+            stream_idx: paystream.stream_idx,
+        });
+    }
+    // On a split stream every recipient claims their own share
+    let share = paystream
+        .shares
+        .iter()
+        .position(|share| share.recipient == info.sender);
+    // Only the recipient or their claim operator can perform a claim from stream
+    if share.is_none()
+        && (!paystream.shares.is_empty() || !can_claim(&paystream, &info.sender, &env))
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Only the recipient decides where funds go, an operator always pays the recipient or their withdrawal address
+    if (to.is_some() || msg.is_some()) && !paystream.is_recipient(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Fiat streams are paid at the oracle price, the claim fails while there is no usable price
+    let price = match &paystream.fiat {
+        Some(terms) => Some(fiat::price(
+            &deps.querier,
+            &env,
+            &paystream.token_addr,
+            terms,
+        )?),
+        None => None,
+    };
+    // Check it doesn't exceed available
+    let available_bal_for_stream: Uint256 = match (share, price) {
+        (Some(position), _) => curve_helpers::share_available(&paystream, &env, position)?.into(),
+        (None, Some(price)) => fiat::amount_due(&paystream, &env, price)?.into(),
+        (None, None) => curve_helpers::avail_balance_of(paystream.clone(), env.clone())
+            .unwrap_or_else(|_| Uint256::zero()),
+    };
+
+    // If they requested more than is available from this stream
+    if Uint256::from(amount) > available_bal_for_stream {
+        return Err(ContractError::NotEnoughAvailableBalance {});
+    }
+    // If they requested more than is remaining from the total stream
+    if Uint256::from(amount) > paystream.remaining_balance {
+        return Err(ContractError::NotEnoughAvailableFunds {});
+    }
+
+    // Make the payout happen, by default to the withdrawal address if one was set or else to the recipient
+    let to = match (to, share) {
+        (Some(to), _) => deps.api.addr_validate(&to)?,
+        (None, Some(_)) => info.sender.clone(),
+        (None, None) => paystream
+            .withdrawal_address
+            .clone()
+            .unwrap_or_else(|| paystream.recipient.clone()),
+    };
+    if let Some(price) = price {
+        fiat::record_payment(&mut paystream, &env, amount, price)?;
+    }
+    // Funds deposited in a vault come back right before they are paid out, what the vault lost is written off
+    // first and only what is left can be paid
+    let (pull, lost) = vault::pull(deps.branch(), &env, &mut paystream, amount)?;
+    let paid = paystream.balance_up_to(amount);
+    let shortfall = amount.checked_sub(paid)?;
+    if let Some(position) = share {
+        let share = &mut paystream.shares[position];
+        share.claimed = share.claimed.checked_add(paid)?;
+    }
+    release_stream_funds(deps.storage, &paystream, paid, Uint128::zero())?;
+    let payout = match msg {
+        _ if paid.is_zero() => None,
+        Some(msg) => Some(send_msg(&paystream.token_addr, &to, paid, msg)?),
+        None => Some(payout_msg(&paystream.token_addr, &to, paid)?),
+    };
+    if Uint256::from(paid) == paystream.remaining_balance {
+        // If the amount requested is the same as the remaining balance, delete the stream
+
+        paystream.remaining_balance = Uint256::zero();
+        paystream.is_closed = true;
+    } else {
+        paystream.remaining_balance = paystream.remaining_balance.checked_sub(paid.into())?;
+    }
+
+    STREAMS.save(
+        deps.storage,
+        (&paystream.sender, &paystream.recipient),
+        &paystream,
+    )?;
+    save_stream(deps.storage, env.block.height, &paystream)?;
 
-            // Check it doesn't exceed available
-            let available_bal_for_stream: Uint128 =
-                curve_helpers::avail_balance_of(stream.clone(), env)
-                    .unwrap_or_else(|_| Uint128::zero());
+    let hook = stream_hook(
+        &paystream,
+        StreamEvent::Claimed {
+            amount: paid,
+            to: to.to_string(),
+        },
+    )?;
 
-            let mut messages: Vec<CosmosMsg> = vec![];
-            let denom = match stream.token_addr {
-                AssetInfo::Native(denom) => denom,
-                _ => unimplemented!(),
-            };
-            if available_bal_for_stream > Uint128::zero() {
-                // Pay the available to the receipient
-                let payout_msg: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
-                    to_address: stream.recipient.to_string(),
-                    amount: vec![Coin {
-                        denom: denom.clone(),
-                        amount: available_bal_for_stream,
-                    }],
-                });
-                messages.push(payout_msg);
-            }
-            // Pay the remaining to the sender
-            let payout_msg: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
-                to_address: stream.sender.to_string(),
-                amount: vec![Coin {
-                    denom,
-                    amount: stream
-                        .remaining_balance
-                        .checked_sub(available_bal_for_stream)?,
-                }],
-            });
-            messages.push(payout_msg);
+    let mut response = Response::new()
+        .add_attribute("method", "try_withdraw_from_stream")
+        .add_attribute("to", to)
+        .add_messages(pull)
+        .add_messages(payout)
+        .add_messages(hook);
+    if !lost.is_zero() {
+        response = response.add_attribute("vault_loss", lost);
+    }
+    if !shortfall.is_zero() {
+        response = response.add_attribute("vault_shortfall", shortfall);
+    }
+    Ok(response)
+}
 
-            // Return response with messages
-            Ok(Response::new()
-                .add_messages(messages)
-                .add_attribute("method", "cancel_stream"))
-        }
+// The recipient can always claim, an operator only until its authorization expires
+fn can_claim(stream: &PaymentStream, who: &Addr, env: &Env) -> bool {
+    if *who == stream.recipient {
+        return true;
+    }
+    match &stream.claim_operator {
+        Some(operator) => operator.address == *who && !operator.expires.is_expired(&env.block),
+        None => false,
     }
 }
 
-// receive_cw20 routes a cw20 token to the proper handler in this case stake and unstake
-fn receive_cw20(
+// Moves a stream to a new recipient, the recipient can always do this and the sender only if allowed at creation
+pub fn transfer_stream(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    cw20_msg: Cw20ReceiveMsg,
+    stream_idx: u64,
+    new_recipient: String,
 ) -> Result<Response, ContractError> {
-    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
-
-    match from_binary(&cw20_msg.msg)? {
-        Cw20HookMsg::CreateStream {
-            recipient,
-            start_time,
-            stop_time,
-            stream_type,
-            curve,
-        } => {
-            if cw20_msg.amount.is_zero() {
-                return Err(ContractError::InvalidAmount {});
-            }
-            let asset = AssetInfo::Cw20(info.sender.clone());
-            try_create_stream(
-                deps,
-                info,
-                recipient,
-                cw20_msg.amount,
-                asset,
-                StreamData {
-                    start_time: Timestamp::from_seconds(start_time),
-                    stop_time: Timestamp::from_seconds(stop_time),
-                    stream_type: stream_type,
-                    curve: curve,
-                },
-            )
-        }
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    // Each share holder of a split stream manages their own claims
+    if !stream.shares.is_empty() {
+        return Err(ContractError::SplitStream { stream_idx });
+    }
+    if info.sender != stream.recipient
+        && !(info.sender == stream.sender && stream.sender_can_transfer)
+    {
+        return Err(ContractError::Unauthorized {});
     }
+
+    let new_recipient = deps.api.addr_validate(&new_recipient)?;
+    let old_recipient = std::mem::replace(&mut stream.recipient, new_recipient.clone());
+    // Operator, withdrawal address and notifications were chosen for the previous recipient, the new one may not
+    // implement the receive hook at all
+    stream.claim_operator = None;
+    stream.withdrawal_address = None;
+    stream.notify_recipient = false;
+    // Saving through the IndexedMap moves the stream in the recipient index as well
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_stream")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("old_recipient", old_recipient)
+        .add_attribute("new_recipient", new_recipient))
 }
 
-// To create a stream we want verify a number of things before saving the stream info and starting some accrual process
-// 1. The recipient is a valid address
-// 2. The deposit is a valid amount
-// 3. The first entry in funds is a native token and the amount is non zero
-// 4. The start time is before the stop time
-pub fn try_create_stream(
+// Lets the recipient authorize an address, such as a bot, to claim on their behalf
+pub fn set_claim_operator(
     deps: DepsMut,
     info: MessageInfo,
-    recipient: String,
-    deposit: Uint128,
-    token_addr: AssetInfoBase<Addr>,
-    stream_data: StreamData,
+    stream_idx: u64,
+    operator: Option<String>,
+    expires: Option<Expiration>,
 ) -> Result<Response, ContractError> {
-    let recipient = deps.api.addr_validate(&recipient)?;
-    let start_time = stream_data.start_time.seconds();
-    let stop_time = stream_data.stop_time.seconds();
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    // Each share holder of a split stream manages their own claims
+    if !stream.shares.is_empty() {
+        return Err(ContractError::SplitStream { stream_idx });
+    }
+    if info.sender != stream.recipient {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    if stop_time <= start_time {
-        return Err(ContractError::DeltaIssue {
-            start_time: start_time,
-            stop_time: stop_time,
-        });
+    stream.claim_operator = operator
+        .map(|operator| -> Result<_, ContractError> {
+            Ok(ClaimOperator {
+                address: deps.api.addr_validate(&operator)?,
+                expires: expires.unwrap_or_default(),
+            })
+        })
+        .transpose()?;
+    payment_streams().save(deps.storage, &stream_idx.to_string(), &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_claim_operator")
+        .add_attribute("stream_idx", stream_idx.to_string()))
+}
+
+// Lets the recipient route claimed funds to another address
+pub fn set_withdrawal_address(
+    deps: DepsMut,
+    info: MessageInfo,
+    stream_idx: u64,
+    address: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    // Each share holder of a split stream manages their own claims
+    if !stream.shares.is_empty() {
+        return Err(ContractError::SplitStream { stream_idx });
+    }
+    if info.sender != stream.recipient {
+        return Err(ContractError::Unauthorized {});
     }
 
-    // Get the time delta
-    let duration = stop_time - start_time;
+    stream.withdrawal_address = address
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    payment_streams().save(deps.storage, &stream_idx.to_string(), &stream)?;
 
-    // Unless stream_type is provided we will assume it is StreamType::Basic
-    let stream_type = stream_data.stream_type.unwrap_or(StreamType::Basic);
+    Ok(Response::new()
+        .add_attribute("method", "set_withdrawal_address")
+        .add_attribute("stream_idx", stream_idx.to_string()))
+}
 
-    let stream_idx = LAST_STREAM_IDX.load(deps.storage)? + 1;
+// Lets the recipient hand part of their future vesting to someone else, e.g. a co-founder or an escrow
+// The original stream keeps what vested so far plus the rest of its schedule, the new stream vests the carved part
+// under the same schedule shape
+pub fn split_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+    amount_or_ratio: AmountOrRatio,
+    new_recipient: String,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if !stream.shares.is_empty() {
+        return Err(ContractError::SplitStream { stream_idx });
+    }
+    if info.sender != stream.recipient {
+        return Err(ContractError::Unauthorized {});
+    }
+    // The schedule of a pending stream or one being cancelled is not final yet, recurring streams vest in
+    // whole periods which can not be carved up and delegations or vault positions are not split
+    if stream.status != StreamStatus::Active
+        || stream.cancels_at.is_some()
+        || stream.recurrence.is_some()
+        || stream.staking.is_some()
+        || stream.vault.is_some()
+        || stream.fiat.is_some()
+        || !stream.milestones.is_empty()
+    {
+        return Err(ContractError::InvalidSchedule {});
+    }
+    let new_recipient = deps.api.addr_validate(&new_recipient)?;
 
-    // Only stream type is supported right now
-    let stream_data = match stream_type {
-        StreamType::Basic => {
-            // Calculate rate_per_second with error handling
-            let rate_per_second = curve_helpers::calc_rate_per_second(duration, deposit).unwrap();
-            let stream_data = PaymentStream {
-                stream_idx,
-                recipient: recipient.clone(),
-                deposit,
-                token_addr: token_addr,
-                start_time: stream_data.start_time,
-                stop_time: stream_data.stop_time,
-                is_closed: false,
-                rate_per_second,
-                remaining_balance: deposit,
-                sender: info.sender.clone(),
-                curve: None,
-            };
-            stream_data
-        }
-        StreamType::LinearCurveBased => {
-            // Verify the provided curve is valid, in this case we want to make sure its the right curve type and its monotonically increasing
-            let curve = stream_data.curve.unwrap();
-            curve.validate_monotonic_increasing()?;
-            curve_helpers::validate_curve(StreamType::LinearCurveBased, &curve)?;
-            match curve.clone() {
-                Curve::Constant { y } => {
-                    // We can get rate per second in the case of a constant curve by dividing the deposit by the duration
-                    let rate_per_second = y;
-                    let stream_data = PaymentStream {
-                        stream_idx,
-                        recipient: recipient.clone(),
-                        deposit,
-                        token_addr,
-                        start_time: stream_data.start_time,
-                        stop_time: stream_data.stop_time,
-                        is_closed: false,
-                        rate_per_second,
-                        remaining_balance: deposit,
-                        sender: info.sender.clone(),
-                        curve: Some(curve),
-                    };
-                    println!("Stream created: {:?}", stream_data);
-                    stream_data
-                }
-                Curve::SaturatingLinear(s) => {
-                    // We can get rate per second in the case of a constant curve by dividing the deposit by the duration
-                    let rate_per_second = s.max_y - s.min_y;
-                    let stream_data = PaymentStream {
-                        stream_idx,
-                        recipient: recipient.clone(),
-                        deposit,
-                        token_addr,
-                        start_time: stream_data.start_time,
-                        stop_time: stream_data.stop_time,
-                        is_closed: false,
-                        rate_per_second,
-                        remaining_balance: deposit,
-                        sender: info.sender.clone(),
-                        curve: Some(curve),
-                    };
-                    println!("Stream created: {:?}", stream_data);
-                    stream_data
-                }
-                _ => {
-                    return Err(ContractError::Unauthorized {});
-                }
-            }
-        }
-        StreamType::CliffCurveBased => {
-            let curve = stream_data.curve.unwrap();
-            curve.validate_monotonic_increasing()?;
-            curve_helpers::validate_curve(StreamType::CliffCurveBased, &curve)?;
-            match curve.clone(){
-                Curve::PiecewiseLinear(p) => {
-                    // We can get rate per second in the case of a constant curve by dividing the deposit by the duration
-                    let stream_data = PaymentStream {
-                        stream_idx,
-                        recipient: recipient.clone(),
-                        deposit,
-                        token_addr,
-                        start_time: stream_data.start_time,
-                        stop_time: stream_data.stop_time,
-                        is_closed: false,
-                        rate_per_second: 0u128.into(),
-                        remaining_balance: deposit,
-                        sender: info.sender.clone(),
-                        curve: Some(curve),
-                    };
-                    println!("Stream created: {:?}", stream_data);
-                    stream_data
-                }
-                _ => {
-                    return Err(ContractError::Unauthorized {});
-                }
+    let now = stream.start_time.current(&env.block);
+    let amount = match amount_or_ratio {
+        AmountOrRatio::Amount(amount) => amount,
+        AmountOrRatio::Ratio(ratio) => {
+            if ratio > Decimal::one() {
+                return Err(ContractError::InvalidAmount {});
             }
+            curve_helpers::unvested_at(&stream, now)? * ratio
         }
-        _ => return Err(ContractError::Unauthorized {}),
+    };
+    let (kept, carved) = curve_helpers::split_schedule(&stream, now, amount)?;
+
+    let split_idx = LAST_STREAM_IDX.load(deps.storage)? + 1;
+    let start_time = stream.start_time.at(now.max(stream.start_time.value()));
+    let split = PaymentStream {
+        stream_idx: split_idx,
+        deposit: amount.into(),
+        rate_per_second: curve_helpers::calc_rate_per_second(
+            stream
+                .stop_time
+                .value()
+                .saturating_sub(start_time.value())
+                .max(1),
+            Uint256::from(amount),
+        )?,
+        remaining_balance: amount.into(),
+        start_time,
+        recipient: new_recipient.clone(),
+        is_closed: false,
+        curve: Some(carved),
+        activator: None,
+        claim_operator: None,
+        withdrawal_address: None,
+        notify_recipient: false,
+        ..stream.clone()
     };
 
-    // Increment the stream count
+    stream.deposit = stream.deposit.checked_sub(amount.into())?;
+    stream.remaining_balance = stream.remaining_balance.checked_sub(amount.into())?;
+    stream.rate_per_second = curve_helpers::calc_rate_per_second(
+        stream.stop_time.value() - stream.start_time.value(),
+        stream.deposit,
+    )?;
+    stream.curve = Some(kept);
+
     STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
         state.count += 1;
         Ok(state)
     })?;
-    // Save the stream
-    payment_streams().save(
-        deps.storage,
-        stream_data.stream_idx.to_string().as_ref(),
-        &stream_data,
-    )?;
-    // payment_streams().load(deps.storage, &stream_data.stream_idx.to_string())?;
-    // STREAMS.save(deps.storage, (&recipient, &info.sender), &stream_data)?;
-    LAST_STREAM_IDX.save(deps.storage, &stream_idx)?;
+    save_stream(deps.storage, env.block.height, &stream)?;
+    save_stream(deps.storage, env.block.height, &split)?;
+    LAST_STREAM_IDX.save(deps.storage, &split_idx)?;
 
-    Ok(Response::new().add_attribute("method", "try_create_stream"))
+    Ok(Response::new()
+        .add_attribute("method", "split_stream")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("new_stream_idx", split_idx.to_string())
+        .add_attribute("new_recipient", new_recipient)
+        .add_attribute("amount", amount))
 }
 
-pub fn claim_from_stream(
+// Lets the recipient fold streams from the same sender, such as many small top ups, into a single stream
+// The first stream is kept and vests the sum of all of them, the others are removed
+pub fn merge_streams(
     deps: DepsMut,
-    info: MessageInfo,
     env: Env,
-    recipient: String,
-    amount: Uint128,
-    denom: String,
-    stream_idx: Option<u64>,
+    info: MessageInfo,
+    stream_idxs: Vec<u64>,
 ) -> Result<Response, ContractError> {
-    let recipient = deps.api.addr_validate(&recipient)?;
-
-    // Check amount is valid
-    if amount == Uint128::zero() {
-        return Err(ContractError::InvalidAmount {});
+    let mut unique = stream_idxs.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if stream_idxs.len() < 2 || unique.len() != stream_idxs.len() {
+        return Err(ContractError::IncompatibleStreams {});
     }
-    // let stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
-    let mut paystream: PaymentStream = if let Some(stream_idx) = stream_idx {
-        payment_streams().load(deps.storage, &stream_idx.to_string())?
-    } else {
-        STREAMS.load(deps.storage, (&recipient, &info.sender))?
-    };
-    // Only the recipient can perform a claim from stream
-    if info.sender != paystream.recipient {
+    let streams = stream_idxs
+        .iter()
+        .map(|stream_idx| payment_streams().load(deps.storage, &stream_idx.to_string()))
+        .collect::<StdResult<Vec<PaymentStream>>>()?;
+    let first = &streams[0];
+    if info.sender != first.recipient {
         return Err(ContractError::Unauthorized {});
     }
+    for stream in &streams {
+        ensure_open(stream)?;
+        // Merging must not change who gets paid, what or under which terms
+        if stream.sender != first.sender
+            || stream.recipient != first.recipient
+            || stream.token_addr != first.token_addr
+            || stream.cancel_policy != first.cancel_policy
+            || stream.claim_deadline != first.claim_deadline
+            || stream.sender_can_transfer != first.sender_can_transfer
+            || stream.notify_recipient != first.notify_recipient
+            || stream.awaiting_acceptance.is_some()
+            || !stream.start_time.same_unit(&first.start_time)
+            || stream.status != StreamStatus::Active
+            || stream.cancels_at.is_some()
+            || stream.recurrence.is_some()
+            || stream.funded_from_balance != first.funded_from_balance
+            || !stream.shares.is_empty()
+            || stream.staking.is_some()
+            || stream.vault.is_some()
+            || stream.fiat.is_some()
+            || !stream.milestones.is_empty()
+        {
+            return Err(ContractError::IncompatibleStreams {});
+        }
+    }
 
-    // Check it doesn't exceed available
-    let available_bal_for_stream: Uint128 =
-        curve_helpers::avail_balance_of(paystream.clone(), env).unwrap_or_else(|_| Uint128::zero());
-
-    // If they requested more than is available from this stream
-    println!(
-        "Amount: {:?}, Available: {:?}",
-        amount, available_bal_for_stream
-    );
-    if amount > available_bal_for_stream {
-        return Err(ContractError::NotEnoughAvailableBalance {});
+    let curve = curve_helpers::merge_schedules(&streams)?;
+    let mut merged = first.clone();
+    merged.deposit = Uint256::zero();
+    merged.remaining_balance = Uint256::zero();
+    for stream in &streams {
+        merged.deposit = merged.deposit.checked_add(stream.deposit)?;
+        merged.remaining_balance = merged
+            .remaining_balance
+            .checked_add(stream.remaining_balance)?;
+        if stream.start_time.value() < merged.start_time.value() {
+            merged.start_time = stream.start_time;
+        }
+        if stream.stop_time.value() > merged.stop_time.value() {
+            merged.stop_time = stream.stop_time;
+        }
     }
-    // If they requested more than is remaining from the total stream
-    if amount > paystream.remaining_balance {
-        return Err(ContractError::NotEnoughAvailableFunds {});
+    merged.rate_per_second = curve_helpers::calc_rate_per_second(
+        merged.stop_time.value() - merged.start_time.value(),
+        merged.deposit,
+    )?;
+    merged.curve = Some(curve);
+    merged.is_closed = merged.remaining_balance.is_zero();
+
+    save_stream(deps.storage, env.block.height, &merged)?;
+    for stream in &streams[1..] {
+        payment_streams().remove(deps.storage, &stream.stream_idx.to_string())?;
+        voting::sync_voting_power(deps.storage, env.block.height, Some(stream), None)?;
     }
+    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
+        state.count -= (streams.len() - 1) as i32;
+        Ok(state)
+    })?;
 
-    // Make the payout happen
-    let payout_msg: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: recipient.to_string(),
-        amount: vec![Coin { denom, amount }],
-    });
-    if amount == paystream.remaining_balance {
-        // If the amount requested is the same as the remaining balance, delete the stream
+    Ok(Response::new()
+        .add_attribute("method", "merge_streams")
+        .add_attribute("stream_idx", merged.stream_idx.to_string())
+        .add_attribute("merged", (streams.len() - 1).to_string()))
+}
 
-        paystream.remaining_balance = 0u128.into();
-        paystream.is_closed = true;
-    } else {
-        paystream.remaining_balance = paystream.remaining_balance.checked_sub(amount)?;
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        // The recipient contract failed to handle the cancel, its state was reverted but the cancel stands
+        CANCEL_HOOK_REPLY_ID => Ok(Response::new().add_attribute("cancel_hook", "failed")),
+        ibc_hooks::IBC_RETURN_REPLY_ID => ibc_hooks::register_return(deps, msg),
+        ibc::STREAM_PACKET_REPLY_ID => ibc::stream_packet_failed(msg),
+        _ => Err(ContractError::Unauthorized {}),
     }
+}
 
-    STREAMS.save(deps.storage, (&recipient, &info.sender), &paystream)?;
-    payment_streams().save(
-        deps.storage,
-        paystream.stream_idx.to_string().as_ref(),
-        &paystream,
-    )?;
-
-    Ok(Response::new()
-        .add_attribute("method", "try_withdraw_from_stream")
-        .add_message(payout_msg))
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => ibc_hooks::ibc_lifecycle_complete(deps, msg),
+    }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -444,9 +1776,52 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::StreamClaimableAmount { index } => {
             to_binary(&query_stream_amount_claimable(deps, env, index)?)
         }
+        QueryMsg::SenderBalance { sender, asset } => {
+            to_binary(&query_sender_balance(deps, sender, asset)?)
+        }
+        QueryMsg::Airdrop { airdrop_id } => to_binary(&airdrop::query_airdrop(deps, airdrop_id)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::VotingPowerAtHeight { address, height } => to_binary(
+            &voting::query_voting_power_at_height(deps, env, address, height)?,
+        ),
+        QueryMsg::TotalPowerAtHeight { height } => {
+            to_binary(&voting::query_total_power_at_height(deps, env, height)?)
+        }
+        QueryMsg::Dao {} => to_binary(&STATE.load(deps.storage)?.owner),
+        QueryMsg::Info {} => to_binary(&InfoResponse {
+            info: get_contract_version(deps.storage)?,
+        }),
+        QueryMsg::AllowedVaults {} => to_binary(&vault::query_allowed_vaults(deps)?),
+        QueryMsg::FiatStatus { stream_idx } => {
+            to_binary(&fiat::query_fiat_status(deps, env, stream_idx)?)
+        }
     }
 }
 
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: state.owner,
+        fee_asset: state.fee_asset,
+        fees: state.fees,
+    })
+}
+
+fn query_sender_balance(
+    deps: Deps,
+    sender: String,
+    asset: AssetInfo,
+) -> StdResult<SenderBalanceResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let balance = SENDER_BALANCES
+        .may_load(deps.storage, (&sender, &asset.to_string()))?
+        .unwrap_or_default();
+    Ok(SenderBalanceResponse {
+        free: balance.free,
+        committed: balance.committed,
+    })
+}
+
 fn query_stream_count(deps: Deps) -> StdResult<CountResponse> {
     let state = STATE.load(deps.storage)?;
     Ok(CountResponse { count: state.count })
@@ -467,15 +1842,31 @@ pub fn query_streams_by_recipient(
     limit: Option<Uint128>,
 ) -> StdResult<StreamsResponse> {
     let _vld_payee = deps.api.addr_validate(&payee)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT_FOR_QUERY).u128() as usize;
 
-    let streams: Vec<PaymentStream> = payment_streams()
+    let mut streams: Vec<PaymentStream> = payment_streams()
         .idx
         .recipient
         .prefix(payee)
         .range(deps.storage, None, None, order)
-        .take(limit.unwrap_or(DEFAULT_LIMIT_FOR_QUERY).u128() as usize)
+        .take(limit)
         .flat_map(|vc| Ok::<PaymentStream, ContractError>(vc?.1))
         .collect();
+    // Split streams where the payee holds a share without being the stream recipient
+    let shared = SHARED_STREAMS
+        .prefix(&_vld_payee)
+        .keys(deps.storage, None, None, order)
+        .take(limit)
+        .map(|stream_idx| payment_streams().load(deps.storage, &stream_idx?.to_string()))
+        .collect::<StdResult<Vec<PaymentStream>>>()?;
+    if !shared.is_empty() {
+        streams.extend(shared);
+        streams.sort_by_key(|stream| stream.stream_idx);
+        if order == Order::Descending {
+            streams.reverse();
+        }
+        streams.truncate(limit);
+    }
 
     Ok(StreamsResponse { streams })
 }
@@ -517,24 +1908,30 @@ pub fn query_stream_amount_claimable(
 ) -> StdResult<StreamClaimableAmtResponse> {
     // Get 1 from payment_streams
     let stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
-    // Get the time delta
-    let delta = curve_helpers::delta(stream.clone(), env.clone())?;
-    // Use delta to get the balance that should be available
-    let rec_bal = Uint128::from(delta).checked_mul(stream.rate_per_second)?;
-    // println!("Delta: {:?}, Recipient Balance: {:?}", delta, rec_bal);
     // Check it doesn't exceed available
-    let available_bal_for_stream: Uint128 =
-        curve_helpers::avail_balance_of(stream.clone(), env).unwrap_or_else(|_| Uint128::zero());
+    let available_bal_for_stream: Uint256 = match &stream.fiat {
+        Some(terms) => fiat::price(&deps.querier, &env, &stream.token_addr, terms)
+            .and_then(|price| fiat::amount_due(&stream, &env, price))
+            .map_or_else(|_| Uint256::zero(), Uint256::from),
+        None => curve_helpers::avail_balance_of(stream.clone(), env.clone())
+            .unwrap_or_else(|_| Uint256::zero()),
+    };
+    let amount_sweepable = if ensure_open(&stream).is_ok() && stream.claim_expired(&env.block) {
+        stream.remaining_balance
+    } else {
+        Uint256::zero()
+    };
 
     let streamed_balance = stream.deposit.checked_sub(stream.remaining_balance)?;
     Ok(StreamClaimableAmtResponse {
+        claim_deadline: stream.claim_deadline,
         stream,
         amount_available: available_bal_for_stream,
         amount_streamed: streamed_balance,
+        amount_sweepable,
     })
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -546,7 +1943,10 @@ mod tests {
     fn proper_initialization() {
         let mut deps = mock_dependencies();
 
-        let msg = InstantiateMsg { count: 0 };
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
         let info = mock_info("creator", &coins(1000, "earth"));
 
         // we can just call .unwrap() to assert this was a success
@@ -563,7 +1963,10 @@ mod tests {
     fn can_create_stream() {
         let mut deps = mock_dependencies();
 
-        let msg = InstantiateMsg { count: 0 };
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
         let info = mock_info("creator", &coins(1000, "earth"));
 
         // we can just call .unwrap() to assert this was a success
@@ -588,10 +1991,12 @@ mod tests {
                 info: AssetInfo::Native("axlusdc".to_string()),
             },
             recipient: payee.sender.to_string(),
-            start_time: env.block.time.seconds(),
-            stop_time: env.block.time.plus_seconds(100).seconds(),
+            start_time: Some(StreamTime::Time(env.block.time)),
+            stop_time: Some(StreamTime::Time(env.block.time.plus_seconds(100))),
+            duration: None,
             stream_type: None,
             curve: None,
+            options: None,
         };
 
         let _ = execute(deps.as_mut(), env.clone(), payer.clone(), stream_msg).unwrap();
@@ -608,7 +2013,10 @@ mod tests {
     fn can_not_create_with_deposit_more_than_provided_funds() {
         let mut deps = mock_dependencies();
 
-        let msg = InstantiateMsg { count: 0 };
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
         let info = mock_info("creator", &coins(1000, "earth"));
 
         // we can just call .unwrap() to assert this was a success
@@ -636,10 +2044,12 @@ mod tests {
                 info: AssetInfo::Native("axlusdc".to_string()),
             },
             recipient: payee.sender.to_string(),
-            start_time: env.block.time.seconds(),
-            stop_time: env.block.time.plus_seconds(100).seconds(),
+            start_time: Some(StreamTime::Time(env.block.time)),
+            stop_time: Some(StreamTime::Time(env.block.time.plus_seconds(100))),
+            duration: None,
             stream_type: None,
             curve: None,
+            options: None,
         };
 
         // We need this to unwrap as an error
@@ -652,10 +2062,12 @@ mod tests {
                 info: AssetInfo::Native("axlusdc".to_string()),
             },
             recipient: payee.sender.to_string(),
-            start_time: env.block.time.seconds(),
-            stop_time: env.block.time.plus_seconds(100).seconds(),
+            start_time: Some(StreamTime::Time(env.block.time)),
+            stop_time: Some(StreamTime::Time(env.block.time.plus_seconds(100))),
+            duration: None,
             stream_type: None,
             curve: None,
+            options: None,
         };
         // No issue
         let _ = execute(deps.as_mut(), env.clone(), payer.clone(), stream_msg).unwrap();
@@ -673,7 +2085,10 @@ mod tests {
     fn can_withdraw_from_a_created_stream_happy_and_sad_path() {
         let mut deps = mock_dependencies();
 
-        let msg = InstantiateMsg { count: 0 };
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
         // Creator user will create the stream
         let info = mock_info("creator", &coins(1000, "earth"));
 
@@ -700,10 +2115,12 @@ mod tests {
                 info: AssetInfo::Native("axlusdc".to_string()),
             },
             recipient: payee.sender.to_string(),
-            start_time: env.block.time.seconds(),
-            stop_time: env.block.time.plus_seconds(100).seconds(),
+            start_time: Some(StreamTime::Time(env.block.time)),
+            stop_time: Some(StreamTime::Time(env.block.time.plus_seconds(100))),
+            duration: None,
             stream_type: None,
             curve: None,
+            options: None,
         };
 
         let execute_res = execute(deps.as_mut(), env.clone(), payer.clone(), stream_msg).unwrap();
@@ -714,8 +2131,9 @@ mod tests {
         let withdraw_msg = ExecuteMsg::ClaimFromStream {
             amount: Uint128::new(90),
             denom: String::from("axlusdc"),
-            recipient: payee.sender.to_string(),
             stream_idx: Some(1),
+            to: None,
+            msg: None,
         };
         let execute_res =
             execute(deps.as_mut(), env.clone(), payee.clone(), withdraw_msg).unwrap_err();
@@ -731,8 +2149,9 @@ mod tests {
         let withdraw_msg = ExecuteMsg::ClaimFromStream {
             amount: Uint128::new(10),
             denom: String::from("axlusdc"),
-            recipient: payee.sender.to_string(),
             stream_idx: Some(1),
+            to: None,
+            msg: None,
         };
         let execute_res = execute(deps.as_mut(), env.clone(), payee.clone(), withdraw_msg).unwrap();
         assert_eq!(1, execute_res.messages.len());
@@ -753,8 +2172,9 @@ mod tests {
         let withdraw_msg = ExecuteMsg::ClaimFromStream {
             amount: Uint128::new(90),
             denom: String::from("axlusdc"),
-            recipient: payee.sender.to_string(),
             stream_idx: Some(1),
+            to: None,
+            msg: None,
         };
         let execute_res =
             execute(deps.as_mut(), env.clone(), payee.clone(), withdraw_msg).unwrap_err();
@@ -770,8 +2190,9 @@ mod tests {
         let withdraw_msg = ExecuteMsg::ClaimFromStream {
             amount: Uint128::new(90),
             denom: String::from("axlusdc"),
-            recipient: payee.sender.to_string(),
             stream_idx: Some(1),
+            to: None,
+            msg: None,
         };
         let execute_res = execute(deps.as_mut(), env.clone(), payee.clone(), withdraw_msg).unwrap();
 
@@ -795,8 +2216,9 @@ mod tests {
         let withdraw_msg = ExecuteMsg::ClaimFromStream {
             amount: Uint128::new(10),
             denom: String::from("axlusdc"),
-            recipient: payee.sender.to_string(),
             stream_idx: Some(1),
+            to: None,
+            msg: None,
         };
         let execute_res = execute(deps.as_mut(), env.clone(), payee, withdraw_msg).unwrap_err();
 
@@ -805,4 +2227,210 @@ mod tests {
             _ => panic!("DO NOT ENTER HERE"),
         }
     }
+
+    #[test]
+    fn streams_stored_by_the_first_release_still_load() {
+        let env = mock_env();
+        let stored = r#"{
+            "stream_idx": 1,
+            "deposit": "100",
+            "rate_per_second": "1",
+            "remaining_balance": "100",
+            "stop_time": "1571797519879305533",
+            "start_time": "1571797419879305533",
+            "recipient": "payee",
+            "sender": "payer",
+            "token_addr": { "native": "axlusdc" },
+            "is_closed": false,
+            "curve": null
+        }"#;
+        let stream: PaymentStream = cosmwasm_std::from_slice(stored.as_bytes()).unwrap();
+        assert_eq!(stream.start_time, StreamTime::Time(env.block.time));
+        assert_eq!(
+            stream.stop_time,
+            StreamTime::Time(env.block.time.plus_seconds(100))
+        );
+        assert_eq!(stream.rate_per_second, cosmwasm_std::Decimal256::one());
+        assert_eq!(stream.status, StreamStatus::Active);
+        assert_eq!(stream.cancel_policy, crate::state::CancelPolicy::default());
+        assert!(!stream.funded_from_balance);
+        assert!(stream.shares.is_empty());
+
+        // It is claimed from like any other stream
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        payment_streams()
+            .save(deps.as_mut().storage, "1", &stream)
+            .unwrap();
+        LAST_STREAM_IDX.save(deps.as_mut().storage, &1).unwrap();
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(40);
+        let claim = ExecuteMsg::ClaimFromStream {
+            amount: Uint128::new(40),
+            denom: String::from("axlusdc"),
+            stream_idx: Some(1),
+            to: None,
+            msg: None,
+        };
+        let res = execute(deps.as_mut(), env, mock_info("payee", &[]), claim).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "payee".to_string(),
+                amount: coins(40, "axlusdc"),
+            }))]
+        );
+    }
+
+    #[test]
+    fn streams_hold_deposits_beyond_uint128() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // An 18 decimal token with a large supply, more than a Uint128 can hold is locked in the stream
+        let deposit = Uint256::from(u128::MAX) * Uint256::from(1_000u128);
+        let stored = format!(
+            r#"{{
+            "stream_idx": 1,
+            "deposit": "{deposit}",
+            "rate_per_second": "0",
+            "remaining_balance": "{deposit}",
+            "stop_time": {{ "time": "{}" }},
+            "start_time": {{ "time": "{}" }},
+            "recipient": "payee",
+            "sender": "payer",
+            "token_addr": {{ "native": "wei" }},
+            "is_closed": false,
+            "curve": null
+        }}"#,
+            env.block.time.plus_seconds(300).nanos(),
+            env.block.time.nanos(),
+        );
+        let mut stream: PaymentStream = cosmwasm_std::from_slice(stored.as_bytes()).unwrap();
+        stream.rate_per_second = curve_helpers::calc_rate_per_second(300, deposit).unwrap();
+        payment_streams()
+            .save(deps.as_mut().storage, "1", &stream)
+            .unwrap();
+
+        env.block.time = env.block.time.plus_seconds(100);
+        let claimable = query_stream_amount_claimable(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(
+            claimable.amount_available,
+            deposit.multiply_ratio(100u128, 300u128)
+        );
+
+        // Payouts are bank coins, a claim takes a Uint128 and is debited from the Uint256 balance
+        let claim = ExecuteMsg::ClaimFromStream {
+            amount: Uint128::MAX,
+            denom: String::from("wei"),
+            stream_idx: Some(1),
+            to: None,
+            msg: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("payee", &[]), claim).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "payee".to_string(),
+                amount: vec![Coin {
+                    denom: "wei".to_string(),
+                    amount: Uint128::MAX,
+                }],
+            }))]
+        );
+        let claimable = query_stream_amount_claimable(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            claimable.stream.remaining_balance,
+            deposit - Uint256::from(u128::MAX)
+        );
+        assert_eq!(claimable.amount_streamed, Uint256::from(u128::MAX));
+    }
+
+    #[test]
+    fn migrate_rebuilds_voting_power_in_pages() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            count: 0,
+            governance_asset: Some(AssetInfo::Native("ujuno".to_string())),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        let stream_msg = ExecuteMsg::CreateStream {
+            asset: Asset {
+                amount: Uint128::new(100),
+                info: AssetInfo::Native("axlusdc".to_string()),
+            },
+            recipient: "payee".to_string(),
+            start_time: Some(StreamTime::Time(env.block.time)),
+            stop_time: Some(StreamTime::Time(env.block.time.plus_seconds(100))),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            options: None,
+        };
+        let payer = mock_info("payer", &coins(100, "axlusdc"));
+        for _ in 0..2 {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                payer.clone(),
+                stream_msg.clone(),
+            )
+            .unwrap();
+        }
+        env.block.height += 1;
+        let power = |deps: Deps, env: &Env| {
+            voting::query_voting_power_at_height(deps, env.clone(), "payee".to_string(), None)
+                .unwrap()
+                .power
+        };
+        // Streams outside the governance asset don't count
+        assert_eq!(Uint128::zero(), power(deps.as_ref(), &env));
+
+        let migrate_msg = MigrateMsg {
+            governance_asset: Some(AssetInfo::Native("axlusdc".to_string())),
+        };
+        migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap();
+        let rebuild = ExecuteMsg::RebuildVotingPower { limit: Some(1) };
+        let anyone = mock_info("anyone", &[]);
+        // Nobody had power to reset, then one stream per page
+        execute(deps.as_mut(), env.clone(), anyone.clone(), rebuild.clone()).unwrap();
+        execute(deps.as_mut(), env.clone(), anyone.clone(), rebuild.clone()).unwrap();
+        env.block.height += 1;
+        assert_eq!(Uint128::new(100), power(deps.as_ref(), &env));
+
+        // A stream the rebuild has not reached yet is only counted once it does
+        execute(deps.as_mut(), env.clone(), payer, stream_msg).unwrap();
+        env.block.height += 1;
+        assert_eq!(Uint128::new(100), power(deps.as_ref(), &env));
+        for _ in 0..3 {
+            execute(deps.as_mut(), env.clone(), anyone.clone(), rebuild.clone()).unwrap();
+        }
+        env.block.height += 1;
+        assert_eq!(Uint128::new(300), power(deps.as_ref(), &env));
+        let total = voting::query_total_power_at_height(deps.as_ref(), env.clone(), None).unwrap();
+        assert_eq!(Uint128::new(300), total.power);
+        let err = execute(deps.as_mut(), env.clone(), anyone.clone(), rebuild).unwrap_err();
+        assert_eq!(ContractError::NoVotingRebuild {}, err);
+
+        // Running it again rebuilds the same powers instead of adding to them
+        migrate(deps.as_mut(), env.clone(), migrate_msg).unwrap();
+        let rebuild = ExecuteMsg::RebuildVotingPower { limit: None };
+        execute(deps.as_mut(), env.clone(), anyone.clone(), rebuild.clone()).unwrap();
+        execute(deps.as_mut(), env.clone(), anyone, rebuild).unwrap();
+        env.block.height += 1;
+        assert_eq!(Uint128::new(300), power(deps.as_ref(), &env));
+        let total = voting::query_total_power_at_height(deps.as_ref(), env, None).unwrap();
+        assert_eq!(Uint128::new(300), total.power);
+    }
 }
@@ -1,33 +1,68 @@
-use cosmwasm_std::{Deps, Env, Response, StdResult, Timestamp, Uint128};
-use wynd_utils::{Curve, CurveError};
+use cosmwasm_std::{Decimal256, Deps, Env, StdResult, Uint128, Uint256};
+use cw_utils::Duration;
+use wynd_utils::{Curve, CurveError, PiecewiseLinear, SaturatingLinear};
 
 use crate::{
-    state::{payment_streams, PaymentStream, StreamType},
+    staking,
+    state::{
+        payment_streams, MilestoneStatus, PaymentStream, RecipientShare, Recurrence, StreamStatus,
+        StreamType,
+    },
     ContractError,
 };
 
-pub fn calc_rate_per_second(duration: u64, deposit: Uint128) -> Result<Uint128, ContractError> {
+/// The fixed point scale of a `Decimal256`, 1.0 expressed in atomics (10^18)
+fn rate_scale() -> Uint256 {
+    Decimal256::one().atomics()
+}
+
+/// Calculates the rate per second of a stream as a `Decimal256` with 18 decimals.
+/// The rate is truncated at the 18th decimal, what is lost there is recovered by `accrued_amount`
+/// so the rate can be used for display without ever stranding dust in the stream
+pub fn calc_rate_per_second(duration: u64, deposit: Uint256) -> Result<Decimal256, ContractError> {
     if duration == 0 {
-        Err(ContractError::DivisionByZero {})
-    } else {
-        // Perform the calculation in a higher precision by scaling the deposit before division
-        // This scales the deposit to a larger number to avoid losing the fraction when dividing by a large duration.
-        let scaling_factor = 1_000_000_000_000_000; // Scaling to a billion for precision
-        let scaled_deposit = deposit
-            .u128()
-            .checked_mul(scaling_factor)
-            .ok_or(ContractError::DivisionByZero {})?;
+        return Err(ContractError::DivisionByZero {});
+    }
+    let rate_atomics = deposit
+        .checked_mul(rate_scale())?
+        .checked_div(Uint256::from(duration))?;
+    Ok(Decimal256::new(rate_atomics))
+}
 
-        let rate_per_second = scaled_deposit
-            .checked_div(duration as u128)
-            .ok_or(ContractError::DivisionByZero {})?
-            .checked_div(scaling_factor)
-            .ok_or(ContractError::DivisionByZero {})?;
-        println!("Rate per second: {:?}", rate_per_second);
-        println!("Scaled deposit: {:?}", scaled_deposit);
+/// The part of `deposit` which is not covered by `rate_per_second` over the full duration,
+/// expressed in rate atomics. This is always smaller than `duration`
+pub fn rate_remainder(
+    deposit: Uint256,
+    rate_per_second: Decimal256,
+    duration: u64,
+) -> Result<Uint256, ContractError> {
+    Ok(deposit.checked_mul(rate_scale())?.checked_sub(
+        rate_per_second
+            .atomics()
+            .checked_mul(Uint256::from(duration))?,
+    )?)
+}
 
-        Ok(Uint128::new(rate_per_second))
+/// The accrual engine for rate based streams.
+/// Accrues `rate_per_second` for every elapsed unit and spreads the remainder left over by the truncated rate
+/// pro rata on top. The result is exactly floor(deposit * elapsed / duration) and the full deposit once
+/// elapsed reaches duration, which means no dust is left behind in the stream
+pub fn accrued_amount(
+    deposit: Uint256,
+    rate_per_second: Decimal256,
+    elapsed: u64,
+    duration: u64,
+) -> Result<Uint256, ContractError> {
+    if duration == 0 {
+        return Err(ContractError::DivisionByZero {});
     }
+    let elapsed = elapsed.min(duration);
+    let remainder = rate_remainder(deposit, rate_per_second, duration)?;
+    let accrued_atomics = rate_per_second
+        .atomics()
+        .checked_mul(Uint256::from(elapsed))?
+        .checked_add(remainder.multiply_ratio(elapsed, duration))?;
+    Ok(accrued_atomics.checked_div(rate_scale())?)
 }
 
 pub fn validate_curve(stream_type: StreamType, curve: &Curve) -> Result<(), CurveError> {
@@ -71,6 +106,8 @@ pub fn validate_curve(stream_type: StreamType, curve: &Curve) -> Result<(), Curv
             // if the curve is monotonically increasing which is a basic expectation.
             curve.validate_monotonic_increasing()
         }
+        // Recurring and milestone streams vest by period or by approval and have no curve
+        StreamType::Recurring(_) | StreamType::Milestones(_) => Err(CurveError::NotMonotonic),
         StreamType::TraditionalUnlockStepCurve => {
             // A traditional unlock step curve should have steps, which means it should be PiecewiseLinear
             // with segments where y remains the same (steps), followed by increases.
@@ -97,144 +134,318 @@ pub fn validate_curve(stream_type: StreamType, curve: &Curve) -> Result<(), Curv
     }
 }
 
-pub fn avail_balance_of(stream: PaymentStream, env: Env) -> Result<Uint128, ContractError> {
-    // Get the time delta
-    let delta = delta(stream.clone(), env.clone())?;
-    println!("Delta: {:?}", delta);
-    match stream.curve {
-        Some(curve) => {
-            match curve.clone() {
-                // The calculation for each curve is the same only the curve changes so we can use the same logic for each
-                // Any of these types are the same Curve::Constant { _ } | Curve::SaturatingLinear(s) | Curve::PiecewiseLinear(s)
-                Curve::Constant { y } => {
-                    let rec_amt =
-                        stream.deposit.u128() - curve.value(env.block.time.seconds()).u128();
-                    let rec_bal = Uint128::from(rec_amt);
-                    let amount_available = stream.deposit.checked_sub(stream.remaining_balance)?;
-                    let new_balance = rec_bal.checked_sub(amount_available)?;
+/// Moves a curve along the x axis, used to anchor curves which were given relative to the start of a stream
+pub fn shift_curve(curve: Curve, offset: u64) -> Curve {
+    match curve {
+        Curve::Constant { y } => Curve::Constant { y },
+        Curve::SaturatingLinear(s) => Curve::SaturatingLinear(SaturatingLinear {
+            min_x: s.min_x.saturating_add(offset),
+            max_x: s.max_x.saturating_add(offset),
+            ..s
+        }),
+        Curve::PiecewiseLinear(p) => Curve::PiecewiseLinear(PiecewiseLinear {
+            steps: p
+                .steps
+                .into_iter()
+                .map(|(x, y)| (x.saturating_add(offset), y))
+                .collect(),
+        }),
+    }
+}
 
-                    // Calc this better
+/// The amount a curve ends at, None for a constant curve as it does not vest over time
+pub fn curve_total(curve: &Curve) -> Option<Uint128> {
+    match curve {
+        Curve::Constant { .. } => None,
+        Curve::SaturatingLinear(s) => Some(s.max_y),
+        Curve::PiecewiseLinear(p) => p.steps.last().map(|(_, y)| *y),
+    }
+}
 
-                    return Ok(new_balance);
-                }
-                Curve::SaturatingLinear(s) => {
-                    let avail_amt = curve.value(env.block.time.seconds());
-                    let amount_available = stream.deposit.checked_sub(stream.remaining_balance)?;
-                    let new_balance = avail_amt.checked_sub(amount_available)?;
-                    println!("New balance {:?}", new_balance);
-                    println!(
-                        "New balance {:?}",
-                        curve.value(env.block.time.seconds()).u128()
-                    );
-
-                    // Calc this better
-
-                    return Ok(new_balance);
-                }
-                Curve::PiecewiseLinear(s) => {
-                    let avail_amt = curve.value(env.block.time.seconds());
-                    let amount_available = stream.deposit.checked_sub(stream.remaining_balance)?;
-                    let new_balance = avail_amt.checked_sub(amount_available)?;
-                    println!("New balance {:?}", new_balance);
-                    println!(
-                        "New balance {:?}",
-                        curve.value(env.block.time.seconds()).u128()
-                    );
-
-                    // Calc this better
-
-                    return Ok(new_balance);
-                }
-                _ => {
-                    return Err(ContractError::Unauthorized {});
-                }
-            };
-        }
-        None => {
-            // TO get the available balance off a stream we need to calculate the delta and multiply it by the rate per second
-            // This requires the rate_per_second to be set and to be able to handle
-            // Use delta to get the balance that should be available
-            let rate_per_second: Uint128;
-            let due_balance: Uint128;
-            // Calculate the expected amount for the given duration to find a fallback rate
-            let fallback_rate_per_second = stream.deposit.multiply_ratio(
-                Uint128::from(delta), // For rate per second, the numerator should be 1 second
-                Uint128::from(stream.stop_time.seconds() - stream.start_time.seconds()),
-            );
-            println!("Fallback rate per second: {:?}", fallback_rate_per_second);
-            rate_per_second = fallback_rate_per_second;
-            due_balance = fallback_rate_per_second;
-            // println!("Delta: {:?}, Recipient Balance: {:?}", delta, rec_bal);
-
-            // Calculate the rate per second on the fly and use that to calculate the available balance
-            // This is a fallback in case the rate_per_second is not set
-            // let rate_per_second = calc_rate_per_second(delta, stream.deposit)?;
-            println!("Rate per second: {:?}", rate_per_second);
-
-            if stream.deposit >= stream.remaining_balance {
-                println!(
-                    "Deposit: {:?}, Remaining: {:?}",
-                    stream.deposit, stream.remaining_balance
-                );
-                // If the stream is ended, just give the remaining balance
-                if stream.stop_time <= env.block.time {
-                    println!("{:?} {:?}", stream.stop_time, env.block.time);
-                    return Ok(stream.remaining_balance);
-                }
+/// Scales a curve so it ends at `amount` while keeping its shape
+pub fn scale_curve(curve: &Curve, amount: Uint128) -> Result<Curve, ContractError> {
+    let total = curve_total(curve)
+        .filter(|total| !total.is_zero())
+        .ok_or(ContractError::InvalidSchedule {})?;
+    let scale = |y: Uint128| y.multiply_ratio(amount, total);
+    Ok(match curve {
+        Curve::Constant { .. } => return Err(ContractError::InvalidSchedule {}),
+        Curve::SaturatingLinear(s) => Curve::SaturatingLinear(SaturatingLinear {
+            min_y: scale(s.min_y),
+            max_y: scale(s.max_y),
+            ..s.clone()
+        }),
+        Curve::PiecewiseLinear(p) => Curve::PiecewiseLinear(PiecewiseLinear {
+            steps: p.steps.iter().map(|(x, y)| (*x, scale(*y))).collect(),
+        }),
+    })
+}
 
-                let amount_available = stream.deposit.checked_sub(stream.remaining_balance)?;
-                println!("Amount available: {:?}", amount_available);
-                let new_balance = due_balance.checked_sub(amount_available)?;
-                // println!("New balance {:?}", new_balance);
-                println!("New balance {:?}", new_balance);
+/// The total amount of a stream which has vested at the current block, regardless of what was already claimed
+pub fn vested_amount(stream: &PaymentStream, env: &Env) -> Result<Uint256, ContractError> {
+    // Nothing accrues until a stream is activated
+    if stream.status == StreamStatus::Pending {
+        return Ok(Uint256::zero());
+    }
+    vested_at(stream, accrual_point(stream, env))
+}
 
-                return Ok(new_balance);
+/// The total amount of a stream vested at `point`, in the unit of the stream
+pub fn vested_at(stream: &PaymentStream, point: u64) -> Result<Uint256, ContractError> {
+    if let Some(recurrence) = &stream.recurrence {
+        return recurring_vested_at(stream, recurrence, point);
+    }
+    if !stream.milestones.is_empty() {
+        return milestones_vested_at(stream, point);
+    }
+    let vested = match &stream.curve {
+        // The calculation for each curve is the same only the curve changes so we can use the same logic for each
+        Some(curve) => match curve {
+            Curve::Constant { y } => stream.deposit.checked_sub(Uint256::from(*y))?,
+            // Curves are expressed in the unit of the stream, seconds or blocks
+            Curve::SaturatingLinear(_) | Curve::PiecewiseLinear(_) => {
+                Uint256::from(curve.value(point))
             }
-            Ok(Uint128::from(0u128))
+        },
+        None => {
+            let start = stream.start_time.value();
+            let stop = stream.stop_time.value();
+            let duration = stop.checked_sub(start).ok_or(ContractError::DeltaIssue {
+                start_time: start,
+                stop_time: stop,
+            })?;
+            let elapsed = point.clamp(start, stop) - start;
+            accrued_amount(stream.deposit, stream.rate_per_second, elapsed, duration)?
         }
+    };
+    // A stream can never release more than was deposited into it
+    Ok(vested.min(stream.deposit))
+}
+
+/// The amount of a milestone stream vested at `point`, only approved milestones vest and each one from the point
+/// it was approved at
+fn milestones_vested_at(stream: &PaymentStream, point: u64) -> Result<Uint256, ContractError> {
+    let mut vested = Uint256::zero();
+    for milestone in &stream.milestones {
+        let approved_at = match milestone.status {
+            MilestoneStatus::Approved { at } if at <= point => at,
+            _ => continue,
+        };
+        let length = match milestone.vesting {
+            Some(Duration::Height(blocks)) => blocks,
+            Some(Duration::Time(seconds)) => seconds,
+            None => 0,
+        };
+        let amount = if length == 0 {
+            milestone.amount
+        } else {
+            milestone
+                .amount
+                .multiply_ratio((point - approved_at).min(length), length)
+        };
+        vested = vested.checked_add(Uint256::from(amount))?;
+    }
+    Ok(vested.min(stream.deposit))
+}
+
+/// The amount of a recurring stream vested at `point`, every period which has started vested in full as long as
+/// the deposit covered it
+fn recurring_vested_at(
+    stream: &PaymentStream,
+    recurrence: &Recurrence,
+    point: u64,
+) -> Result<Uint256, ContractError> {
+    let periods =
+        started_periods(stream, recurrence, point).min(funded_periods(stream, recurrence));
+    Ok(Uint256::from(recurrence.amount_per_period).checked_mul(Uint256::from(periods))?)
+}
+
+/// How many periods of a recurring stream have started at `point`, bounded by the periods until the stream stops
+pub fn started_periods(stream: &PaymentStream, recurrence: &Recurrence, point: u64) -> u64 {
+    let start = stream.start_time.value();
+    if point < start {
+        return 0;
     }
+    (point.min(stream.stop_time.value().saturating_sub(1)) - start) / recurrence.period_length() + 1
+}
+
+/// How many periods a recurring stream runs for when fully paid
+pub fn scheduled_periods(stream: &PaymentStream, recurrence: &Recurrence) -> u64 {
+    let duration = stream.stop_time.value() - stream.start_time.value();
+    duration
+        .div_ceil(recurrence.period_length())
+        .min(recurrence.max_periods.unwrap_or(u64::MAX))
+}
+
+/// How many periods of a recurring stream the deposit pays for, bounded by the periods until the stream stops
+pub fn funded_periods(stream: &PaymentStream, recurrence: &Recurrence) -> u64 {
+    let affordable = stream.deposit / Uint256::from(recurrence.amount_per_period);
+    let affordable = Uint128::try_from(affordable).map_or(u64::MAX, |periods| {
+        periods.u128().try_into().unwrap_or(u64::MAX)
+    });
+    scheduled_periods(stream, recurrence).min(affordable)
+}
+
+/// True once a period started which the deposit of a recurring stream could not pay for
+pub fn has_lapsed(stream: &PaymentStream, recurrence: &Recurrence, env: &Env) -> bool {
+    stream.status != StreamStatus::Pending
+        && started_periods(stream, recurrence, accrual_point(stream, env))
+            > funded_periods(stream, recurrence)
 }
 
+/// The points, in the unit of the stream, where the vesting of a stream changes pace
+pub fn schedule_points(stream: &PaymentStream) -> Vec<u64> {
+    match &stream.curve {
+        None | Some(Curve::Constant { .. }) => {
+            vec![stream.start_time.value(), stream.stop_time.value()]
+        }
+        Some(Curve::SaturatingLinear(s)) => vec![s.min_x, s.max_x],
+        Some(Curve::PiecewiseLinear(p)) => p.steps.iter().map(|(x, _)| *x).collect(),
+    }
+}
+
+/// What a stream vested at `point` as a point of a curve, curves are Uint128
+fn curve_point(stream: &PaymentStream, point: u64) -> Result<Uint128, ContractError> {
+    Ok(Uint128::try_from(vested_at(stream, point)?)?)
+}
+
+/// The curve vesting the sum of what all `streams` vest, each stream is linear between its schedule points
+/// so the sum is linear between the points of all of them
+pub fn merge_schedules(streams: &[PaymentStream]) -> Result<Curve, ContractError> {
+    let mut points: Vec<u64> = streams.iter().flat_map(schedule_points).collect();
+    points.sort_unstable();
+    points.dedup();
+    let steps = points
+        .into_iter()
+        .map(|x| {
+            let total = streams.iter().try_fold(Uint128::zero(), |total, stream| {
+                Ok::<_, ContractError>(total.checked_add(curve_point(stream, x)?)?)
+            })?;
+            Ok((x, total))
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    Ok(Curve::PiecewiseLinear(PiecewiseLinear { steps }))
+}
+
+/// What a stream still has to vest after `now`, in the unit of the stream
+pub fn unvested_at(stream: &PaymentStream, now: u64) -> Result<Uint128, ContractError> {
+    let end = schedule_points(stream).into_iter().fold(now, u64::max);
+    Ok(curve_point(stream, end)?.checked_sub(curve_point(stream, now)?)?)
+}
+
+/// Carves `amount` of what is still to vest from `now` on out of a stream.
+/// Returns the curve the stream keeps and the curve of the carved part, both follow the shape of the original
+/// schedule and together they vest exactly what the original would have
+pub fn split_schedule(
+    stream: &PaymentStream,
+    now: u64,
+    amount: Uint128,
+) -> Result<(Curve, Curve), ContractError> {
+    let points = schedule_points(stream);
+    let vested_now = curve_point(stream, now)?;
+    let unvested = unvested_at(stream, now)?;
+    if amount.is_zero() || amount > unvested {
+        return Err(ContractError::InvalidAmount {});
+    }
+
+    let mut kept = vec![];
+    for x in points.iter().copied().filter(|x| *x < now) {
+        kept.push((x, curve_point(stream, x)?));
+    }
+    kept.push((now, vested_now));
+    let mut carved = vec![(now, Uint128::zero())];
+    for x in points.iter().copied().filter(|x| *x > now) {
+        let total = curve_point(stream, x)?;
+        let part = total
+            .checked_sub(vested_now)?
+            .multiply_ratio(amount, unvested);
+        kept.push((x, total.checked_sub(part)?));
+        carved.push((x, part));
+    }
+    Ok((
+        Curve::PiecewiseLinear(PiecewiseLinear { steps: kept }),
+        Curve::PiecewiseLinear(PiecewiseLinear { steps: carved }),
+    ))
+}
+
+/// The amount a recipient can claim from a stream right now, this is what has vested minus what was already claimed
+pub fn avail_balance_of(stream: PaymentStream, env: Env) -> Result<Uint256, ContractError> {
+    // A cancelled stream was settled in full and an expired one belongs to the sender, nothing is left to claim
+    if matches!(stream.status, StreamStatus::Cancelled { .. }) || stream.claim_expired(&env.block) {
+        return Ok(Uint256::zero());
+    }
+    let vested = vested_amount(&stream, &env)?;
+    let already_claimed = stream.deposit.checked_sub(stream.remaining_balance)?;
+    let available = vested.checked_sub(already_claimed)?;
+    // Staked funds can only be claimed once they are undelegated and unbonded
+    match &stream.staking {
+        Some(staking) => {
+            let deposit = Uint128::try_from(stream.deposit)?;
+            let liquid = Uint256::from(staking::unbonded(staking, deposit, &env.block));
+            Ok(available.min(liquid.saturating_sub(already_claimed)))
+        }
+        None => Ok(available),
+    }
+}
+
+/// The part of `vested` which belongs to the share at `position` of a split stream.
+/// Every share is rounded down except the last one which takes what the rounding left, so the shares
+/// always add up to exactly what has vested
+pub fn vested_share(
+    stream: &PaymentStream,
+    vested: Uint128,
+    position: usize,
+) -> Result<Uint128, ContractError> {
+    let rounded = |share: &RecipientShare| vested * share.share;
+    if position + 1 < stream.shares.len() {
+        return Ok(rounded(&stream.shares[position]));
+    }
+    let others = stream.shares[..position]
+        .iter()
+        .try_fold(Uint128::zero(), |total, share| {
+            total.checked_add(rounded(share))
+        })?;
+    Ok(vested.checked_sub(others)?)
+}
+
+/// The amount the share holder at `position` of a split stream can claim right now
+pub fn share_available(
+    stream: &PaymentStream,
+    env: &Env,
+    position: usize,
+) -> Result<Uint128, ContractError> {
+    if matches!(stream.status, StreamStatus::Cancelled { .. }) || stream.claim_expired(&env.block) {
+        return Ok(Uint128::zero());
+    }
+    let vested = Uint128::try_from(vested_amount(stream, env)?)?;
+    let entitled = vested_share(stream, vested, position)?;
+    Ok(entitled.checked_sub(stream.shares[position].claimed)?)
+}
+
+/// The point, in the unit of the stream, up to which a stream accrues right now.
+/// This is the current block unless a cancel was scheduled, in which case accrual stops when the notice period ends
+pub fn accrual_point(stream: &PaymentStream, env: &Env) -> u64 {
+    let now = stream.start_time.current(&env.block);
+    match stream.cancels_at {
+        Some(cancels_at) => now.min(cancels_at.value()),
+        None => now,
+    }
+}
+
+/// The amount of units (seconds or blocks) a stream has been running for, capped at its duration
 pub fn delta(stream: PaymentStream, env: Env) -> StdResult<u64> {
-    if env.block.time <= stream.start_time {
+    let now = accrual_point(&stream, &env);
+    let start = stream.start_time.value();
+    // If the stream hasn't started yet, return 0
+    if now <= start {
         return Ok(0);
     }
-    if env.block.time < stream.stop_time {
-        return Ok(env
-            .block
-            .time
-            .minus_seconds(stream.start_time.seconds())
-            .seconds());
-    }
-    let duration = stream
-        .stop_time
-        .seconds()
-        .checked_sub(stream.start_time.seconds())
-        .ok_or(ContractError::Unauthorized {})
-        .unwrap();
-    Ok(Timestamp::from_seconds(duration).seconds())
+    // If the stream has ended the delta is the full duration
+    Ok(now.min(stream.stop_time.value()).saturating_sub(start))
 }
 
 pub fn deltaOf(deps: Deps, env: Env, stream_id: u64) -> StdResult<u64> {
     // Get the stream from storage
     let stream = payment_streams().load(deps.storage, &stream_id.to_string())?;
-    // If the stream hasn't started yet, return 0
-    if env.block.time <= stream.start_time {
-        return Ok(0);
-    }
-    // If the stream has started but not ended, return the delta
-    if env.block.time < stream.stop_time {
-        return Ok(env
-            .block
-            .time
-            .minus_seconds(stream.start_time.seconds())
-            .seconds());
-    }
-    // If the stream has ended, return the duration
-    let duration = stream
-        .stop_time
-        .seconds()
-        .checked_sub(stream.start_time.seconds())
-        .unwrap();
-    Ok(Timestamp::from_seconds(duration).seconds())
+    delta(stream, env)
 }
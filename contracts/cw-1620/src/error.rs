@@ -1,4 +1,6 @@
-use cosmwasm_std::{OverflowError, StdError};
+use cosmwasm_std::{
+    ConversionOverflowError, DivideByZeroError, OverflowError, StdError, Timestamp, Uint128,
+};
 use thiserror::Error;
 use wynd_utils::CurveError;
 
@@ -10,6 +12,12 @@ pub enum ContractError {
     #[error("{0}")]
     OverflowError(#[from] OverflowError),
 
+    #[error("{0}")]
+    DivideByZeroError(#[from] DivideByZeroError),
+
+    #[error("{0}")]
+    ConversionOverflowError(#[from] ConversionOverflowError),
+
     #[error("{0}")]
     Curve(#[from] CurveError),
 
@@ -28,11 +36,153 @@ pub enum ContractError {
     #[error("Could not find a stream with provided index or address")]
     StreamNotFound {},
 
+    #[error("Stream {stream_idx} is closed")]
+    StreamClosed { stream_idx: u64 },
+
+    #[error("Asset type is not supported")]
+    UnsupportedAsset {},
+
+    #[error("Shares must be positive, unique, include the recipient and add up to 1")]
+    InvalidShares {},
+
+    #[error("Stream {stream_idx} is split between several recipients")]
+    SplitStream { stream_idx: u64 },
+
+    #[error("Only active streams with the same sender, recipient, asset and cancel policy can be merged")]
+    IncompatibleStreams {},
+
+    #[error("Stream {stream_idx} has not been accepted by its recipient")]
+    AwaitingAcceptance { stream_idx: u64 },
+
+    #[error("Stream {stream_idx} is not waiting to be accepted")]
+    NotAwaitingAcceptance { stream_idx: u64 },
+
+    #[error("The deadline to accept the stream has passed")]
+    AcceptanceExpired {},
+
+    #[error("A stream requiring acceptance needs a deadline in the future to accept it by")]
+    InvalidAcceptanceDeadline {},
+
+    #[error("The deadline to accept the stream has not passed yet")]
+    AcceptanceOpen {},
+
+    #[error("The deadline to claim from stream {stream_idx} has passed")]
+    ClaimDeadlinePassed { stream_idx: u64 },
+
+    #[error("The deadline to claim from stream {stream_idx} has not passed yet")]
+    ClaimDeadlineNotPassed { stream_idx: u64 },
+
+    #[error("Merkle root must be a hex encoded 32 byte hash")]
+    InvalidMerkleRoot {},
+
+    #[error("Invalid merkle proof")]
+    InvalidProof {},
+
+    #[error("Already claimed from this airdrop")]
+    AlreadyClaimed {},
+
+    #[error("Airdrop {airdrop_id} has expired")]
+    AirdropExpired { airdrop_id: u64 },
+
+    #[error("Airdrop {airdrop_id} has not expired yet")]
+    AirdropNotExpired { airdrop_id: u64 },
+
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    #[error("The offer has expired")]
+    OfferExpired {},
+
+    #[error("Nonce {nonce} was already used")]
+    NonceUsed { nonce: u64 },
+
+    #[error("Sender is not the ibc-hooks intermediate sender of the channel and original sender")]
+    InvalidIbcSender {},
+
+    #[error("Only unordered channels are supported")]
+    OnlyUnorderedChannel {},
+
+    #[error("Expected IBC version paystreams-1, got {version}")]
+    InvalidIbcVersion { version: String },
+
+    #[error("No provider offers {amount}{source_denom} of liquidity on {channel_id}")]
+    NoChannelLiquidity {
+        channel_id: String,
+        source_denom: String,
+        amount: Uint128,
+    },
+
+    #[error("Only funded streams of the staking denom with a single recipient and a known validator can be staked")]
+    InvalidStaking {},
+
+    #[error("Stream is not staked")]
+    NotStaked {},
+
+    #[error("Funds of this stream are unbonding until {release_at}")]
+    StillUnbonding { release_at: Timestamp },
+
+    #[error("Vault {vault} is not allowlisted")]
+    VaultNotAllowed { vault: String },
+
+    #[error("Only native streams which are not staked can use a vault, giving the recipient at most all the yield")]
+    InvalidVaultStream {},
+
+    #[error("Fiat streams pay a native asset linearly to a single recipient and are neither staked nor in a vault")]
+    InvalidFiatStream {},
+
+    #[error("The oracle price was last updated at {updated_at} and is too old")]
+    StalePrice { updated_at: Timestamp },
+
+    #[error("The oracle price is too uncertain")]
+    PriceOutOfTolerance {},
+
+    #[error("The schedule of the fiat stream is not over yet")]
+    FiatStreamNotEnded {},
+
+    #[error("Milestones need a positive amount, distinct approvers and a threshold between 1 and their number, and must add up to the deposit")]
+    InvalidMilestones {},
+
+    #[error("Milestone {milestone} does not exist")]
+    MilestoneNotFound { milestone: u32 },
+
+    #[error("Milestone {milestone} was already approved or reclaimed")]
+    MilestoneNotPending { milestone: u32 },
+
+    #[error("Already approved")]
+    AlreadyApproved {},
+
+    #[error("The deadline of milestone {milestone} has passed")]
+    MilestoneDeadlinePassed { milestone: u32 },
+
+    #[error("The deadline of milestone {milestone} has not passed yet")]
+    MilestoneDeadlineNotPassed { milestone: u32 },
+
     #[error("Attempted to divide by zero")]
     DivisionByZero {},
 
     #[error("Time delta is not set up properly {start_time}, {stop_time}")]
     DeltaIssue { start_time: u64, stop_time: u64 },
+
+    #[error("Start and stop of a stream must both be block heights or both be timestamps")]
+    MismatchedStreamUnits {},
+
+    #[error("A stream needs a start and stop, a start and a duration, or only a duration")]
+    InvalidSchedule {},
+
+    #[error("This stream type requires a curve")]
+    MissingCurve {},
+
+    #[error("Stream is not pending")]
+    StreamNotPending {},
+
+    #[error("The notice period of this cancel is not over until {cancels_at}")]
+    NoticePeriodNotOver { cancels_at: u64 },
+
+    #[error("Recurring stream {stream_idx} ran out of funds for a period and lapsed")]
+    StreamLapsed { stream_idx: u64 },
+
+    #[error("No voting power rebuild is in progress")]
+    NoVotingRebuild {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -0,0 +1,285 @@
+// Streams denominated in a fiat currency and paid in a volatile asset
+// What vests is an amount of the currency, linearly over the schedule of the stream. Every payout converts what is
+// owed into the asset at the price of the stream's oracle, the deposit only serves as collateral. Prices which are
+// stale or too uncertain are rejected and nothing is paid until the oracle recovers. Only a cancel can go ahead
+// without the oracle, once it has been down past the grace period it settles at the last usable price
+use cosmwasm_std::{
+    Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdError, StdResult,
+    Uint128, Uint256,
+};
+use cw_asset::AssetInfo;
+use cw_utils::must_pay;
+use paystreams::{OracleQueryMsg, PriceResponse};
+
+use crate::contract::{ensure_open, release_stream_funds, save_stream, update_sender_balance};
+use crate::curve_helpers;
+use crate::msg::{FiatOptions, FiatStatusResponse};
+use crate::state::{payment_streams, FiatTerms, PaymentStream, StreamStatus};
+use crate::ContractError;
+
+// How long after the last usable price went stale a cancel settles at it instead of waiting for the oracle
+pub const ORACLE_GRACE_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+/// Validates the fiat terms of a new stream, the oracle must price its asset in the currency right away
+pub fn validate_terms(
+    deps: Deps,
+    env: &Env,
+    asset: &AssetInfo,
+    options: FiatOptions,
+) -> Result<FiatTerms, ContractError> {
+    if !matches!(asset, AssetInfo::Native(_))
+        || options.amount.is_zero()
+        || options.tolerance > Decimal::one()
+    {
+        return Err(ContractError::InvalidFiatStream {});
+    }
+    let mut terms = FiatTerms {
+        oracle: deps.api.addr_validate(&options.oracle)?,
+        currency: options.currency,
+        amount: options.amount,
+        paid: Uint128::zero(),
+        max_staleness: options.max_staleness,
+        tolerance: options.tolerance,
+        last_price: Decimal::zero(),
+        last_priced_at: env.block.time,
+    };
+    terms.last_price = price(&deps.querier, env, asset, &terms)?;
+    Ok(terms)
+}
+
+/// The price of one unit of the asset in the currency, as long as it is fresh and certain enough
+pub fn price(
+    querier: &QuerierWrapper,
+    env: &Env,
+    asset: &AssetInfo,
+    terms: &FiatTerms,
+) -> Result<Decimal, ContractError> {
+    let base = match asset {
+        AssetInfo::Native(denom) => denom.clone(),
+        _ => return Err(ContractError::InvalidFiatStream {}),
+    };
+    let response: PriceResponse = querier.query_wasm_smart(
+        &terms.oracle,
+        &OracleQueryMsg::Price {
+            base,
+            quote: terms.currency.clone(),
+        },
+    )?;
+    if response.updated_at.plus_seconds(terms.max_staleness) < env.block.time {
+        return Err(ContractError::StalePrice {
+            updated_at: response.updated_at,
+        });
+    }
+    if response.price.is_zero() || response.confidence > response.price * terms.tolerance {
+        return Err(ContractError::PriceOutOfTolerance {});
+    }
+    Ok(response.price)
+}
+
+/// The price a cancel settles at. Without a usable oracle price this is the last one seen, as long as it has been
+/// stale for longer than the grace period, so a dead oracle can not lock the collateral for good
+pub fn settlement_price(
+    querier: &QuerierWrapper,
+    env: &Env,
+    stream: &PaymentStream,
+) -> Result<Decimal, ContractError> {
+    let terms = stream
+        .fiat
+        .as_ref()
+        .ok_or(ContractError::InvalidFiatStream {})?;
+    match price(querier, env, &stream.token_addr, terms) {
+        Ok(price) => Ok(price),
+        Err(err) => {
+            let fallback_at = terms
+                .last_priced_at
+                .plus_seconds(terms.max_staleness)
+                .plus_seconds(ORACLE_GRACE_PERIOD);
+            if env.block.time < fallback_at {
+                return Err(err);
+            }
+            Ok(terms.last_price)
+        }
+    }
+}
+
+// What vested so far in the currency
+fn vested_fiat(stream: &PaymentStream, terms: &FiatTerms, env: &Env) -> Uint128 {
+    if stream.status == StreamStatus::Pending {
+        return Uint128::zero();
+    }
+    let start = stream.start_time.value();
+    let stop = stream.stop_time.value();
+    let point = curve_helpers::accrual_point(stream, env).clamp(start, stop);
+    terms
+        .amount
+        .multiply_ratio(point - start, (stop - start).max(1))
+}
+
+// Converts an amount of the currency into the asset, rounding down
+fn to_asset(fiat: Uint128, price: Decimal) -> Uint128 {
+    fiat.multiply_ratio(Decimal::one().atomics(), price.atomics())
+}
+
+/// What can be paid out of a fiat stream at `price`, everything owed as long as the collateral covers it
+pub fn amount_due(
+    stream: &PaymentStream,
+    env: &Env,
+    price: Decimal,
+) -> Result<Uint128, ContractError> {
+    let terms = stream
+        .fiat
+        .as_ref()
+        .ok_or(ContractError::InvalidFiatStream {})?;
+    if stream.claim_expired(&env.block) {
+        return Ok(Uint128::zero());
+    }
+    let owed = vested_fiat(stream, terms, env).checked_sub(terms.paid)?;
+    Ok(stream.balance_up_to(to_asset(owed, price)))
+}
+
+/// Records `amount` of the asset paid out at `price` against what the stream owes in the currency, `price` becomes
+/// the last one seen. Paying everything due settles all that is owed so rounding never leaves a debt behind
+pub fn record_payment(
+    stream: &mut PaymentStream,
+    env: &Env,
+    amount: Uint128,
+    price: Decimal,
+) -> Result<(), ContractError> {
+    let vested = match &stream.fiat {
+        Some(terms) => vested_fiat(stream, terms, env),
+        None => return Ok(()),
+    };
+    let terms = stream
+        .fiat
+        .as_mut()
+        .ok_or(ContractError::InvalidFiatStream {})?;
+    let owed = vested.checked_sub(terms.paid)?;
+    let settled = if amount >= to_asset(owed, price) {
+        owed
+    } else {
+        (amount * price).min(owed)
+    };
+    terms.paid = terms.paid.checked_add(settled)?;
+    terms.last_price = price;
+    terms.last_priced_at = env.block.time;
+    Ok(())
+}
+
+// Adds the native funds sent along to the collateral of a fiat stream, only the sender can do this.
+// On a stream funded from the prepaid balance the collateral is committed like the deposit and what is left of it
+// returns to the balance
+pub fn add_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let denom = match (&stream.token_addr, &stream.fiat) {
+        (AssetInfo::Native(denom), Some(_)) => denom.clone(),
+        _ => return Err(ContractError::InvalidFiatStream {}),
+    };
+    let amount = must_pay(&info, &denom).map_err(|_| ContractError::InvalidAmount {})?;
+    stream.deposit = stream.deposit.checked_add(amount.into())?;
+    stream.remaining_balance = stream.remaining_balance.checked_add(amount.into())?;
+    if stream.funded_from_balance {
+        update_sender_balance(
+            deps.storage,
+            &stream.sender,
+            &stream.token_addr,
+            |mut balance| {
+                balance.committed = balance.committed.checked_add(amount)?;
+                Ok(balance)
+            },
+        )?;
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_collateral")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("amount", amount))
+}
+
+// Returns the collateral of a fiat stream beyond what it still owes at the oracle price once its schedule is over,
+// only the sender can do this. A stream which paid everything it owes is closed with the last of its collateral
+pub fn withdraw_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let terms = stream
+        .fiat
+        .as_ref()
+        .ok_or(ContractError::InvalidFiatStream {})?;
+    if vested_fiat(&stream, terms, &env) < terms.amount {
+        return Err(ContractError::FiatStreamNotEnded {});
+    }
+    // Whatever the recipient can still claim stays, which takes a usable price unless everything was paid
+    let owed = terms.amount.checked_sub(terms.paid)?;
+    let needed = if owed.is_zero() {
+        Uint128::zero()
+    } else {
+        let price = price(&deps.querier, &env, &stream.token_addr, terms)?;
+        to_asset(owed, price)
+    };
+    let excess = Uint128::try_from(stream.remaining_balance.saturating_sub(needed.into()))?;
+    if excess.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let refund = release_stream_funds(deps.storage, &stream, Uint128::zero(), excess)?;
+    stream.remaining_balance = stream.remaining_balance.checked_sub(excess.into())?;
+    if stream.remaining_balance.is_zero() {
+        stream.is_closed = true;
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_attribute("method", "withdraw_collateral")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("amount", excess))
+}
+
+pub fn query_fiat_status(deps: Deps, env: Env, stream_idx: u64) -> StdResult<FiatStatusResponse> {
+    let stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    let terms = stream
+        .fiat
+        .clone()
+        .ok_or_else(|| StdError::generic_err("Not a fiat stream"))?;
+    let vested = vested_fiat(&stream, &terms, &env);
+    let owed = vested.saturating_sub(terms.paid);
+    // Without a usable price nothing can be converted, the rest is still reported
+    let price = price(&deps.querier, &env, &stream.token_addr, &terms).ok();
+    let (amount_available, shortfall) = match price {
+        Some(price) => {
+            let needed = to_asset(terms.amount.checked_sub(terms.paid)?, price);
+            (
+                stream.balance_up_to(to_asset(owed, price)),
+                Uint128::try_from(Uint256::from(needed).saturating_sub(stream.remaining_balance))?,
+            )
+        }
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+
+    Ok(FiatStatusResponse {
+        price,
+        currency: terms.currency,
+        vested,
+        paid: terms.paid,
+        owed,
+        amount_available,
+        collateral: stream.remaining_balance,
+        shortfall,
+    })
+}
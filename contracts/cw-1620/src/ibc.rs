@@ -0,0 +1,586 @@
+// Streams created from another chain over a dedicated channel
+// A hub sends a `StreamPacket` for what its sender paid and holds on the other chain, and the stream is paid
+// from the liquidity a provider offers for that channel. The provider is paid what the hub holds once the packet
+// is acknowledged. The stream is created in a submessage, so if that fails nothing of it is kept and the reply
+// turns the error into the acknowledgement, for the hub to refund the sender
+use cosmwasm_schema::cw_serde;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, IbcBasicResponse, IbcChannel,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo,
+    Order, Reply, Response, SubMsg, Timestamp, Uint128, WasmMsg,
+};
+use cw_asset::AssetInfo;
+use cw_utils::nonpayable;
+
+use crate::contract::{deposit_balance, try_create_stream, update_sender_balance};
+use crate::msg::{ExecuteMsg, StreamOptions};
+use crate::state::{
+    CancelPolicy, CancelableBy, ChannelOffer, StreamData, StreamTime, CHANNEL_OFFERS,
+    LAST_STREAM_IDX, SENDER_BALANCES,
+};
+use crate::ContractError;
+
+/// Version of the channels between a hub and the stream contracts it creates streams on
+pub const IBC_APP_VERSION: &str = "paystreams-1";
+// Reply id of the submessage creating the stream of a packet, only called back when it fails
+pub const STREAM_PACKET_REPLY_ID: u64 = 3;
+
+/// Packet asking the stream contract at the other end of a channel to create a stream
+#[cw_serde]
+pub struct StreamPacket {
+    /// Address of the sender on the source chain, refunded if the packet fails
+    pub sender: String,
+    /// What the sender paid on the source chain
+    pub source_denom: String,
+    pub amount: Uint128,
+    pub recipient: String,
+    /// Address on this chain the stream is created for, which can cancel it and gets back what a cancel returns.
+    /// Without one the stream is created for the channel and can't be cancelled
+    pub remote_sender: Option<String>,
+    pub start_time: Timestamp,
+    pub stop_time: Timestamp,
+    /// By default sender and recipient can cancel right away
+    pub cancel_policy: Option<CancelPolicy>,
+}
+
+/// Acknowledgement of a `StreamPacket`, the result holds a `StreamPacketResult`
+#[cw_serde]
+pub enum StreamPacketAck {
+    Result(Binary),
+    Error(String),
+}
+
+#[cw_serde]
+pub struct StreamPacketResult {
+    pub stream_idx: u64,
+    /// Where the source chain pays what the sender paid, the provider of the liquidity
+    pub pay_to: String,
+}
+
+// The account streams created over a channel are sent from when the packet names no sender on this chain
+pub fn channel_account(channel_id: &str) -> Addr {
+    Addr::unchecked(format!("ibc/{}", channel_id))
+}
+
+// Sets or withdraws what the sender offers for the streams asked for over a channel
+pub fn offer_channel_liquidity(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel_id: String,
+    offer: Option<ChannelOffer>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::InvalidAmount {})?;
+    match &offer {
+        Some(offer) => CHANNEL_OFFERS.save(deps.storage, (&channel_id, &info.sender), offer)?,
+        None => CHANNEL_OFFERS.remove(deps.storage, (&channel_id, &info.sender)),
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "offer_channel_liquidity")
+        .add_attribute("channel_id", channel_id)
+        .add_attribute("provider", info.sender)
+        .add_attribute("denom", offer.map(|offer| offer.denom).unwrap_or_default()))
+}
+
+// The first provider of the channel offering liquidity for what the packet paid, with enough of it left
+fn find_provider(
+    deps: Deps,
+    channel_id: &str,
+    packet: &StreamPacket,
+) -> Result<(Addr, ChannelOffer), ContractError> {
+    for item in CHANNEL_OFFERS
+        .prefix(channel_id)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (provider, offer) = item?;
+        if offer.source_denom != packet.source_denom {
+            continue;
+        }
+        let asset = AssetInfo::Native(offer.denom.clone());
+        let balance = SENDER_BALANCES
+            .may_load(deps.storage, (&provider, &asset.to_string()))?
+            .unwrap_or_default();
+        if balance.free >= packet.amount {
+            return Ok((provider, offer));
+        }
+    }
+    Err(ContractError::NoChannelLiquidity {
+        channel_id: channel_id.to_string(),
+        source_denom: packet.source_denom.clone(),
+        amount: packet.amount,
+    })
+}
+
+// Creates the stream of a packet from the liquidity of `provider`, called by this contract for every packet it
+// receives
+pub fn receive_stream_packet(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    provider: String,
+    packet: StreamPacket,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let provider = deps.api.addr_validate(&provider)?;
+    let offer = CHANNEL_OFFERS.load(deps.storage, (&channel_id, &provider))?;
+    let asset = AssetInfo::Native(offer.denom);
+    let (sender, cancel_policy) = match packet.remote_sender {
+        Some(remote_sender) => (
+            deps.api.addr_validate(&remote_sender)?,
+            packet.cancel_policy,
+        ),
+        // Nobody could take back what a cancel returns to the channel account
+        None => (
+            channel_account(&channel_id),
+            Some(CancelPolicy {
+                cancelable_by: CancelableBy::None,
+                notice_period: None,
+            }),
+        ),
+    };
+
+    // The liquidity moves to the stream sender, who funds the stream from it
+    update_sender_balance(deps.storage, &provider, &asset, |mut balance| {
+        balance.free = balance
+            .free
+            .checked_sub(packet.amount)
+            .map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+        Ok(balance)
+    })?;
+    deposit_balance(deps.storage, &sender, &asset, packet.amount)?;
+    let response = try_create_stream(
+        deps.branch(),
+        env,
+        MessageInfo {
+            sender,
+            funds: vec![],
+        },
+        packet.recipient,
+        packet.amount,
+        asset,
+        StreamData {
+            start_time: Some(StreamTime::Time(packet.start_time)),
+            stop_time: Some(StreamTime::Time(packet.stop_time)),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            options: Some(StreamOptions {
+                from_balance: Some(true),
+                cancel_policy,
+                ..Default::default()
+            }),
+        },
+    )?;
+
+    Ok(response
+        .add_attribute("channel_id", channel_id)
+        .add_attribute("provider", provider)
+        .add_attribute("remote_sender", packet.sender))
+}
+
+// The stream of a packet could not be created, the packet is acknowledged with the error instead
+pub fn stream_packet_failed(msg: Reply) -> Result<Response, ContractError> {
+    let error = msg.result.into_result().err().unwrap_or_default();
+    Ok(Response::new()
+        .set_data(to_binary(&StreamPacketAck::Error(error.clone()))?)
+        .add_attribute("method", "stream_packet_failed")
+        .add_attribute("error", error))
+}
+
+fn validate_channel(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.order != IbcOrder::Unordered {
+        return Err(ContractError::OnlyUnorderedChannel {});
+    }
+    if channel.version != IBC_APP_VERSION {
+        return Err(ContractError::InvalidIbcVersion {
+            version: channel.version.clone(),
+        });
+    }
+    if let Some(version) = counterparty_version {
+        if version != IBC_APP_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    validate_channel(msg.channel(), msg.counterparty_version())?;
+    Ok(None)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    validate_channel(msg.channel(), msg.counterparty_version())?;
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_connect")
+        .add_attribute("channel_id", &msg.channel().endpoint.channel_id))
+}
+
+// Streams already created over a closed channel keep running, providers withdraw their balance as usual
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_close")
+        .add_attribute("channel_id", &msg.channel().endpoint.channel_id))
+}
+
+// Nothing is written here, a packet which can't be read or paid for is acknowledged with an error right away
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let channel_id = msg.packet.dest.channel_id;
+    let routed = from_binary::<StreamPacket>(&msg.packet.data)
+        .map_err(ContractError::from)
+        .and_then(|packet| {
+            find_provider(deps.as_ref(), &channel_id, &packet)
+                .map(|(provider, offer)| (packet, provider, offer))
+        });
+    let (packet, provider, offer) = match routed {
+        Ok(routed) => routed,
+        Err(err) => {
+            return Ok(IbcReceiveResponse::new()
+                .set_ack(to_binary(&StreamPacketAck::Error(err.to_string()))?)
+                .add_attribute("method", "ibc_packet_receive")
+                .add_attribute("error", err.to_string()))
+        }
+    };
+
+    // The submessage creates the next stream, nothing else runs in between
+    let result = StreamPacketResult {
+        stream_idx: LAST_STREAM_IDX.load(deps.storage)? + 1,
+        pay_to: offer.source_address,
+    };
+    let create = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_binary(&ExecuteMsg::ReceiveStreamPacket {
+            channel_id: channel_id.clone(),
+            provider: provider.to_string(),
+            packet,
+        })?,
+        funds: vec![],
+    };
+
+    Ok(IbcReceiveResponse::new()
+        .set_ack(to_binary(&StreamPacketAck::Result(to_binary(&result)?))?)
+        .add_submessage(SubMsg::reply_on_error(create, STREAM_PACKET_REPLY_ID))
+        .add_attribute("method", "ibc_packet_receive")
+        .add_attribute("channel_id", channel_id)
+        .add_attribute("provider", provider)
+        .add_attribute("stream_idx", result.stream_idx.to_string()))
+}
+
+// This contract never sends packets, so there are no acknowledgements or timeouts to handle
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{execute, instantiate, query, reply};
+    use crate::msg::{InstantiateMsg, QueryMsg, SenderBalanceResponse, StreamsResponse};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_ibc_channel_connect_ack, mock_ibc_channel_open_try,
+        mock_ibc_packet_recv, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{coins, CosmosMsg, OwnedDeps, Storage, SubMsgResult, Uint256};
+
+    const CHANNEL: &str = "channel-7";
+    const DENOM: &str = "ibc/axlusdc";
+
+    // Instantiated with 1000 of liquidity the provider offers on the channel for uusdc
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                count: 0,
+                governance_asset: None,
+            },
+        )
+        .unwrap();
+        let open = mock_ibc_channel_open_try(CHANNEL, IbcOrder::Unordered, IBC_APP_VERSION);
+        ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap();
+        let connect = mock_ibc_channel_connect_ack(CHANNEL, IbcOrder::Unordered, IBC_APP_VERSION);
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &coins(1000, DENOM)),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::OfferChannelLiquidity {
+                channel_id: CHANNEL.to_string(),
+                offer: Some(ChannelOffer {
+                    denom: DENOM.to_string(),
+                    source_denom: "uusdc".to_string(),
+                    source_address: "juno1provider".to_string(),
+                }),
+            },
+        )
+        .unwrap();
+        deps
+    }
+
+    fn packet(amount: u128, recipient: &str) -> StreamPacket {
+        let now = mock_env().block.time;
+        StreamPacket {
+            sender: "juno1bob".to_string(),
+            source_denom: "uusdc".to_string(),
+            amount: Uint128::new(amount),
+            recipient: recipient.to_string(),
+            remote_sender: Some("bob".to_string()),
+            start_time: now,
+            stop_time: now.plus_seconds(100),
+            cancel_policy: None,
+        }
+    }
+
+    fn balance(deps: Deps, sender: &str) -> (u128, u128) {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::SenderBalance {
+                sender: sender.to_string(),
+                asset: AssetInfo::Native(DENOM.to_string()),
+            },
+        )
+        .unwrap();
+        let balance: SenderBalanceResponse = from_binary(&res).unwrap();
+        (balance.free.u128(), balance.committed.u128())
+    }
+
+    // Receives the packet and runs the submessage it creates the stream in, the way the chain would
+    fn relay(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        packet: &StreamPacket,
+    ) -> StreamPacketAck {
+        let msg = mock_ibc_packet_recv(CHANNEL, packet).unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+        let ack = from_binary(&res.acknowledgement).unwrap();
+        let submsg = match res.messages.first() {
+            Some(submsg) => submsg,
+            None => return ack,
+        };
+        assert_eq!(submsg.id, STREAM_PACKET_REPLY_ID);
+        let msg = match &submsg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => from_binary(msg).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        let env = mock_env();
+        let info = mock_info(env.contract.address.as_str(), &[]);
+        // A failed submessage is reverted, only its reply is kept
+        let snapshot: Vec<_> = deps.storage.range(None, None, Order::Ascending).collect();
+        match execute(deps.as_mut(), env.clone(), info, msg) {
+            Ok(_) => ack,
+            Err(err) => {
+                deps.storage = MockStorage::new();
+                for (key, value) in snapshot {
+                    deps.storage.set(&key, &value);
+                }
+                let res = reply(
+                    deps.as_mut(),
+                    env,
+                    Reply {
+                        id: STREAM_PACKET_REPLY_ID,
+                        result: SubMsgResult::Err(err.to_string()),
+                    },
+                )
+                .unwrap();
+                from_binary(&res.data.unwrap()).unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn only_opens_unordered_channels_of_the_app() {
+        let mut deps = mock_dependencies();
+        let open = mock_ibc_channel_open_try(CHANNEL, IbcOrder::Ordered, IBC_APP_VERSION);
+        assert_eq!(
+            ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap_err(),
+            ContractError::OnlyUnorderedChannel {}
+        );
+        let open = mock_ibc_channel_open_try(CHANNEL, IbcOrder::Unordered, "ics20-1");
+        assert_eq!(
+            ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap_err(),
+            ContractError::InvalidIbcVersion {
+                version: "ics20-1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn packet_creates_stream_from_offered_liquidity() {
+        let mut deps = setup();
+        let ack = relay(&mut deps, &packet(400, "alice"));
+        let result = StreamPacketResult {
+            stream_idx: 1,
+            pay_to: "juno1provider".to_string(),
+        };
+        assert_eq!(ack, StreamPacketAck::Result(to_binary(&result).unwrap()));
+        assert_eq!(balance(deps.as_ref(), "provider"), (600, 0));
+        assert_eq!(balance(deps.as_ref(), "bob"), (0, 400));
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StreamsByIndex { index: 1 },
+        )
+        .unwrap();
+        let stream = from_binary::<StreamsResponse>(&res)
+            .unwrap()
+            .streams
+            .remove(0);
+        assert_eq!(stream.sender, Addr::unchecked("bob"));
+        assert_eq!(stream.recipient, Addr::unchecked("alice"));
+        assert_eq!(stream.deposit, Uint256::from(400u128));
+        assert_eq!(stream.cancel_policy, CancelPolicy::default());
+
+        // Without a sender on this chain the stream is the channel's and can't be cancelled
+        let mut packet = packet(100, "alice");
+        packet.remote_sender = None;
+        relay(&mut deps, &packet);
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StreamsByIndex { index: 2 },
+        )
+        .unwrap();
+        let stream = from_binary::<StreamsResponse>(&res)
+            .unwrap()
+            .streams
+            .remove(0);
+        assert_eq!(stream.sender, channel_account(CHANNEL));
+        assert_eq!(stream.cancel_policy.cancelable_by, CancelableBy::None);
+    }
+
+    #[test]
+    fn failed_packet_is_acknowledged_with_an_error_and_keeps_the_liquidity() {
+        let mut deps = setup();
+        // More than the provider has left
+        let ack = relay(&mut deps, &packet(1500, "alice"));
+        assert_eq!(
+            ack,
+            StreamPacketAck::Error(
+                ContractError::NoChannelLiquidity {
+                    channel_id: CHANNEL.to_string(),
+                    source_denom: "uusdc".to_string(),
+                    amount: Uint128::new(1500),
+                }
+                .to_string()
+            )
+        );
+
+        // The stream itself can't be created, the liquidity moved for it is put back
+        let ack = relay(&mut deps, &packet(400, ""));
+        assert!(matches!(ack, StreamPacketAck::Error(_)));
+        assert_eq!(balance(deps.as_ref(), "provider"), (1000, 0));
+
+        // Nothing is offered for other denoms
+        let mut other = packet(400, "alice");
+        other.source_denom = "uatom".to_string();
+        assert!(matches!(
+            relay(&mut deps, &other),
+            StreamPacketAck::Error(_)
+        ));
+
+        // Only the contract itself creates the streams of packets
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::ReceiveStreamPacket {
+                channel_id: CHANNEL.to_string(),
+                provider: "provider".to_string(),
+                packet: packet(400, "alice"),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn provider_withdraws_liquidity() {
+        let mut deps = setup();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::WithdrawBalance {
+                asset: cw_asset::Asset::native(DENOM, 700u128),
+            },
+        )
+        .unwrap();
+        assert_eq!(balance(deps.as_ref(), "provider"), (300, 0));
+        assert!(matches!(
+            relay(&mut deps, &packet(400, "alice")),
+            StreamPacketAck::Error(_)
+        ));
+
+        // Without an offer the remaining balance isn't used either
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::OfferChannelLiquidity {
+                channel_id: CHANNEL.to_string(),
+                offer: None,
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            relay(&mut deps, &packet(100, "alice")),
+            StreamPacketAck::Error(_)
+        ));
+        assert_eq!(balance(deps.as_ref(), "provider"), (300, 0));
+    }
+}
@@ -0,0 +1,414 @@
+// Streams created straight from an ICS-20 transfer through the ibc-hooks middleware
+// The memo of the transfer calls `CreateStreamFromIbc`, which ibc-hooks executes as an intermediate address derived
+// from the channel and the sender on the source chain. That address funds the stream from its prepaid balance, so
+// whatever a cancel or sweep returns lands there and can be sent back to the original sender with `ReturnIbcBalance`.
+// Returns which fail or time out are reported by ibc-hooks through `sudo` and credited back to the balance
+use bech32::{ToBase32, Variant};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Binary, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, Uint128,
+};
+use cw_asset::AssetInfo;
+use cw_utils::one_coin;
+use prost::Message;
+use sha2::{Digest, Sha256};
+
+use crate::contract::{deposit_balance, try_create_stream, update_sender_balance};
+use crate::msg::StreamOptions;
+use crate::state::{IbcSender, StreamData, IBC_RETURNS, IBC_SENDERS, PENDING_IBC_RETURN};
+use crate::ContractError;
+
+// Reply id of the ICS-20 transfer returning a balance, its response holds the packet sequence
+pub const IBC_RETURN_REPLY_ID: u64 = 2;
+// Prefix ibc-hooks hashes the channel and original sender with
+const SENDER_PREFIX: &str = "ibc-wasm-hook-intermediary";
+// How long a returning transfer has to be relayed
+const RETURN_TIMEOUT_SECONDS: u64 = 60 * 60;
+
+/// Callbacks ibc-hooks sends for the transfers of this contract which set `ibc_callback` in their memo
+#[cw_serde]
+pub enum SudoMsg {
+    IbcLifecycleComplete(IbcLifecycleComplete),
+}
+
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoCoin {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+/// ibc.applications.transfer.v1.MsgTransfer, timing out by timestamp only
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgTransfer {
+    #[prost(string, tag = "1")]
+    pub source_port: String,
+    #[prost(string, tag = "2")]
+    pub source_channel: String,
+    #[prost(message, optional, tag = "3")]
+    pub token: Option<ProtoCoin>,
+    #[prost(string, tag = "4")]
+    pub sender: String,
+    #[prost(string, tag = "5")]
+    pub receiver: String,
+    #[prost(uint64, tag = "7")]
+    pub timeout_timestamp: u64,
+    #[prost(string, tag = "8")]
+    pub memo: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgTransferResponse {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+}
+
+/// The address ibc-hooks executes as for `original_sender` coming in over `channel_id`,
+/// encoded with the bech32 prefix of this chain
+pub fn derive_intermediate_sender(
+    channel_id: &str,
+    original_sender: &str,
+    bech32_prefix: &str,
+) -> Result<String, ContractError> {
+    let prefix_hash = Sha256::digest(SENDER_PREFIX.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(prefix_hash);
+    hasher.update(format!("{}/{}", channel_id, original_sender).as_bytes());
+    let hash = hasher.finalize();
+    bech32::encode(bech32_prefix, hash.to_base32(), Variant::Bech32)
+        .map_err(|_| ContractError::InvalidIbcSender {})
+}
+
+/// The human readable part of a bech32 `address`, none if it isn't one
+pub fn bech32_prefix(address: &str) -> Option<String> {
+    bech32::decode(address).ok().map(|(prefix, _, _)| prefix)
+}
+
+// Creates a stream from the funds of the transfer which called this, paying them into the prepaid balance of
+// the stream sender first. That is `sender` when the transfer names one, or else the intermediate sender.
+// Any error fails the transfer itself so the source chain refunds it
+#[allow(clippy::too_many_arguments)]
+pub fn create_stream_from_ibc(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    original_sender: String,
+    sender: Option<String>,
+    recipient: String,
+    mut stream_data: StreamData,
+) -> Result<Response, ContractError> {
+    let prefix = info
+        .sender
+        .as_str()
+        .rsplit_once('1')
+        .map(|(prefix, _)| prefix)
+        .ok_or(ContractError::InvalidIbcSender {})?;
+    if derive_intermediate_sender(&channel_id, &original_sender, prefix)? != info.sender {
+        return Err(ContractError::InvalidIbcSender {});
+    }
+    let payment = one_coin(&info).map_err(|_| ContractError::NotEnoughAvailableFunds {})?;
+    let asset = AssetInfo::Native(payment.denom.clone());
+    let stream_sender = match sender {
+        Some(sender) => deps.api.addr_validate(&sender)?,
+        None => info.sender.clone(),
+    };
+
+    deposit_balance(deps.storage, &stream_sender, &asset, payment.amount)?;
+    IBC_SENDERS.save(
+        deps.storage,
+        &info.sender,
+        &IbcSender {
+            channel_id,
+            original_sender,
+        },
+    )?;
+    stream_data.options = Some(StreamOptions {
+        from_balance: Some(true),
+        ..stream_data.options.unwrap_or_default()
+    });
+    let response = try_create_stream(
+        deps.branch(),
+        env,
+        MessageInfo {
+            sender: stream_sender,
+            funds: info.funds,
+        },
+        recipient,
+        payment.amount,
+        asset,
+        stream_data,
+    )?;
+
+    Ok(response.add_attribute("ibc_hooks", "create_stream_from_ibc"))
+}
+
+// Sends the free balance an intermediate sender holds in `denom` back to its original sender, anyone can do this
+pub fn return_ibc_balance(
+    deps: DepsMut,
+    env: Env,
+    sender: String,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let sender = Addr::unchecked(sender);
+    let ibc_sender = IBC_SENDERS
+        .may_load(deps.storage, &sender)?
+        .ok_or(ContractError::InvalidIbcSender {})?;
+    let asset = AssetInfo::Native(denom.clone());
+    let mut amount = Uint128::zero();
+    update_sender_balance(deps.storage, &sender, &asset, |mut balance| {
+        amount = balance.free;
+        balance.free = Uint128::zero();
+        Ok(balance)
+    })?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let coin = Coin { denom, amount };
+    PENDING_IBC_RETURN.save(
+        deps.storage,
+        &(sender.clone(), ibc_sender.channel_id.clone(), coin.clone()),
+    )?;
+
+    let transfer = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: ibc_sender.channel_id,
+        token: Some(ProtoCoin {
+            denom: coin.denom.clone(),
+            amount: coin.amount.to_string(),
+        }),
+        sender: env.contract.address.to_string(),
+        receiver: ibc_sender.original_sender.clone(),
+        timeout_timestamp: env.block.time.plus_seconds(RETURN_TIMEOUT_SECONDS).nanos(),
+        memo: format!(r#"{{"ibc_callback":"{}"}}"#, env.contract.address),
+    };
+    let msg = CosmosMsg::Stargate {
+        type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(),
+        value: Binary::from(transfer.encode_to_vec()),
+    };
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(msg, IBC_RETURN_REPLY_ID))
+        .add_attribute("method", "return_ibc_balance")
+        .add_attribute("to", ibc_sender.original_sender)
+        .add_attribute("amount", coin.to_string()))
+}
+
+// Remembers the sequence of a returning transfer so its outcome can be matched in `sudo`
+pub fn register_return(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let data = msg
+        .result
+        .into_result()
+        .map_err(|_| ContractError::InvalidIbcSender {})?
+        .data
+        .unwrap_or_default();
+    let response = MsgTransferResponse::decode(data.as_slice())
+        .map_err(|_| ContractError::InvalidIbcSender {})?;
+    let (sender, channel_id, coin) = PENDING_IBC_RETURN.load(deps.storage)?;
+    PENDING_IBC_RETURN.remove(deps.storage);
+    IBC_RETURNS.save(
+        deps.storage,
+        (&channel_id, response.sequence),
+        &(sender, coin),
+    )?;
+
+    Ok(Response::new().add_attribute("sequence", response.sequence.to_string()))
+}
+
+// A return which was acknowledged with an error or timed out was refunded to this contract by ICS-20,
+// it goes back to the balance of the intermediate sender to be returned again later
+pub fn ibc_lifecycle_complete(
+    deps: DepsMut,
+    msg: IbcLifecycleComplete,
+) -> Result<Response, ContractError> {
+    let (channel, sequence, success) = match msg {
+        IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            success,
+            ..
+        } => (channel, sequence, success),
+        IbcLifecycleComplete::IbcTimeout { channel, sequence } => (channel, sequence, false),
+    };
+    let (sender, coin) = match IBC_RETURNS.may_load(deps.storage, (&channel, sequence))? {
+        Some(pending) => pending,
+        None => return Ok(Response::new()),
+    };
+    IBC_RETURNS.remove(deps.storage, (&channel, sequence));
+    if !success {
+        deposit_balance(
+            deps.storage,
+            &sender,
+            &AssetInfo::Native(coin.denom.clone()),
+            coin.amount,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "ibc_lifecycle_complete")
+        .add_attribute("sequence", sequence.to_string())
+        .add_attribute("returned", success.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{execute, instantiate, reply, sudo};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::state::{StreamTime, SENDER_BALANCES};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coins, Deps, SubMsgResponse, SubMsgResult};
+
+    const CHANNEL: &str = "channel-42";
+    const REMOTE_SENDER: &str = "axelar1remotesender";
+    const DENOM: &str = "ibc/axlusdc";
+
+    fn intermediate() -> String {
+        derive_intermediate_sender(CHANNEL, REMOTE_SENDER, "osmo").unwrap()
+    }
+
+    fn create_msg(channel_id: &str) -> ExecuteMsg {
+        let now = mock_env().block.time;
+        ExecuteMsg::CreateStreamFromIbc {
+            channel_id: channel_id.to_string(),
+            original_sender: REMOTE_SENDER.to_string(),
+            sender: None,
+            recipient: "alice".to_string(),
+            start_time: Some(StreamTime::Time(now)),
+            stop_time: Some(StreamTime::Time(now.plus_seconds(100))),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            options: None,
+        }
+    }
+
+    fn balance(deps: Deps, sender: &str) -> (u128, u128) {
+        let balance = SENDER_BALANCES
+            .load(
+                deps.storage,
+                (&Addr::unchecked(sender), &format!("native:{}", DENOM)),
+            )
+            .unwrap();
+        (balance.free.u128(), balance.committed.u128())
+    }
+
+    #[test]
+    fn derives_the_ibc_hooks_sender() {
+        let derived = intermediate();
+        assert!(derived.starts_with("osmo1"));
+        assert_ne!(
+            derived,
+            derive_intermediate_sender("channel-43", REMOTE_SENDER, "osmo").unwrap()
+        );
+    }
+
+    #[test]
+    fn creates_stream_from_transfer() {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                count: 0,
+                governance_asset: None,
+            },
+        )
+        .unwrap();
+
+        // Only the address ibc-hooks derives for the channel and sender can use this path
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(&intermediate(), &coins(400, DENOM)),
+            create_msg("channel-43"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidIbcSender {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(&intermediate(), &coins(400, DENOM)),
+            create_msg(CHANNEL),
+        )
+        .unwrap();
+        assert_eq!(balance(deps.as_ref(), &intermediate()), (0, 400));
+
+        // Cancelling returns the sender part to the balance, from where it goes back over IBC
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::CancelStream { stream_idx: 1 },
+        )
+        .unwrap();
+        assert_eq!(balance(deps.as_ref(), &intermediate()), (400, 0));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::ReturnIbcBalance {
+                sender: intermediate(),
+                denom: DENOM.to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages[0].id, IBC_RETURN_REPLY_ID);
+        assert_eq!(balance(deps.as_ref(), &intermediate()), (0, 0));
+
+        // The transfer is assigned sequence 7 and times out
+        let data = MsgTransferResponse { sequence: 7 }.encode_to_vec();
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: IBC_RETURN_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(Binary::from(data)),
+                }),
+            },
+        )
+        .unwrap();
+        sudo(
+            deps.as_mut(),
+            mock_env(),
+            SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+                channel: CHANNEL.to_string(),
+                sequence: 7,
+            }),
+        )
+        .unwrap();
+        assert_eq!(balance(deps.as_ref(), &intermediate()), (400, 0));
+
+        // A timeout reported twice credits nothing more
+        sudo(
+            deps.as_mut(),
+            mock_env(),
+            SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+                channel: CHANNEL.to_string(),
+                sequence: 7,
+            }),
+        )
+        .unwrap();
+        assert_eq!(balance(deps.as_ref(), &intermediate()), (400, 0));
+    }
+}
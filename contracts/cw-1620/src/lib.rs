@@ -1,8 +1,17 @@
+pub mod airdrop;
 pub mod contract;
 pub mod curve_helpers;
 mod error;
+pub mod fiat;
+pub mod ibc;
+pub mod ibc_hooks;
+pub mod milestones;
 pub mod msg;
+pub mod offers;
+pub mod staking;
 pub mod state;
+pub mod vault;
+pub mod voting;
 pub use crate::error::ContractError;
 
 #[cfg(test)]
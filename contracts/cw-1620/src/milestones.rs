@@ -0,0 +1,150 @@
+// Milestone streams for grants and contractors
+// The deposit is split into milestones, each approved by its own set of approvers. A milestone vests, at once or over
+// its vesting period, only after enough approvers signed off, and its amount goes back to the sender if it is not
+// approved before its deadline
+use cosmwasm_std::{Api, DepsMut, Env, MessageInfo, Response, Uint128};
+
+use crate::contract::{ensure_open, release_stream_funds, save_stream};
+use crate::state::{
+    payment_streams, Milestone, MilestoneStatus, MilestoneTerms, PaymentStream, StreamTime,
+};
+use crate::vault;
+use crate::ContractError;
+
+/// Validates the milestones of a new stream, together they have to add up to the deposit
+pub fn validate_milestones(
+    api: &dyn Api,
+    start_time: &StreamTime,
+    deposit: Uint128,
+    terms: &[MilestoneTerms],
+) -> Result<Vec<Milestone>, ContractError> {
+    let mut total = Uint128::zero();
+    let mut milestones = vec![];
+    for milestone in terms {
+        let mut approvers = milestone
+            .approvers
+            .iter()
+            .map(|approver| api.addr_validate(approver))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = approvers.len();
+        approvers.sort();
+        approvers.dedup();
+        let threshold = milestone.threshold.unwrap_or(count as u32);
+        if milestone.amount.is_zero()
+            || approvers.len() != count
+            || threshold == 0
+            || threshold as usize > count
+        {
+            return Err(ContractError::InvalidMilestones {});
+        }
+        // Vesting runs on the same clock as the stream
+        if let Some(vesting) = &milestone.vesting {
+            start_time
+                .after(vesting)
+                .ok_or(ContractError::MismatchedStreamUnits {})?;
+        }
+        total = total.checked_add(milestone.amount)?;
+        milestones.push(Milestone {
+            amount: milestone.amount,
+            approvers,
+            threshold,
+            approvals: vec![],
+            vesting: milestone.vesting,
+            deadline: milestone.deadline,
+            status: MilestoneStatus::Pending,
+        });
+    }
+    if milestones.is_empty() || total != deposit {
+        return Err(ContractError::InvalidMilestones {});
+    }
+    Ok(milestones)
+}
+
+fn load_milestone(
+    stream: &mut PaymentStream,
+    milestone: u32,
+) -> Result<&mut Milestone, ContractError> {
+    stream
+        .milestones
+        .get_mut(milestone as usize)
+        .ok_or(ContractError::MilestoneNotFound { milestone })
+}
+
+// Records the approval of one of the approvers of a milestone, the milestone starts vesting once enough approved
+pub fn approve_milestone(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+    milestone: u32,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    let now = stream.start_time.current(&env.block);
+    let entry = load_milestone(&mut stream, milestone)?;
+    if entry.status != MilestoneStatus::Pending {
+        return Err(ContractError::MilestoneNotPending { milestone });
+    }
+    if !entry.approvers.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if entry.approvals.contains(&info.sender) {
+        return Err(ContractError::AlreadyApproved {});
+    }
+    if entry.deadline.is_expired(&env.block) {
+        return Err(ContractError::MilestoneDeadlinePassed { milestone });
+    }
+    entry.approvals.push(info.sender.clone());
+    let approved = entry.approvals.len() >= entry.threshold as usize;
+    if approved {
+        entry.status = MilestoneStatus::Approved { at: now };
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "approve_milestone")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("milestone", milestone.to_string())
+        .add_attribute("approved", approved.to_string()))
+}
+
+// Returns the amount of a milestone nobody approved in time to the sender, only the sender can do this
+pub fn reclaim_milestone(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_idx: u64,
+    milestone: u32,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    if info.sender != stream.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let entry = load_milestone(&mut stream, milestone)?;
+    if entry.status != MilestoneStatus::Pending {
+        return Err(ContractError::MilestoneNotPending { milestone });
+    }
+    if !entry.deadline.is_expired(&env.block) {
+        return Err(ContractError::MilestoneDeadlineNotPassed { milestone });
+    }
+    entry.status = MilestoneStatus::Reclaimed;
+    let amount = entry.amount;
+
+    // The amount leaves the schedule entirely, so what vested and what was claimed still add up. What the vault
+    // lost is written off first and only what is left of the balance goes back
+    let (pull, _) = vault::pull(deps.branch(), &env, &mut stream, amount)?;
+    let returned = stream.balance_up_to(amount);
+    let refund = release_stream_funds(deps.storage, &stream, Uint128::zero(), returned)?;
+    stream.deposit = stream.deposit.checked_sub(amount.into())?;
+    stream.remaining_balance = stream.remaining_balance.checked_sub(returned.into())?;
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(pull)
+        .add_messages(refund)
+        .add_attribute("method", "reclaim_milestone")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("milestone", milestone.to_string())
+        .add_attribute("sender_amount", returned))
+}
@@ -1,46 +1,344 @@
-use crate::state::{PaymentStream, StreamType};
+use crate::ibc::StreamPacket;
+use crate::state::{
+    Airdrop, CancelPolicy, ChannelOffer, PaymentStream, RewardsTo, StreamTime, StreamType,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Timestamp, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128, Uint256};
+use cw2::ContractVersion;
 use cw20::Cw20ReceiveMsg;
 use cw_asset::{Asset, AssetInfo};
+use cw_utils::{Duration, Expiration};
+use white_whale::pool_network::asset::Asset as WwAsset;
 use wynd_utils::Curve;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub count: i32,
+    /// Only streams of this asset count for voting power, by default every stream does
+    pub governance_asset: Option<AssetInfo>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Sets the asset whose streams count for voting power, every stream counts without one.
+    /// The voting power is rebuilt afterwards with `RebuildVotingPower`
+    pub governance_asset: Option<AssetInfo>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
+    /// Create a stream from start_time to stop_time, from start_time for a duration, or with only a duration
+    /// in which case the stream is Pending until activated and any curve is relative to the activation
     CreateStream {
         recipient: String,
         asset: Asset,
-        start_time: u64,
-        stop_time: u64,
+        start_time: Option<StreamTime>,
+        stop_time: Option<StreamTime>,
+        duration: Option<Duration>,
         stream_type: Option<StreamType>,
         curve: Option<Curve>,
+        options: Option<StreamOptions>,
     },
+    /// Claim from a stream, by default funds go to the withdrawal address if set or else to the recipient.
+    /// The recipient can send them `to` another address instead, and with `msg` into a contract as a callback
     ClaimFromStream {
-        recipient: String,
         amount: Uint128,
         denom: String,
         stream_idx: Option<u64>,
+        to: Option<String>,
+        msg: Option<Binary>,
+    },
+    /// Start a Pending stream, callable by the sender or the stream's activator
+    ActivateStream {
+        stream_idx: u64,
     },
     CancelStream {
         stream_idx: u64,
     },
+    /// Move a stream to a new recipient, callable by the recipient and by the sender if allowed at creation
+    TransferStream {
+        stream_idx: u64,
+        new_recipient: String,
+    },
+    /// Authorize an address to claim for the recipient, funds still go to the recipient or their withdrawal address.
+    /// Passing no operator removes the current one, without expires the operator never expires
+    SetClaimOperator {
+        stream_idx: u64,
+        operator: Option<String>,
+        expires: Option<Expiration>,
+    },
+    /// Send claimed funds to another address than the recipient, passing no address resets it
+    SetWithdrawalAddress {
+        stream_idx: u64,
+        address: Option<String>,
+    },
+    /// Carve part of what is still to vest out of a stream into a new stream to `new_recipient`.
+    /// The new stream follows the same schedule so together both vest exactly what the original would have
+    SplitStream {
+        stream_idx: u64,
+        amount_or_ratio: AmountOrRatio,
+        new_recipient: String,
+    },
+    /// Combine streams with the same sender, recipient and asset into the first of them.
+    /// The merged stream vests the sum of the originals, which are removed
+    MergeStreams {
+        stream_idxs: Vec<u64>,
+    },
+    /// Accept a stream created with `requires_acceptance`, only the recipient can do this
+    AcceptStream {
+        stream_idx: u64,
+    },
+    /// Take back the funds of a stream which was not accepted before its deadline, only the sender can do this
+    ReclaimStream {
+        stream_idx: u64,
+    },
+    /// Return everything the recipient did not claim before the claim deadline to the sender
+    SweepExpired {
+        stream_idx: u64,
+    },
+    /// Fund a merkle airdrop with the native funds sent along, each eligible address claims its own stream
+    /// following the schedule given here. A curve is a template scaled to each claimed amount. Claims are
+    /// accepted until the expiration, after which the creator can reclaim what is left
+    RegisterAirdrop {
+        merkle_root: String,
+        asset: Asset,
+        start_time: StreamTime,
+        stop_time: StreamTime,
+        curve: Option<Curve>,
+        expiration: Option<Expiration>,
+    },
+    /// Prove `amount` is allocated to the caller in an airdrop and create their stream
+    ClaimAirdropStream {
+        airdrop_id: u64,
+        amount: Uint128,
+        proof: Vec<String>,
+    },
+    /// Return what nobody claimed from an expired airdrop to its creator, only the creator can do this
+    ReclaimAirdrop {
+        airdrop_id: u64,
+    },
+    /// Set the secp256k1 public key stream offers of the sender are signed with
+    RegisterOfferKey {
+        public_key: Binary,
+    },
+    /// Create the stream of an offer signed by its sender, anyone holding the signature can submit it.
+    /// The stream is funded from the prepaid balance of the sender
+    SubmitSignedOffer {
+        offer: StreamOffer,
+        signature: Binary,
+    },
+    /// Invalidate an offer the sender signed before it is submitted
+    RevokeOffer {
+        nonce: u64,
+    },
+    /// Top up the prepaid balance of the sender with the native funds sent along
+    Deposit {},
+    /// Withdraw from the free part of the prepaid balance of the sender
+    WithdrawBalance {
+        asset: Asset,
+    },
+    /// Called by ibc-hooks from the wasm memo of an ICS-20 transfer, creates a stream with the transferred funds.
+    /// The caller must be the intermediate sender ibc-hooks derives from `channel_id` and `original_sender`.
+    /// The stream is created for `sender` when set, it gets back what a cancel returns instead of the intermediate sender
+    CreateStreamFromIbc {
+        channel_id: String,
+        original_sender: String,
+        sender: Option<String>,
+        recipient: String,
+        start_time: Option<StreamTime>,
+        stop_time: Option<StreamTime>,
+        duration: Option<Duration>,
+        stream_type: Option<StreamType>,
+        curve: Option<Curve>,
+        options: Option<StreamOptions>,
+    },
+    /// Offer the prepaid balance of the sender to pay for the streams asked for over `channel_id`, passing none
+    /// withdraws the offer. The balance stays the sender's and can be withdrawn as usual
+    OfferChannelLiquidity {
+        channel_id: String,
+        offer: Option<ChannelOffer>,
+    },
+    /// Create the stream of a packet received over `channel_id` from the liquidity of `provider`, only this contract
+    /// can call this. A failure is acknowledged to the other chain with an error
+    ReceiveStreamPacket {
+        channel_id: String,
+        provider: String,
+        packet: StreamPacket,
+    },
+    /// Send what an ibc-hooks intermediate sender has left in its balance back to its original sender
+    ReturnIbcBalance {
+        sender: String,
+        denom: String,
+    },
+    /// Undelegate what a staked stream vested so far, it becomes claimable once unbonded. Anyone can call this
+    UndelegateVested {
+        stream_idx: u64,
+    },
+    /// Pay the staking rewards a stream earned to whoever the stream sends them to
+    ClaimStakingRewards {
+        stream_idx: u64,
+    },
+    /// Pay out a cancelled staked stream once its funds finished unbonding
+    SettleUnbonded {
+        stream_idx: u64,
+    },
+    /// Take the rebuild of the voting power started by a migration up to `limit` holders or streams further,
+    /// anyone can call this until it is done
+    RebuildVotingPower {
+        limit: Option<u32>,
+    },
+    /// Pay out what the vault position of a stream earned so far, anyone can call this
+    HarvestYield {
+        stream_idx: u64,
+    },
+    /// Sign off on a milestone of a milestone stream as one of its approvers
+    ApproveMilestone {
+        stream_idx: u64,
+        milestone: u32,
+    },
+    /// Take back the amount of a milestone which was not approved before its deadline, only the sender can do this
+    ReclaimMilestone {
+        stream_idx: u64,
+        milestone: u32,
+    },
+    /// Add to the deposit of a recurring stream so it keeps paying its periods, only the sender can do this.
+    /// Streams funded from the prepaid balance draw `amount` from it, others take the native funds sent along.
+    /// A stream which already lapsed can not be topped up
+    TopUpStream {
+        stream_idx: u64,
+        amount: Option<Uint128>,
+    },
+    /// Add the native funds sent along to the collateral of a fiat stream, only the sender can do this
+    AddCollateral {
+        stream_idx: u64,
+    },
+    /// Take back the collateral of a fiat stream which is not needed to pay what it still owes once its schedule is
+    /// over, only the sender can do this
+    WithdrawCollateral {
+        stream_idx: u64,
+    },
+    /// Add and remove vaults streams can deposit into, only the owner can do this
+    UpdateVaultAllowlist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Change the owner, the fee settings or the unbonding period of the chain in seconds, only the owner can do this.
+    /// Without an unbonding period the contract queries the staking params of the chain
+    UpdateConfig {
+        owner: Option<String>,
+        fee_asset: Option<WwAsset>,
+        fees: Option<Vec<Decimal>>,
+        unbonding_period: Option<u64>,
+    },
 }
 
 #[cw_serde]
 pub enum Cw20HookMsg {
     CreateStream {
         recipient: String,
-        start_time: u64,
-        stop_time: u64,
+        start_time: Option<StreamTime>,
+        stop_time: Option<StreamTime>,
+        duration: Option<Duration>,
         stream_type: Option<StreamType>,
         curve: Option<Curve>,
+        options: Option<StreamOptions>,
+    },
+    /// Fund a merkle airdrop with the tokens sent along
+    RegisterAirdrop {
+        merkle_root: String,
+        start_time: StreamTime,
+        stop_time: StreamTime,
+        curve: Option<Curve>,
+        expiration: Option<Expiration>,
     },
+    /// Top up the prepaid balance of the sender of the tokens
+    Deposit {},
+    /// Add the tokens sent along to the deposit of a recurring stream of the sender
+    TopUpStream { stream_idx: u64 },
+}
+
+/// A stream a sender offers by signing the sha256 hash of this message encoded as JSON
+#[cw_serde]
+pub struct StreamOffer {
+    pub sender: String,
+    pub recipient: String,
+    pub asset: Asset,
+    pub start_time: Option<StreamTime>,
+    pub stop_time: Option<StreamTime>,
+    pub duration: Option<Duration>,
+    pub stream_type: Option<StreamType>,
+    pub curve: Option<Curve>,
+    /// Each nonce of a sender can be used once
+    pub nonce: u64,
+    pub expires: Expiration,
+    /// The contract and chain the offer is meant for
+    pub contract: String,
+    pub chain_id: String,
+}
+
+/// How much of a stream to split off, a fixed amount or a ratio of what is still to vest
+#[cw_serde]
+pub enum AmountOrRatio {
+    Amount(Uint128),
+    Ratio(Decimal),
+}
+
+/// Optional settings for a new stream, everything here has a sensible default
+#[cw_serde]
+#[derive(Default)]
+pub struct StreamOptions {
+    /// An address allowed to activate a deferred stream besides the sender
+    pub activator: Option<String>,
+    /// Who can cancel the stream and with how much notice, by default sender and recipient can cancel right away
+    pub cancel_policy: Option<CancelPolicy>,
+    /// Allow the sender to transfer the stream to another recipient
+    pub sender_can_transfer: Option<bool>,
+    /// Notify a recipient contract of stream events with a `StreamReceiveMsg`, turned off when the stream is transferred
+    pub notify_recipient: Option<bool>,
+    /// Split the stream between several recipients, each with a share of what vests.
+    /// Shares must add up to 1 and include the stream recipient
+    pub shares: Option<Vec<(String, Decimal)>>,
+    /// Draw the deposit from the prepaid balance of the sender instead of funds sent along
+    pub from_balance: Option<bool>,
+    /// Keep the stream Pending until the recipient accepts it
+    pub requires_acceptance: Option<bool>,
+    /// When the sender can take back a stream which was not accepted, required with `requires_acceptance`
+    pub acceptance_deadline: Option<Expiration>,
+    /// After this the recipient can no longer claim and the sender can sweep what is left with `SweepExpired`
+    pub claim_deadline: Option<Expiration>,
+    /// Delegate the locked funds while they vest, only for streams paying the staking denom
+    pub stake: Option<StakeOptions>,
+    /// Deposit the balance into an allowlisted vault until it is paid out, only for native streams
+    pub vault: Option<VaultOptions>,
+    /// Denominate the stream in a fiat currency, the deposit then only serves as collateral
+    pub fiat: Option<FiatOptions>,
+}
+
+#[cw_serde]
+pub struct StakeOptions {
+    pub validator: String,
+    /// By default rewards go to the recipient
+    pub rewards_to: Option<RewardsTo>,
+}
+
+/// A stream of `amount` of a fiat currency over its schedule, paid in the stream asset at the price of `oracle`
+#[cw_serde]
+pub struct FiatOptions {
+    pub oracle: String,
+    pub currency: String,
+    pub amount: Uint128,
+    /// How old a price can be in seconds
+    pub max_staleness: u64,
+    /// The widest confidence interval accepted, as a part of the price
+    pub tolerance: Decimal,
+}
+
+#[cw_serde]
+pub struct VaultOptions {
+    pub vault: String,
+    /// Part of the yield paid to the recipient, by default all of it goes to the sender
+    pub recipient_yield: Option<Decimal>,
 }
 
 #[cw_serde]
@@ -67,6 +365,46 @@ pub enum QueryMsg {
 
     #[returns(StreamClaimableAmtResponse)]
     StreamClaimableAmount { index: u64 },
+
+    #[returns(SenderBalanceResponse)]
+    SenderBalance { sender: String, asset: AssetInfo },
+
+    #[returns(AirdropResponse)]
+    Airdrop { airdrop_id: u64 },
+
+    #[returns(ConfigResponse)]
+    Config {},
+
+    /// What the streams of `address` still owe it at `height`, by default the current height
+    #[returns(VotingPowerAtHeightResponse)]
+    VotingPowerAtHeight {
+        address: String,
+        height: Option<u64>,
+    },
+
+    #[returns(TotalPowerAtHeightResponse)]
+    TotalPowerAtHeight { height: Option<u64> },
+
+    /// The owner, acting as the DAO of the voting module
+    #[returns(Addr)]
+    Dao {},
+
+    #[returns(InfoResponse)]
+    Info {},
+
+    #[returns(AllowedVaultsResponse)]
+    AllowedVaults {},
+
+    /// What a fiat stream owes at the current oracle price and whether its collateral still covers it
+    #[returns(FiatStatusResponse)]
+    FiatStatus { stream_idx: u64 },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub fee_asset: Option<WwAsset>,
+    pub fees: Option<Vec<Decimal>>,
 }
 
 // We define a custom struct for each query response
@@ -89,7 +427,62 @@ pub struct StreamsResponse {
 
 #[cw_serde]
 pub struct StreamClaimableAmtResponse {
-    pub amount_available: Uint128,
-    pub amount_streamed: Uint128,
+    pub amount_available: Uint256,
+    pub amount_streamed: Uint256,
     pub stream: PaymentStream,
+    pub claim_deadline: Option<Expiration>,
+    /// What the sender can sweep back, only once the claim deadline passed
+    pub amount_sweepable: Uint256,
+}
+
+#[cw_serde]
+pub struct SenderBalanceResponse {
+    /// Available to fund new streams or to be withdrawn
+    pub free: Uint128,
+    /// Locked in streams funded from the balance
+    pub committed: Uint128,
+}
+
+#[cw_serde]
+pub struct AirdropResponse {
+    pub airdrop: Airdrop,
+}
+
+#[cw_serde]
+pub struct VotingPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct TotalPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+/// Amounts in the currency of the stream unless said otherwise
+#[cw_serde]
+pub struct FiatStatusResponse {
+    /// Current oracle price of the asset, none when the oracle is stale or too uncertain
+    pub price: Option<Decimal>,
+    pub currency: String,
+    pub vested: Uint128,
+    pub paid: Uint128,
+    pub owed: Uint128,
+    /// What the recipient can claim now, in the asset
+    pub amount_available: Uint128,
+    /// What is left of the deposit, in the asset
+    pub collateral: Uint256,
+    /// How much more of the asset it takes to pay the rest of the stream at the current price
+    pub shortfall: Uint128,
+}
+
+#[cw_serde]
+pub struct AllowedVaultsResponse {
+    pub vaults: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct InfoResponse {
+    pub info: ContractVersion,
 }
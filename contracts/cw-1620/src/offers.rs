@@ -0,0 +1,103 @@
+// Stream offers signed off-chain by a sender
+// A sender registers a secp256k1 public key once, after which anyone holding one of its signed offers can submit it
+// and the stream is created from the prepaid balance of the sender
+use cosmwasm_std::{to_binary, Binary, DepsMut, Empty, Env, MessageInfo, Response};
+use sha2::{Digest, Sha256};
+
+use crate::contract::try_create_stream;
+use crate::msg::{StreamOffer, StreamOptions};
+use crate::state::{StreamData, OFFER_KEYS, USED_OFFER_NONCES};
+use crate::ContractError;
+
+// Sets the key offers of the sender are verified with, replacing any previous key
+pub fn register_offer_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    public_key: Binary,
+) -> Result<Response, ContractError> {
+    // Only compressed or uncompressed secp256k1 public keys are accepted
+    if public_key.len() != 33 && public_key.len() != 65 {
+        return Err(ContractError::InvalidSignature {});
+    }
+    OFFER_KEYS.save(deps.storage, &info.sender, &public_key)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "register_offer_key")
+        .add_attribute("sender", info.sender))
+}
+
+// Burns a nonce so the offer signed with it can no longer be submitted
+pub fn revoke_offer(
+    deps: DepsMut,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response, ContractError> {
+    if USED_OFFER_NONCES.has(deps.storage, (&info.sender, nonce)) {
+        return Err(ContractError::NonceUsed { nonce });
+    }
+    USED_OFFER_NONCES.save(deps.storage, (&info.sender, nonce), &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("method", "revoke_offer")
+        .add_attribute("nonce", nonce.to_string()))
+}
+
+// Verifies a signed offer and creates its stream out of the prepaid balance of the sender
+// The signature covers the sha256 hash of the JSON encoded offer, which names this contract and chain so
+// it can't be replayed elsewhere, and every nonce can only be used once
+pub fn submit_signed_offer(
+    deps: DepsMut,
+    env: Env,
+    offer: StreamOffer,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    let sender = deps.api.addr_validate(&offer.sender)?;
+    if offer.contract != env.contract.address.as_str() || offer.chain_id != env.block.chain_id {
+        return Err(ContractError::InvalidSignature {});
+    }
+    if offer.expires.is_expired(&env.block) {
+        return Err(ContractError::OfferExpired {});
+    }
+    if USED_OFFER_NONCES.has(deps.storage, (&sender, offer.nonce)) {
+        return Err(ContractError::NonceUsed { nonce: offer.nonce });
+    }
+    let public_key = OFFER_KEYS
+        .may_load(deps.storage, &sender)?
+        .ok_or(ContractError::Unauthorized {})?;
+    let hash = Sha256::digest(to_binary(&offer)?.as_slice());
+    let valid = deps
+        .api
+        .secp256k1_verify(&hash, &signature, &public_key)
+        .map_err(|_| ContractError::InvalidSignature {})?;
+    if !valid {
+        return Err(ContractError::InvalidSignature {});
+    }
+    USED_OFFER_NONCES.save(deps.storage, (&sender, offer.nonce), &Empty {})?;
+
+    // The stream is created as if the sender did it, funded from its prepaid balance
+    let sender_info = MessageInfo {
+        sender,
+        funds: vec![],
+    };
+    let response = try_create_stream(
+        deps,
+        env,
+        sender_info,
+        offer.recipient,
+        offer.asset.amount,
+        offer.asset.info,
+        StreamData {
+            start_time: offer.start_time,
+            stop_time: offer.stop_time,
+            duration: offer.duration,
+            stream_type: offer.stream_type,
+            curve: offer.curve,
+            options: Some(StreamOptions {
+                from_balance: Some(true),
+                ..Default::default()
+            }),
+        },
+    )?;
+
+    Ok(response.add_attribute("nonce", offer.nonce.to_string()))
+}
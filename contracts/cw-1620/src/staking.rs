@@ -0,0 +1,363 @@
+// Streams whose locked funds are delegated while they vest
+// The contract delegates for every staked stream, so rewards are accounted per validator with a reward index and
+// each stream earns on what it keeps delegated. Slashes are found by comparing the delegation of the contract with
+// what it delegated and accounted the same way with a slash factor. Vested funds are undelegated with
+// `UndelegateVested` and can be claimed once unbonded
+use cosmwasm_std::{
+    BlockInfo, Coin, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg, Env, QueryRequest,
+    Response, StakingMsg, StdError, Storage, Uint128,
+};
+use cw_asset::AssetInfo;
+use serde::Deserialize;
+
+use crate::contract::{ensure_open, payout_msg, save_stream};
+use crate::curve_helpers;
+use crate::msg::StakeOptions;
+use crate::state::{
+    payment_streams, PaymentStream, RewardsTo, StreamStaking, Unbonding, ValidatorRewards,
+    UNBONDING_PERIOD, VALIDATOR_REWARDS,
+};
+use crate::ContractError;
+
+const STAKING_PARAMS_PATH: &str = "/cosmos.staking.v1beta1.Query/Params";
+
+// The part of cosmos.staking.v1beta1.QueryParamsResponse needed here, the chain answers in JSON
+#[derive(Deserialize)]
+struct StakingParamsResponse {
+    params: StakingParams,
+}
+
+#[derive(Deserialize)]
+struct StakingParams {
+    // A protobuf duration such as "1814400s"
+    unbonding_time: String,
+}
+
+/// What of a staked stream is liquid by `block`, everything neither delegated, still unbonding nor slashed
+pub fn unbonded(staking: &StreamStaking, deposit: Uint128, block: &BlockInfo) -> Uint128 {
+    let unbonding: Uint128 = staking
+        .unbonding
+        .iter()
+        .filter(|entry| entry.release_at > block.time)
+        .map(|entry| entry.amount)
+        .sum();
+    deposit
+        .saturating_sub(staking.slashed)
+        .saturating_sub(staking.delegated)
+        .saturating_sub(unbonding)
+}
+
+// How long undelegated funds take to unbond, as configured by the owner or else from the staking params of the chain
+fn unbonding_period(deps: Deps) -> Result<u64, ContractError> {
+    if let Some(period) = UNBONDING_PERIOD.may_load(deps.storage)? {
+        return Ok(period);
+    }
+    let response: StakingParamsResponse = deps.querier.query(&QueryRequest::Stargate {
+        path: STAKING_PARAMS_PATH.to_string(),
+        data: Default::default(),
+    })?;
+    let unbonding_time = response.params.unbonding_time;
+    unbonding_time
+        .strip_suffix('s')
+        .and_then(|seconds| seconds.split('.').next())
+        .and_then(|seconds| seconds.parse().ok())
+        .ok_or_else(|| {
+            StdError::generic_err(format!("Invalid unbonding time {unbonding_time}")).into()
+        })
+}
+
+// Brings the reward index and the slash factor of a validator up to date with the delegation of the contract.
+// Rewards are withdrawn, which has to run before any delegation change would withdraw them implicitly, and whatever
+// the delegation is short of what was delegated was slashed
+fn sync_validator(
+    deps: &mut DepsMut,
+    env: &Env,
+    validator: &str,
+) -> Result<(ValidatorRewards, Option<CosmosMsg>), ContractError> {
+    let mut rewards = VALIDATOR_REWARDS
+        .may_load(deps.storage, validator)?
+        .unwrap_or_default();
+    let denom = deps.querier.query_bonded_denom()?;
+    let (earned, delegated) = deps
+        .querier
+        .query_delegation(&env.contract.address, validator)?
+        .map(|delegation| {
+            let earned = delegation
+                .accumulated_rewards
+                .iter()
+                .filter(|coin| coin.denom == denom)
+                .map(|coin| coin.amount)
+                .sum::<Uint128>();
+            (earned, delegation.amount.amount)
+        })
+        .unwrap_or_default();
+
+    let mut withdraw = None;
+    if !earned.is_zero() && !rewards.delegated.is_zero() {
+        rewards.reward_index = rewards
+            .reward_index
+            .checked_add(Decimal::from_ratio(earned, rewards.delegated))?;
+        withdraw = Some(
+            DistributionMsg::WithdrawDelegatorReward {
+                validator: validator.to_string(),
+            }
+            .into(),
+        );
+    }
+    if delegated < rewards.delegated {
+        rewards.slash_factor = rewards
+            .slash_factor
+            .checked_mul(Decimal::from_ratio(delegated, rewards.delegated))?;
+        rewards.delegated = delegated;
+    }
+    VALIDATOR_REWARDS.save(deps.storage, validator, &rewards)?;
+    Ok((rewards, withdraw))
+}
+
+// Accounts the rewards a stream earned and what it lost to slashes since it was last synced with its validator,
+// returning the messages withdrawing the rewards
+fn sync_stream(
+    deps: &mut DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let staking = stream.staking.as_mut().ok_or(ContractError::NotStaked {})?;
+    let (rewards, withdraw) = sync_validator(deps, env, &staking.validator)?;
+    let earned = staking.delegated * (rewards.reward_index - staking.reward_index);
+    staking.pending_rewards = staking.pending_rewards.checked_add(earned)?;
+    staking.reward_index = rewards.reward_index;
+    if rewards.slash_factor < staking.slash_factor {
+        let left = staking.delegated.multiply_ratio(
+            rewards.slash_factor.atomics(),
+            staking.slash_factor.atomics(),
+        );
+        staking.slashed = staking
+            .slashed
+            .checked_add(staking.delegated.checked_sub(left)?)?;
+        staking.delegated = left;
+        staking.slash_factor = rewards.slash_factor;
+    }
+    Ok(withdraw.into_iter().collect())
+}
+
+fn update_validator_delegation(
+    storage: &mut dyn Storage,
+    validator: &str,
+    delegate: Uint128,
+    undelegate: Uint128,
+) -> Result<(), ContractError> {
+    VALIDATOR_REWARDS.update(storage, validator, |rewards| -> Result<_, ContractError> {
+        let mut rewards = rewards.unwrap_or_default();
+        rewards.delegated = rewards
+            .delegated
+            .checked_add(delegate)?
+            .checked_sub(undelegate)?;
+        Ok(rewards)
+    })?;
+    Ok(())
+}
+
+// Undelegates `amount` of a synced stream, returning the message doing so
+fn undelegate(
+    deps: &mut DepsMut,
+    env: &Env,
+    staking: &mut StreamStaking,
+    amount: Uint128,
+) -> Result<CosmosMsg, ContractError> {
+    let period = unbonding_period(deps.as_ref())?;
+    staking.delegated = staking.delegated.checked_sub(amount)?;
+    staking.unbonding.push(Unbonding {
+        amount,
+        release_at: env.block.time.plus_seconds(period),
+    });
+    update_validator_delegation(deps.storage, &staking.validator, Uint128::zero(), amount)?;
+    let denom = deps.querier.query_bonded_denom()?;
+    Ok(StakingMsg::Undelegate {
+        validator: staking.validator.clone(),
+        amount: Coin { denom, amount },
+    }
+    .into())
+}
+
+/// Sets up the delegation of a new stream, which must be in the staking denom and paid in directly.
+/// Streams the sender could take back before they vest, awaiting acceptance, with a claim deadline or milestones,
+/// are not staked
+pub fn delegate_stream(
+    mut deps: DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+    options: StakeOptions,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let denom = deps.querier.query_bonded_denom()?;
+    if stream.token_addr != AssetInfo::Native(denom.clone())
+        || stream.funded_from_balance
+        || !stream.shares.is_empty()
+        || stream.awaiting_acceptance.is_some()
+        || stream.claim_deadline.is_some()
+        || !stream.milestones.is_empty()
+    {
+        return Err(ContractError::InvalidStaking {});
+    }
+    deps.querier
+        .query_validator(&options.validator)?
+        .ok_or(ContractError::InvalidStaking {})?;
+
+    let deposit = Uint128::try_from(stream.deposit)?;
+    let (rewards, withdraw) = sync_validator(&mut deps, env, &options.validator)?;
+    update_validator_delegation(deps.storage, &options.validator, deposit, Uint128::zero())?;
+    let delegate = StakingMsg::Delegate {
+        validator: options.validator.clone(),
+        amount: Coin {
+            denom,
+            amount: deposit,
+        },
+    };
+    stream.staking = Some(StreamStaking {
+        validator: options.validator,
+        rewards_to: options.rewards_to.unwrap_or_default(),
+        delegated: deposit,
+        unbonding: vec![],
+        reward_index: rewards.reward_index,
+        slash_factor: rewards.slash_factor,
+        slashed: Uint128::zero(),
+        pending_rewards: Uint128::zero(),
+        settlement: None,
+    });
+    Ok(withdraw.into_iter().chain([delegate.into()]).collect())
+}
+
+// Undelegates what vested since the last call so it can be claimed once unbonded, anyone can do this
+pub fn undelegate_vested(
+    mut deps: DepsMut,
+    env: Env,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    let mut messages = sync_stream(&mut deps, &env, &mut stream)?;
+    let vested = Uint128::try_from(curve_helpers::vested_amount(&stream, &env)?)?;
+    let deposit = Uint128::try_from(stream.deposit)?;
+    let staking = stream.staking.as_mut().ok_or(ContractError::NotStaked {})?;
+    // Slashed funds don't count as undelegated, what was slashed is missing from the end of the stream
+    let undelegated = deposit
+        .checked_sub(staking.slashed)?
+        .checked_sub(staking.delegated)?;
+    let amount = vested.saturating_sub(undelegated).min(staking.delegated);
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    messages.push(undelegate(&mut deps, &env, staking, amount)?);
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "undelegate_vested")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("amount", amount))
+}
+
+// Pays the rewards a stream earned to the recipient or the sender, anyone can do this
+pub fn claim_staking_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    let mut messages = sync_stream(&mut deps, &env, &mut stream)?;
+    let staking = stream.staking.as_mut().ok_or(ContractError::NotStaked {})?;
+    let rewards = staking.pending_rewards;
+    if rewards.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    staking.pending_rewards = Uint128::zero();
+    let to = match staking.rewards_to {
+        RewardsTo::Recipient => stream.recipient.clone(),
+        RewardsTo::Sender => stream.sender.clone(),
+    };
+    let denom = deps.querier.query_bonded_denom()?;
+    messages.push(payout_msg(&AssetInfo::Native(denom), &to, rewards)?);
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "claim_staking_rewards")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("to", to)
+        .add_attribute("amount", rewards))
+}
+
+/// A cancel of a staked stream undelegates everything left and defers paying the recipient and the sender
+/// until it unbonded, see `settle_unbonded`. The recipient is owed everything vested and unclaimed, what was
+/// slashed comes out of the part of the sender first. Returns the messages and what each of them is owed
+pub fn defer_settlement(
+    deps: &mut DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+) -> Result<(Vec<CosmosMsg>, Uint128, Uint128), ContractError> {
+    let mut messages = sync_stream(deps, env, stream)?;
+    let vested = Uint128::try_from(curve_helpers::vested_amount(stream, env)?)?;
+    let claimed = Uint128::try_from(stream.deposit.checked_sub(stream.remaining_balance)?)?;
+    let balance = Uint128::try_from(stream.remaining_balance)?;
+    let staking = stream.staking.as_mut().ok_or(ContractError::NotStaked {})?;
+    let left = balance.saturating_sub(staking.slashed);
+    let recipient_amount = vested.checked_sub(claimed)?.min(left);
+    let sender_amount = left.checked_sub(recipient_amount)?;
+    let delegated = staking.delegated;
+    if !delegated.is_zero() {
+        messages.push(undelegate(deps, env, staking, delegated)?);
+    }
+    staking.settlement = Some((recipient_amount, sender_amount));
+    Ok((messages, recipient_amount, sender_amount))
+}
+
+// Pays out a cancelled staked stream once all of it unbonded, anyone can do this
+pub fn settle_unbonded(
+    deps: DepsMut,
+    env: Env,
+    stream_idx: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    let staking = stream.staking.as_mut().ok_or(ContractError::NotStaked {})?;
+    let (recipient_amount, sender_amount) =
+        staking.settlement.ok_or(ContractError::InvalidAmount {})?;
+    if let Some(entry) = staking
+        .unbonding
+        .iter()
+        .find(|entry| entry.release_at > env.block.time)
+    {
+        return Err(ContractError::StillUnbonding {
+            release_at: entry.release_at,
+        });
+    }
+    staking.settlement = None;
+    let to = match staking.rewards_to {
+        RewardsTo::Recipient => stream.recipient.clone(),
+        RewardsTo::Sender => stream.sender.clone(),
+    };
+    let rewards = std::mem::take(&mut staking.pending_rewards);
+
+    let mut messages = vec![];
+    if !recipient_amount.is_zero() {
+        messages.push(payout_msg(
+            &stream.token_addr,
+            &stream.recipient,
+            recipient_amount,
+        )?);
+    }
+    if !sender_amount.is_zero() {
+        messages.push(payout_msg(
+            &stream.token_addr,
+            &stream.sender,
+            sender_amount,
+        )?);
+    }
+    if !rewards.is_zero() {
+        messages.push(payout_msg(&stream.token_addr, &to, rewards)?);
+    }
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "settle_unbonded")
+        .add_attribute("stream_idx", stream_idx.to_string()))
+}
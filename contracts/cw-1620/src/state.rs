@@ -1,5 +1,7 @@
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::BlockInfo;
 use cosmwasm_std::Decimal;
+use cosmwasm_std::Decimal256;
 use cosmwasm_std::Timestamp;
 use cw_asset::AssetInfoBase;
 // TODO: Consider using our own impl of Asset derived from WW and other implementations
@@ -7,13 +9,17 @@ use cw_storage_plus::Index;
 use cw_storage_plus::IndexList;
 use cw_storage_plus::IndexedMap;
 use cw_storage_plus::MultiIndex;
+use cw_utils::{Duration, Expiration};
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use white_whale::pool_network::asset::Asset;
 
-use cosmwasm_std::{Addr, Uint128};
+use crate::msg::StreamOptions;
+
+use cosmwasm_std::{Addr, Binary, Coin, Empty, Uint128, Uint256};
 use cw_storage_plus::Item;
 use cw_storage_plus::Map;
+use cw_storage_plus::{SnapshotItem, SnapshotMap, Strategy};
 use wynd_utils::Curve;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -47,36 +53,533 @@ pub enum StreamType {
     ExponentialCurveBased,
     ExponentialCurveBasedWithCliff,
     TraditionalUnlockStepCurve,
+    /// A fixed amount every period, like a subscription
+    Recurring(Recurrence),
+    /// Amounts released as milestones are approved, for grants and contractors
+    Milestones(Vec<MilestoneTerms>),
+}
+
+/// A milestone as the sender defines it when creating the stream
+#[cw_serde]
+pub struct MilestoneTerms {
+    pub amount: Uint128,
+    /// Who can approve the milestone, a single address or several of which `threshold` have to agree
+    pub approvers: Vec<String>,
+    /// By default every approver has to approve
+    pub threshold: Option<u32>,
+    /// Once approved the amount vests over this long, by default it unlocks in full right away
+    pub vesting: Option<Duration>,
+    /// The sender can take the amount back if the milestone is not approved by then
+    pub deadline: Expiration,
+}
+
+#[cw_serde]
+pub enum MilestoneStatus {
+    Pending,
+    /// Approved at this point, in the unit of the stream
+    Approved {
+        at: u64,
+    },
+    /// Returned to the sender after the deadline passed without approval
+    Reclaimed,
+}
+
+#[cw_serde]
+pub struct Milestone {
+    pub amount: Uint128,
+    pub approvers: Vec<Addr>,
+    pub threshold: u32,
+    /// Approvers who signed off so far
+    pub approvals: Vec<Addr>,
+    pub vesting: Option<Duration>,
+    pub deadline: Expiration,
+    pub status: MilestoneStatus,
+}
+
+#[cw_serde]
+/// The terms of a recurring stream.
+/// The deposit is the prepaid balance of the subscription, each period draws its amount from it and vests at the
+/// start of the period. The sender keeps it going with `TopUpStream`, once a period starts which the deposit can
+/// not cover the stream lapses for good and what is left can be refunded by cancelling
+pub struct Recurrence {
+    pub period: Duration,
+    pub amount_per_period: Uint128,
+    /// Bounds the stream by a number of periods instead of, or as well as, a stop time
+    pub max_periods: Option<u64>,
+}
+
+impl Recurrence {
+    /// The length of a period in the unit of the stream
+    pub fn period_length(&self) -> u64 {
+        match self.period {
+            Duration::Height(blocks) => blocks,
+            Duration::Time(seconds) => seconds,
+        }
+    }
+
+    /// The total length of `max_periods` periods, if the stream is bounded by a number of periods
+    pub fn max_duration(&self) -> Option<Duration> {
+        let periods = self.max_periods?;
+        Some(match self.period {
+            Duration::Height(blocks) => Duration::Height(blocks.saturating_mul(periods)),
+            Duration::Time(seconds) => Duration::Time(seconds.saturating_mul(periods)),
+        })
+    }
+}
+#[cw_serde]
+#[derive(Copy)]
+/// A point in time at which a stream starts or stops.
+/// Streams run on block time by default but can also run on block height, which some DAOs prefer for vesting
+/// as it is more predictable. Everything about a stream (delta, balances, curves) is measured in the unit it was created with
+pub enum StreamTime {
+    Height(u64),
+    Time(Timestamp),
+}
+
+impl StreamTime {
+    /// The raw value of this point, in seconds for time based streams and in blocks for height based ones
+    pub fn value(&self) -> u64 {
+        match self {
+            StreamTime::Height(height) => *height,
+            StreamTime::Time(time) => time.seconds(),
+        }
+    }
+
+    /// The current block expressed in the same unit as this point
+    pub fn current(&self, block: &BlockInfo) -> u64 {
+        match self {
+            StreamTime::Height(_) => block.height,
+            StreamTime::Time(_) => block.time.seconds(),
+        }
+    }
+
+    /// True once the current block is past this point
+    pub fn has_passed(&self, block: &BlockInfo) -> bool {
+        self.current(block) > self.value()
+    }
+
+    /// The current block as a point in the unit of the given duration
+    pub fn now(unit: &Duration, block: &BlockInfo) -> StreamTime {
+        match unit {
+            Duration::Height(_) => StreamTime::Height(block.height),
+            Duration::Time(_) => StreamTime::Time(block.time),
+        }
+    }
+
+    /// The point `duration` after this one, None if the duration is measured in another unit
+    pub fn after(&self, duration: &Duration) -> Option<StreamTime> {
+        match (self, duration) {
+            (StreamTime::Height(height), Duration::Height(blocks)) => {
+                Some(StreamTime::Height(height + blocks))
+            }
+            (StreamTime::Time(time), Duration::Time(seconds)) => {
+                Some(StreamTime::Time(time.plus_seconds(*seconds)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The point at `value` measured in the same unit as this one
+    pub fn at(&self, value: u64) -> StreamTime {
+        match self {
+            StreamTime::Height(_) => StreamTime::Height(value),
+            StreamTime::Time(_) => StreamTime::Time(Timestamp::from_seconds(value)),
+        }
+    }
+
+    /// True if both points are measured in the same unit
+    pub fn same_unit(&self, other: &StreamTime) -> bool {
+        matches!(
+            (self, other),
+            (StreamTime::Height(_), StreamTime::Height(_))
+                | (StreamTime::Time(_), StreamTime::Time(_))
+        )
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+/// The lifecycle of a stream
+/// Pending streams are funded but have not started, nothing accrues until they are activated
+/// Cancelled streams are closed for good, any further operation on them fails
+pub enum StreamStatus {
+    Pending,
+    #[default]
+    Active,
+    /// Terminal state of a cancelled stream with what was settled to each party
+    Cancelled {
+        cancelled_at: Timestamp,
+        recipient_amount: Uint128,
+        sender_amount: Uint128,
+    },
+}
+
+#[cw_serde]
+/// Who is allowed to cancel a stream, chosen when the stream is created
+pub enum CancelableBy {
+    /// The stream can not be cancelled at all, e.g. a vesting grant
+    None,
+    Sender,
+    Recipient,
+    Both,
+    /// Only this address can cancel the stream
+    Admin(Addr),
+}
+
+#[cw_serde]
+/// The cancellation rules of a stream
+pub struct CancelPolicy {
+    pub cancelable_by: CancelableBy,
+    /// When set a cancel is only scheduled, the stream keeps accruing for this long before it can be settled
+    pub notice_period: Option<Duration>,
+}
+
+impl Default for CancelPolicy {
+    fn default() -> Self {
+        Self {
+            cancelable_by: CancelableBy::Both,
+            notice_period: None,
+        }
+    }
+}
+
+impl CancelPolicy {
+    pub fn can_cancel(&self, who: &Addr, stream: &PaymentStream) -> bool {
+        match &self.cancelable_by {
+            CancelableBy::None => false,
+            CancelableBy::Sender => *who == stream.sender,
+            CancelableBy::Recipient => stream.is_recipient(who),
+            CancelableBy::Both => *who == stream.sender || stream.is_recipient(who),
+            CancelableBy::Admin(admin) => who == admin,
+        }
+    }
 }
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 // A PaymentStream is a State Object which contains the details for a Payment Stream between two parties
 // Parties in this case being recipient and sender addresses
 // The Stream stores information which can be payer-defined such as the rate of payment per second.
+// Streams stored by the first release load with the defaults of everything added since, the integer rate parses
+// as a decimal and plain timestamps as time based points
 pub struct PaymentStream {
     pub stream_idx: u64,
-    pub deposit: Uint128,
-    pub rate_per_second: Uint128,
-    pub remaining_balance: Uint128,
-    pub stop_time: Timestamp,
-    pub start_time: Timestamp,
+    /// Deposits and balances are kept as Uint256 so 18 decimal tokens never overflow the accounting,
+    /// bank coins and cw20 amounts are Uint128 so amounts are converted when funds come in or go out
+    pub deposit: Uint256,
+    /// The average amount released per second with 18 decimals of precision.
+    /// Basic streams accrue with this rate, curve based streams follow their curve and only report it
+    pub rate_per_second: Decimal256,
+    pub remaining_balance: Uint256,
+    #[serde(deserialize_with = "deserialize_stream_time")]
+    pub stop_time: StreamTime,
+    #[serde(deserialize_with = "deserialize_stream_time")]
+    pub start_time: StreamTime,
     pub recipient: Addr,
     pub sender: Addr,
     pub token_addr: AssetInfoBase<Addr>,
     pub is_closed: bool,
     pub curve: Option<Curve>,
+    #[serde(default)]
+    pub status: StreamStatus,
+    /// An address besides the sender which can activate a pending stream, this can be a contract calling in as a hook
+    pub activator: Option<Addr>,
+    /// How long a pending stream runs once activated, until then start_time and stop_time hold the creation block
+    pub pending_duration: Option<Duration>,
+    #[serde(default)]
+    pub cancel_policy: CancelPolicy,
+    /// Set once a cancel with a notice period was requested, accrual stops here and the stream can then be settled
+    pub cancels_at: Option<StreamTime>,
+    /// Whether the sender may move the stream to another recipient, the recipient always can
+    #[serde(default)]
+    pub sender_can_transfer: bool,
+    /// An address allowed to claim on behalf of the recipient
+    pub claim_operator: Option<ClaimOperator>,
+    /// Where claimed funds are sent instead of the recipient
+    pub withdrawal_address: Option<Addr>,
+    /// Whether the recipient is a contract which is sent a `StreamReceiveMsg` on create, claim and cancel
+    #[serde(default)]
+    pub notify_recipient: bool,
+    /// The terms of a recurring stream, these replace the curve and the rate for the accrual
+    pub recurrence: Option<Recurrence>,
+    /// Set while the stream waits for the recipient to accept it, with the deadline after which the sender
+    /// can reclaim the funds
+    pub awaiting_acceptance: Option<Expiration>,
+    /// After this the recipient can no longer claim and the sender can sweep what is left
+    pub claim_deadline: Option<Expiration>,
+    /// Whether the deposit was drawn from the prepaid balance of the sender, what is left on cancel goes back there
+    #[serde(default)]
+    pub funded_from_balance: bool,
+    /// The recipients of a split stream, each claiming their share independently.
+    /// Empty when the whole stream goes to `recipient`
+    #[serde(default)]
+    pub shares: Vec<RecipientShare>,
+    /// Delegation of the locked funds, for streams in the staking denom created with `stake`
+    pub staking: Option<StreamStaking>,
+    /// Part of the balance deposited in a vault while the stream vests
+    pub vault: Option<StreamVault>,
+    /// For streams denominated in a fiat currency, paid in the stream asset at the oracle price
+    pub fiat: Option<FiatTerms>,
+    /// The milestones of a milestone stream, empty for every other stream
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+}
+
+// The first release stored the start and stop of a stream as a plain timestamp
+fn deserialize_stream_time<'de, D>(deserializer: D) -> Result<StreamTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredStreamTime {
+        StreamTime(StreamTime),
+        Legacy(Timestamp),
+    }
+
+    Ok(match StoredStreamTime::deserialize(deserializer)? {
+        StoredStreamTime::StreamTime(time) => time,
+        StoredStreamTime::Legacy(time) => StreamTime::Time(time),
+    })
+}
+
+impl PaymentStream {
+    /// True once the claim deadline of the stream has passed, from then on what is left belongs to the sender
+    pub fn claim_expired(&self, block: &BlockInfo) -> bool {
+        self.claim_deadline
+            .map_or(false, |deadline| deadline.is_expired(block))
+    }
+
+    /// True for the recipient and, on a split stream, every share holder
+    pub fn is_recipient(&self, who: &Addr) -> bool {
+        *who == self.recipient || self.shares.iter().any(|share| share.recipient == *who)
+    }
+
+    /// `amount`, or the remaining balance of the stream when that is less
+    pub fn balance_up_to(&self, amount: Uint128) -> Uint128 {
+        Uint128::try_from(self.remaining_balance).map_or(amount, |balance| amount.min(balance))
+    }
+}
+
+#[cw_serde]
+/// One recipient of a split stream
+pub struct RecipientShare {
+    pub recipient: Addr,
+    /// The part of everything that vests which goes to this recipient
+    pub share: Decimal,
+    /// What this recipient claimed so far
+    pub claimed: Uint128,
+}
+
+#[cw_serde]
+pub struct ClaimOperator {
+    pub address: Addr,
+    pub expires: Expiration,
 }
 
 #[cw_serde]
 pub struct StreamData {
-    pub start_time: Timestamp,
-    pub stop_time: Timestamp,
+    pub start_time: Option<StreamTime>,
+    pub stop_time: Option<StreamTime>,
+    pub duration: Option<Duration>,
     pub stream_type: Option<StreamType>,
     pub curve: Option<Curve>,
+    pub options: Option<StreamOptions>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+/// The prepaid balance of a sender in one asset
+pub struct SenderBalance {
+    /// Available to fund new streams or to be withdrawn
+    pub free: Uint128,
+    /// Locked in streams funded from this balance and not paid out yet
+    pub committed: Uint128,
+}
+
+#[cw_serde]
+/// A merkle airdrop whose claims vest into streams, every stream follows the schedule of the airdrop
+/// with its curve scaled to the claimed amount
+pub struct Airdrop {
+    pub airdrop_id: u64,
+    /// The sender of every stream claimed from the airdrop
+    pub sender: Addr,
+    /// Hex encoded sha256 merkle root over the (address, amount) leaves
+    pub merkle_root: String,
+    pub asset: AssetInfoBase<Addr>,
+    /// The funding of the airdrop and how much of it was claimed into streams
+    pub total: Uint128,
+    pub claimed: Uint128,
+    pub start_time: StreamTime,
+    pub stop_time: StreamTime,
+    /// The curve template, claims without one vest linearly from start to stop
+    pub curve: Option<Curve>,
+    /// Claims are accepted until then, afterwards the sender can reclaim what is left
+    pub expiration: Option<Expiration>,
+    /// What the sender reclaimed once the airdrop expired
+    pub reclaimed: Uint128,
+}
+
+/// Where a rebuild of the voting power is, it runs in pages so each fits in a block. The power of every holder
+/// is reset first, then the streams are counted in key order
+#[cw_serde]
+pub enum VotingRebuild {
+    ResetHolders { last_holder: Option<Addr> },
+    CountStreams { last_stream: Option<String> },
+}
+
+/// Who the staking rewards of a stream go to
+#[cw_serde]
+#[derive(Default)]
+pub enum RewardsTo {
+    #[default]
+    Recipient,
+    Sender,
+}
+
+/// The locked funds of a stream delegated to a validator. What vests is undelegated and becomes claimable once
+/// it unbonded, until then the funds keep earning rewards
+#[cw_serde]
+pub struct StreamStaking {
+    pub validator: String,
+    pub rewards_to: RewardsTo,
+    /// Still delegated
+    pub delegated: Uint128,
+    /// Undelegated funds and when they are unbonded
+    pub unbonding: Vec<Unbonding>,
+    /// Reward index of the validator when the rewards of the stream were last accounted
+    pub reward_index: Decimal,
+    /// Slash factor of the validator when the delegation of the stream was last reconciled
+    pub slash_factor: Decimal,
+    /// What the stream lost to slashing of the validator
+    pub slashed: Uint128,
+    /// Rewards earned but not paid out yet
+    pub pending_rewards: Uint128,
+    /// What a cancel settled to the recipient and the sender, paid once everything unbonded
+    pub settlement: Option<(Uint128, Uint128)>,
+}
+
+#[cw_serde]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_at: Timestamp,
+}
+
+/// Delegations of the contract to a validator, shared by every stream staking with it.
+/// `reward_index` is the reward earned per delegated token since the first delegation and `slash_factor` what is
+/// left of a token delegated since then after the slashes of the validator
+#[cw_serde]
+pub struct ValidatorRewards {
+    pub delegated: Uint128,
+    pub reward_index: Decimal,
+    pub slash_factor: Decimal,
+}
+
+impl Default for ValidatorRewards {
+    fn default() -> Self {
+        ValidatorRewards {
+            delegated: Uint128::zero(),
+            reward_index: Decimal::zero(),
+            slash_factor: Decimal::one(),
+        }
+    }
+}
+
+/// The position of a stream in a vault. Streams using the same vault share the position of the contract
+/// in it, each stream owns `shares` of it
+#[cw_serde]
+pub struct StreamVault {
+    pub vault: Addr,
+    /// What of the stream balance is in the vault, the position earned whatever its value exceeds this by
+    pub principal: Uint128,
+    pub shares: Uint128,
+    /// Part of the yield going to the recipient, the rest goes to the sender
+    pub recipient_yield: Decimal,
+}
+
+/// A stream vesting an amount of a fiat currency. The deposit is collateral, every payout is converted from
+/// what vested in the currency at the oracle price
+#[cw_serde]
+pub struct FiatTerms {
+    pub oracle: Addr,
+    /// The currency as the oracle names it, e.g. USD
+    pub currency: String,
+    /// What the stream pays over its whole schedule, in the currency
+    pub amount: Uint128,
+    /// What was paid out so far, in the currency
+    pub paid: Uint128,
+    /// Prices older than this many seconds are rejected
+    pub max_staleness: u64,
+    /// Prices are rejected when their confidence interval is wider than this part of the price
+    pub tolerance: Decimal,
+    /// The last usable price seen and when, a cancel falls back to it once the oracle has been down for long enough
+    pub last_price: Decimal,
+    pub last_priced_at: Timestamp,
+}
+
+/// Where an intermediate sender of ibc-hooks comes from, its balance is returned there
+#[cw_serde]
+pub struct IbcSender {
+    pub channel_id: String,
+    pub original_sender: String,
+}
+
+/// Liquidity a provider puts up for the streams asked for over a channel. A stream is paid in `denom` out of the
+/// prepaid balance of the provider, one for one with what its sender paid in `source_denom` on the other chain,
+/// which the counterparty of the channel then pays to `source_address` there
+#[cw_serde]
+pub struct ChannelOffer {
+    pub denom: String,
+    pub source_denom: String,
+    pub source_address: String,
 }
 
 pub const STATE: Item<ConfigState> = Item::new("state");
 // TODO: Make this a Vec of streams and update the logic on create to simply push a new stream to the vec, and on withdraw, unless an index is provided, attempt to withdraw from all. If it is, search the vec and use idx to find it
 pub const STREAMS: Map<(&Addr, &Addr), PaymentStream> = Map::new("streams");
+// Prepaid balances of senders, keyed by sender and asset
+pub const SENDER_BALANCES: Map<(&Addr, &str), SenderBalance> = Map::new("sender_balances");
+pub const AIRDROPS: Map<u64, Airdrop> = Map::new("airdrops");
+// The stream each address claimed from an airdrop
+pub const AIRDROP_CLAIMS: Map<(u64, &Addr), u64> = Map::new("airdrop_claims");
+pub const LAST_AIRDROP_IDX: Item<u64> = Item::new("last_airdrop_idx");
+pub const VALIDATOR_REWARDS: Map<&str, ValidatorRewards> = Map::new("validator_rewards");
+// How long undelegated funds take to unbond on this chain in seconds, overriding the staking params of the chain
+pub const UNBONDING_PERIOD: Item<u64> = Item::new("unbonding_period");
+// Vaults streams can deposit into, managed by the owner
+pub const ALLOWED_VAULTS: Map<&Addr, Empty> = Map::new("allowed_vaults");
+// Shares of the position of the contract in each vault held by all streams together
+pub const VAULT_SHARES: Map<&Addr, Uint128> = Map::new("vault_shares");
+// Voting power of every holder and in total, by height
+pub const VOTING_POWER: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "voting_power",
+    "voting_power__checkpoints",
+    "voting_power__changelog",
+    Strategy::EveryBlock,
+);
+pub const TOTAL_POWER: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_power",
+    "total_power__checkpoints",
+    "total_power__changelog",
+    Strategy::EveryBlock,
+);
+// Only streams of this asset count for voting power when set
+pub const GOVERNANCE_ASSET: Item<AssetInfoBase<Addr>> = Item::new("governance_asset");
+// The rebuild of the voting power in progress, if any
+pub const VOTING_REBUILD: Item<VotingRebuild> = Item::new("voting_rebuild");
+// Intermediate senders which created streams through ibc-hooks
+pub const IBC_SENDERS: Map<&Addr, IbcSender> = Map::new("ibc_senders");
+// Balance returns in flight over IBC by channel and packet sequence
+pub const IBC_RETURNS: Map<(&str, u64), (Addr, Coin)> = Map::new("ibc_returns");
+// The return being sent, until its sequence is known in the reply
+pub const PENDING_IBC_RETURN: Item<(Addr, String, Coin)> = Item::new("pending_ibc_return");
+// What each provider offers for the streams asked for over a channel, by channel and provider
+pub const CHANNEL_OFFERS: Map<(&str, &Addr), ChannelOffer> = Map::new("channel_offers");
+// The secp256k1 public key stream offers of a sender are signed with
+pub const OFFER_KEYS: Map<&Addr, Binary> = Map::new("offer_keys");
+// Nonces of offers a sender made which were submitted or revoked
+pub const USED_OFFER_NONCES: Map<(&Addr, u64), Empty> = Map::new("used_offer_nonces");
+// Split streams are indexed under `recipient` only, this lists them for the other share holders
+pub const SHARED_STREAMS: Map<(&Addr, u64), Empty> = Map::new("shared_streams");
 // Extra State Item to store the index we will use to base a new stream's index off of
 pub const LAST_STREAM_IDX: Item<u64> = Item::new("last_stream_idx");
 // Secondary Indexes for our STREAMS map.
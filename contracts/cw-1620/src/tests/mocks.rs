@@ -0,0 +1,255 @@
+// Minimal contracts standing in for the third party contracts streams interact with
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_binary, to_binary, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Empty, Env, MessageInfo,
+    Response, StdError, StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw_storage_plus::{Item, Map};
+use paystreams::{
+    OracleQueryMsg, PriceResponse, StreamReceiveMsg, VaultBalanceResponse, VaultQueryMsg,
+};
+
+// A savings contract which accepts native deposits and CW20 deposits through the Send hook
+const DEPOSITS: Map<&str, Uint128> = Map::new("deposits");
+
+#[cw_serde]
+pub enum SavingsExecuteMsg {
+    Deposit { depositor: String },
+    Receive(Cw20ReceiveMsg),
+}
+
+#[cw_serde]
+pub enum SavingsQueryMsg {
+    Deposited { depositor: String },
+}
+
+pub fn savings_instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+pub fn savings_execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: SavingsExecuteMsg,
+) -> StdResult<Response> {
+    let (depositor, amount) = match msg {
+        SavingsExecuteMsg::Deposit { depositor } => {
+            let amount = info
+                .funds
+                .iter()
+                .fold(Uint128::zero(), |total, coin| total + coin.amount);
+            (depositor, amount)
+        }
+        SavingsExecuteMsg::Receive(cw20_msg) => match from_binary(&cw20_msg.msg)? {
+            SavingsExecuteMsg::Deposit { depositor } => (depositor, cw20_msg.amount),
+            SavingsExecuteMsg::Receive(_) => return Ok(Response::new()),
+        },
+    };
+    DEPOSITS.update(deps.storage, &depositor, |deposited| -> StdResult<_> {
+        Ok(deposited.unwrap_or_default() + amount)
+    })?;
+    Ok(Response::new())
+}
+
+pub fn savings_query(deps: Deps, _env: Env, msg: SavingsQueryMsg) -> StdResult<Binary> {
+    match msg {
+        SavingsQueryMsg::Deposited { depositor } => to_binary(
+            &DEPOSITS
+                .may_load(deps.storage, &depositor)?
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+// A contract receiving streams which records every StreamReceiveMsg, or rejects them once told to
+const EVENTS: Item<Vec<StreamReceiveMsg>> = Item::new("events");
+const REJECT: Item<bool> = Item::new("reject");
+
+#[cw_serde]
+pub enum ReceiverExecuteMsg {
+    StreamReceive(StreamReceiveMsg),
+    Reject {},
+}
+
+#[cw_serde]
+pub enum ReceiverQueryMsg {
+    Events {},
+}
+
+pub fn receiver_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    EVENTS.save(deps.storage, &vec![])?;
+    REJECT.save(deps.storage, &false)?;
+    Ok(Response::new())
+}
+
+pub fn receiver_execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: ReceiverExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ReceiverExecuteMsg::StreamReceive(event) => {
+            if REJECT.load(deps.storage)? {
+                return Err(StdError::generic_err("stream events are rejected"));
+            }
+            EVENTS.update(deps.storage, |mut events| -> StdResult<_> {
+                events.push(event);
+                Ok(events)
+            })?;
+        }
+        ReceiverExecuteMsg::Reject {} => REJECT.save(deps.storage, &true)?,
+    }
+    Ok(Response::new())
+}
+
+pub fn receiver_query(deps: Deps, _env: Env, msg: ReceiverQueryMsg) -> StdResult<Binary> {
+    match msg {
+        ReceiverQueryMsg::Events {} => to_binary(&EVENTS.load(deps.storage)?),
+    }
+}
+
+// A vault holding native deposits, yield is simulated by adding funds to the balance of a depositor and losses
+// by burning part of it
+const VAULT_BALANCES: Map<&str, Coin> = Map::new("vault_balances");
+
+#[cw_serde]
+pub enum VaultExecuteMsg {
+    Deposit {},
+    Withdraw { amount: Uint128 },
+    AddYield { depositor: String },
+    Lose { depositor: String, amount: Uint128 },
+}
+
+pub fn vault_instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+pub fn vault_execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: VaultExecuteMsg,
+) -> StdResult<Response> {
+    let (depositor, funds) = match msg {
+        VaultExecuteMsg::Deposit {} => (info.sender.to_string(), info.funds),
+        VaultExecuteMsg::AddYield { depositor } => (depositor, info.funds),
+        VaultExecuteMsg::Lose { depositor, amount } => {
+            let mut balance = VAULT_BALANCES.load(deps.storage, &depositor)?;
+            balance.amount = balance.amount.checked_sub(amount)?;
+            VAULT_BALANCES.save(deps.storage, &depositor, &balance)?;
+            return Ok(Response::new().add_message(BankMsg::Burn {
+                amount: vec![Coin {
+                    denom: balance.denom,
+                    amount,
+                }],
+            }));
+        }
+        VaultExecuteMsg::Withdraw { amount } => {
+            let mut balance = VAULT_BALANCES.load(deps.storage, info.sender.as_str())?;
+            balance.amount = balance.amount.checked_sub(amount)?;
+            VAULT_BALANCES.save(deps.storage, info.sender.as_str(), &balance)?;
+            return Ok(Response::new().add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin {
+                    denom: balance.denom,
+                    amount,
+                }],
+            }));
+        }
+    };
+    for coin in funds {
+        VAULT_BALANCES.update(deps.storage, &depositor, |balance| -> StdResult<_> {
+            let mut balance = balance.unwrap_or(Coin {
+                denom: coin.denom.clone(),
+                amount: Uint128::zero(),
+            });
+            balance.amount += coin.amount;
+            Ok(balance)
+        })?;
+    }
+    Ok(Response::new())
+}
+
+pub fn vault_query(deps: Deps, _env: Env, msg: VaultQueryMsg) -> StdResult<Binary> {
+    match msg {
+        VaultQueryMsg::Balance { depositor } => to_binary(&VaultBalanceResponse {
+            amount: VAULT_BALANCES
+                .may_load(deps.storage, &depositor)?
+                .map(|balance| balance.amount)
+                .unwrap_or_default(),
+        }),
+    }
+}
+
+// An oracle anyone can set prices on, each price is stamped with the time it was set
+const PRICES: Map<(&str, &str), PriceResponse> = Map::new("prices");
+
+#[cw_serde]
+pub enum OracleExecuteMsg {
+    SetPrice {
+        base: String,
+        quote: String,
+        price: Decimal,
+        confidence: Decimal,
+    },
+}
+
+pub fn oracle_instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+pub fn oracle_execute(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: OracleExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        OracleExecuteMsg::SetPrice {
+            base,
+            quote,
+            price,
+            confidence,
+        } => PRICES.save(
+            deps.storage,
+            (base.as_str(), quote.as_str()),
+            &PriceResponse {
+                price,
+                confidence,
+                updated_at: env.block.time,
+            },
+        )?,
+    }
+    Ok(Response::new())
+}
+
+pub fn oracle_query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+    match msg {
+        OracleQueryMsg::Price { base, quote } => {
+            to_binary(&PRICES.load(deps.storage, (base.as_str(), quote.as_str()))?)
+        }
+    }
+}
@@ -1,2 +1,3 @@
+pub mod mocks;
 pub mod payment;
 pub mod suite;
@@ -1,6 +1,6 @@
 mod basic_use_cases {
 
-    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
     use wynd_utils::Curve;
 
     use crate::{state::StreamType, tests::suite::SuiteBuilder, ContractError};
@@ -96,17 +96,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -125,17 +125,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -155,17 +155,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -199,12 +199,12 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(59u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(59u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -278,17 +278,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -307,17 +307,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -337,17 +337,17 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
                 assert_eq!(
-                    stream.start_time.seconds(),
+                    stream.start_time.value(),
                     suite.get_time_as_timestamp().seconds()
                 );
                 assert_eq!(
-                    stream.stop_time.seconds(),
+                    stream.stop_time.value(),
                     suite.get_time_as_timestamp().plus_seconds(100).seconds()
                 );
             }
@@ -381,12 +381,12 @@ mod basic_use_cases {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(50u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(50u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -490,7 +490,7 @@ mod views_with_mock_data {
     // We will create a few streams and then query them using the views we have defined
     // We will then assert that the data returned is what we expect
 
-    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
 
     use crate::{state::PaymentStream, tests::suite::SuiteBuilder};
 
@@ -585,17 +585,17 @@ mod views_with_mock_data {
         assert_eq!(streams.len(), 1);
         assert_eq!(streams[0].recipient, recipients[0].to_string());
         assert_eq!(streams[0].sender, funder.to_string());
-        assert_eq!(streams[0].deposit, Uint128::from(100u128));
+        assert_eq!(streams[0].deposit, Uint256::from(100u128));
         assert_eq!(
             streams[0].token_addr.to_string(),
             "native:ibc/something/axlusdc".to_string()
         );
         assert_eq!(
-            streams[0].start_time.seconds(),
+            streams[0].start_time.value(),
             suite.get_time_as_timestamp().seconds()
         );
         assert_eq!(
-            streams[0].stop_time.seconds(),
+            streams[0].stop_time.value(),
             suite.get_time_as_timestamp().plus_seconds(100).seconds()
         );
     }
@@ -667,17 +667,17 @@ mod views_with_mock_data {
         assert_eq!(streams.len(), 2);
         assert_eq!(streams[0].recipient, recipients[1].to_string());
         assert_eq!(streams[0].sender, funder.to_string());
-        assert_eq!(streams[0].deposit, Uint128::from(100u128));
+        assert_eq!(streams[0].deposit, Uint256::from(100u128));
         assert_eq!(
             streams[0].token_addr.to_string(),
             "native:ibc/something/axlusdc".to_string()
         );
         assert_eq!(
-            streams[0].start_time.seconds(),
+            streams[0].start_time.value(),
             suite.get_time_as_timestamp().seconds()
         );
         assert_eq!(
-            streams[0].stop_time.seconds(),
+            streams[0].stop_time.value(),
             suite.get_time_as_timestamp().plus_seconds(100).seconds()
         );
     }
@@ -783,7 +783,7 @@ mod views_with_mock_data {
 }
 
 mod curve_tests {
-    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
     use wynd_utils::{Curve, PiecewiseLinear};
 
     use crate::{state::StreamType, tests::suite::SuiteBuilder};
@@ -838,12 +838,12 @@ mod curve_tests {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(100u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(100u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -871,12 +871,12 @@ mod curve_tests {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(80u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(80u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -905,7 +905,6 @@ mod curve_tests {
         assert_eq!(balance, 40u128);
     }
 
-
     #[test]
     fn test_piecewise_cliff_curve() {
         let funder = Addr::unchecked("funder");
@@ -932,7 +931,10 @@ mod curve_tests {
             steps: vec![
                 (suite.get_time_as_timestamp().seconds(), 0u128.into()),
                 (suite.get_time_as_timestamp().seconds() + 20, 0u128.into()),
-                (suite.get_time_as_timestamp().seconds() + 100, 100u128.into()),
+                (
+                    suite.get_time_as_timestamp().seconds() + 100,
+                    100u128.into(),
+                ),
             ],
         });
 
@@ -951,7 +953,6 @@ mod curve_tests {
                 }],
                 Some(StreamType::CliffCurveBased),
                 Some(curve),
-                
             )
             .unwrap();
 
@@ -972,12 +973,12 @@ mod curve_tests {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(100u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(100u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -1012,12 +1013,12 @@ mod curve_tests {
             Some(stream) => {
                 assert_eq!(stream.recipient, recipients[0].to_string());
                 assert_eq!(stream.sender, funder.to_string());
-                assert_eq!(stream.deposit, Uint128::from(100u128));
+                assert_eq!(stream.deposit, Uint256::from(100u128));
                 assert_eq!(
                     stream.token_addr.to_string(),
                     "native:ibc/something/axlusdc".to_string()
                 );
-                assert_eq!(stream.remaining_balance, Uint128::from(75u128));
+                assert_eq!(stream.remaining_balance, Uint256::from(75u128));
             }
             None => {
                 panic!("Stream was not created");
@@ -1050,13 +1051,99 @@ mod curve_tests {
             .query_balance(&recipients[0].clone().to_string(), "ibc/something/axlusdc")
             .unwrap();
         assert_eq!(balance, 100u128);
-        
     }
 }
 
 mod rounding_tests {
     use crate::{curve_helpers, tests::suite::SuiteBuilder, ContractError};
-    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
+
+    #[test]
+    fn test_rounding_uint256_deposit_for_18_decimal_tokens() {
+        // The engine itself works in Uint256, scaled by 10^18 intermediate products of large 18 decimal deposits
+        // don't fit in a Uint128
+        let deposit = Uint256::from(u128::MAX)
+            .checked_mul(Uint256::from(1_000u128))
+            .unwrap();
+        // A duration which does not divide the deposit evenly so the rate is truncated
+        let duration = 31_536_001u64;
+
+        let rate = curve_helpers::calc_rate_per_second(duration, deposit).unwrap();
+        let remainder = curve_helpers::rate_remainder(deposit, rate, duration).unwrap();
+        assert!(remainder < Uint256::from(duration));
+
+        for elapsed in [0u64, 1, 59, 15_768_000, 31_536_000] {
+            let accrued = curve_helpers::accrued_amount(deposit, rate, elapsed, duration).unwrap();
+            assert_eq!(
+                accrued,
+                deposit.multiply_ratio(elapsed, duration),
+                "Accrued amount should be exact after {} seconds",
+                elapsed
+            );
+        }
+
+        // Nothing is stranded once the stream has ended
+        let accrued = curve_helpers::accrued_amount(deposit, rate, duration, duration).unwrap();
+        assert_eq!(accrued, deposit);
+        let accrued = curve_helpers::accrued_amount(deposit, rate, duration * 2, duration).unwrap();
+        assert_eq!(accrued, deposit);
+
+        // A zero duration can never produce a rate
+        let err = curve_helpers::calc_rate_per_second(0, deposit).unwrap_err();
+        assert_eq!(err, ContractError::DivisionByZero {});
+    }
+
+    #[test]
+    fn test_rounding_largest_deposit_round_trip() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let denom = "ibc/something/weth";
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: denom.to_string(),
+                    amount: Uint128::MAX,
+                }],
+            )
+            .build();
+
+        let start = suite.get_time_as_timestamp().seconds();
+        suite
+            .create_stream(
+                funder.clone(),
+                recipient.clone(),
+                u128::MAX,
+                denom,
+                start,
+                start + 100,
+                &[Coin {
+                    denom: denom.to_string(),
+                    amount: Uint128::MAX,
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+
+        suite.update_time(50);
+        assert_eq!(
+            suite.query_stream_claimable_amount(1u64).unwrap(),
+            u128::MAX / 2
+        );
+        suite
+            .withdraw_from_stream(recipient.clone(), u128::MAX / 2, denom, Some(1u64))
+            .unwrap();
+
+        suite.update_time(50);
+        let rest = u128::MAX - u128::MAX / 2;
+        assert_eq!(suite.query_stream_claimable_amount(1u64).unwrap(), rest);
+        suite
+            .withdraw_from_stream(recipient, rest, denom, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("recipient", denom).unwrap(), u128::MAX);
+        assert_eq!(suite.query_balance("funder", denom).unwrap(), 0);
+    }
 
     #[test]
     fn test_rounding_small_deposit_over_long_duration() {
@@ -1306,7 +1393,7 @@ mod rounding_tests {
             .remaining_balance;
         assert_eq!(
             remaining_balance,
-            Uint128::zero(),
+            Uint256::zero(),
             "Remaining balance should be zero at the end of the stream"
         );
     }
@@ -1399,3 +1486,3515 @@ mod rounding_tests {
         }
     }
 }
+
+mod height_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use wynd_utils::Curve;
+
+    use crate::{
+        state::{StreamTime, StreamType},
+        tests::suite::SuiteBuilder,
+        ContractError,
+    };
+
+    #[test]
+    fn test_basic_stream_by_block_height() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+
+        let start_height = suite.get_block_height();
+        suite
+            .create_stream_between(
+                funder.clone(),
+                recipient.clone(),
+                100u128,
+                "ibc/something/axlusdc",
+                StreamTime::Height(start_height),
+                StreamTime::Height(start_height + 100),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+
+        // The stream reports the unit it was created with
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.start_time, StreamTime::Height(start_height));
+        assert_eq!(stream.stop_time, StreamTime::Height(start_height + 100));
+
+        // Passing time without producing blocks releases nothing
+        suite.next_block(1000);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 1u128);
+
+        suite.advance_blocks(19);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 20u128);
+
+        suite
+            .withdraw_from_stream(
+                recipient.clone(),
+                20u128,
+                "ibc/something/axlusdc",
+                Some(1u64),
+            )
+            .unwrap();
+
+        suite.advance_blocks(200);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 80u128);
+    }
+
+    #[test]
+    fn test_curve_stream_by_block_height() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+
+        // The curve is expressed in block heights, just like the stream
+        let start_height = suite.get_block_height();
+        suite
+            .create_stream_between(
+                funder.clone(),
+                recipient.clone(),
+                100u128,
+                "ibc/something/axlusdc",
+                StreamTime::Height(start_height),
+                StreamTime::Height(start_height + 50),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                Some(StreamType::LinearCurveBased),
+                Some(Curve::saturating_linear(
+                    (start_height, 0u128),
+                    (start_height + 50, 100u128),
+                )),
+            )
+            .unwrap();
+
+        suite.advance_blocks(10);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 20u128);
+    }
+
+    #[test]
+    fn test_mixed_units_are_rejected() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+
+        let start_height = suite.get_block_height();
+        let err = suite
+            .create_stream_between(
+                funder.clone(),
+                recipient.clone(),
+                100u128,
+                "ibc/something/axlusdc",
+                StreamTime::Height(start_height),
+                StreamTime::Time(suite.get_time_as_timestamp().plus_seconds(100)),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(
+            ContractError::MismatchedStreamUnits {},
+            err.downcast().unwrap()
+        );
+    }
+}
+
+mod deferred_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_utils::Duration;
+    use wynd_utils::Curve;
+
+    use crate::{
+        state::{StreamStatus, StreamTime, StreamType},
+        tests::suite::SuiteBuilder,
+        ContractError,
+    };
+
+    #[test]
+    fn test_deferred_stream_waits_for_activation() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let activator = Addr::unchecked("tge_contract");
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+
+        suite
+            .create_deferred_stream(
+                funder.clone(),
+                recipient.clone(),
+                100u128,
+                "ibc/something/axlusdc",
+                Duration::Time(100),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+                Some(activator.clone()),
+            )
+            .unwrap();
+
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.status, StreamStatus::Pending);
+
+        // Nothing accrues while the stream is pending, no matter how long it waits
+        suite.update_time(1000);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 0u128);
+
+        // Only the sender or the activator can activate it
+        let err = suite.activate_stream(recipient.clone(), 1u64).unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite.activate_stream(activator.clone(), 1u64).unwrap();
+        let activated_at = suite.get_time_as_timestamp();
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.status, StreamStatus::Active);
+        assert_eq!(stream.start_time, StreamTime::Time(activated_at));
+        assert_eq!(
+            stream.stop_time,
+            StreamTime::Time(activated_at.plus_seconds(100))
+        );
+
+        // A stream can only be activated once
+        let err = suite.activate_stream(funder.clone(), 1u64).unwrap_err();
+        assert_eq!(ContractError::StreamNotPending {}, err.downcast().unwrap());
+
+        suite.update_time(25);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 25u128);
+    }
+
+    #[test]
+    fn test_deferred_stream_anchors_relative_curve() {
+        let funder = Addr::unchecked("funder");
+        let recipient = Addr::unchecked("recipient");
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                &funder.to_string(),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+
+        // The curve runs from 0 to 100 seconds after activation
+        suite
+            .create_deferred_stream(
+                funder.clone(),
+                recipient.clone(),
+                100u128,
+                "ibc/something/axlusdc",
+                Duration::Time(100),
+                &[Coin {
+                    denom: "ibc/something/axlusdc".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                Some(StreamType::LinearCurveBased),
+                Some(Curve::saturating_linear((0, 0u128), (100, 100u128))),
+                None,
+            )
+            .unwrap();
+
+        suite.update_time(500);
+        suite.activate_stream(funder.clone(), 1u64).unwrap();
+        let activated_at = suite.get_time_as_timestamp().seconds();
+
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(
+            stream.curve,
+            Some(Curve::saturating_linear(
+                (activated_at, 0u128),
+                (activated_at + 100, 100u128)
+            ))
+        );
+
+        suite.update_time(40);
+        let claimable_amount = suite.query_stream_claimable_amount(1u64).unwrap();
+        assert_eq!(claimable_amount, 40u128);
+    }
+}
+
+mod cancel_policies {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_utils::Duration;
+
+    use crate::{
+        msg::StreamOptions,
+        state::{CancelPolicy, CancelableBy},
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup() -> Suite {
+        SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build()
+    }
+
+    fn create_with_policy(suite: &mut Suite, cancel_policy: CancelPolicy) {
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("recipient"),
+                100u128,
+                DENOM,
+                suite.get_time_as_timestamp().seconds(),
+                suite.get_time_as_timestamp().plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    cancel_policy: Some(cancel_policy),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_non_cancelable_stream() {
+        let mut suite = setup();
+        create_with_policy(
+            &mut suite,
+            CancelPolicy {
+                cancelable_by: CancelableBy::None,
+                notice_period: None,
+            },
+        );
+
+        // The policy is visible on the stream
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.cancel_policy.cancelable_by, CancelableBy::None);
+
+        for who in ["funder", "recipient"] {
+            let err = suite.cancel_stream(Addr::unchecked(who), 1u64).unwrap_err();
+            assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sender_only_and_admin_policies() {
+        let mut suite = setup();
+        create_with_policy(
+            &mut suite,
+            CancelPolicy {
+                cancelable_by: CancelableBy::Sender,
+                notice_period: None,
+            },
+        );
+        create_with_policy(
+            &mut suite,
+            CancelPolicy {
+                cancelable_by: CancelableBy::Admin(Addr::unchecked("admin")),
+                notice_period: None,
+            },
+        );
+        suite.update_time(10);
+
+        let err = suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        // The sender can cancel even after the stream started
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+
+        let err = suite
+            .cancel_stream(Addr::unchecked("funder"), 2u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite.cancel_stream(Addr::unchecked("admin"), 2u64).unwrap();
+    }
+
+    #[test]
+    fn test_notice_period_keeps_accruing() {
+        let mut suite = setup();
+        create_with_policy(
+            &mut suite,
+            CancelPolicy {
+                cancelable_by: CancelableBy::Sender,
+                notice_period: Some(Duration::Time(30)),
+            },
+        );
+
+        suite.update_time(20);
+        // The first cancel only schedules the cancel
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("recipient", DENOM).unwrap(), 0u128);
+
+        suite.update_time(10);
+        let err = suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NoticePeriodNotOver {
+                cancels_at: suite.get_time_as_timestamp().plus_seconds(20).seconds()
+            },
+            err.downcast().unwrap()
+        );
+        // Accrual continues during the notice period
+        assert_eq!(suite.query_stream_claimable_amount(1u64).unwrap(), 30u128);
+
+        // Accrual stops once the notice period is over
+        suite.update_time(40);
+        assert_eq!(suite.query_stream_claimable_amount(1u64).unwrap(), 50u128);
+
+        // Either party can settle the stream now
+        suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("recipient", DENOM).unwrap(), 50u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128 - 50u128
+        );
+    }
+}
+
+mod closed_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
+    use cw_utils::Duration;
+
+    use crate::{
+        state::StreamStatus,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_stream() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        suite
+            .create_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("recipient"),
+                100u128,
+                DENOM,
+                suite.get_time_as_timestamp().seconds(),
+                suite.get_time_as_timestamp().plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_cancel_records_terminal_state() {
+        let mut suite = setup_with_stream();
+        suite.update_time(30);
+        let cancelled_at = suite.get_time_as_timestamp();
+        suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap();
+
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(
+            stream.status,
+            StreamStatus::Cancelled {
+                cancelled_at,
+                recipient_amount: Uint128::from(30u128),
+                sender_amount: Uint128::from(70u128),
+            }
+        );
+        assert!(stream.is_closed);
+        assert_eq!(stream.remaining_balance, Uint256::zero());
+        assert_eq!(suite.query_stream_claimable_amount(1u64).unwrap(), 0u128);
+    }
+
+    #[test]
+    fn test_double_cancel_is_rejected() {
+        let mut suite = setup_with_stream();
+        suite.update_time(30);
+        suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap();
+
+        let err = suite
+            .cancel_stream(Addr::unchecked("recipient"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+
+        // Nothing was paid a second time
+        assert_eq!(suite.query_balance("recipient", DENOM).unwrap(), 30u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128 - 30u128
+        );
+    }
+
+    #[test]
+    fn test_claim_after_cancel_is_rejected() {
+        let mut suite = setup_with_stream();
+        suite.update_time(30);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        suite.update_time(30);
+
+        let err = suite
+            .withdraw_from_stream(Addr::unchecked("recipient"), 10u128, DENOM, Some(1u64))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+        assert_eq!(suite.query_balance("recipient", DENOM).unwrap(), 30u128);
+    }
+
+    #[test]
+    fn test_activate_after_cancel_is_rejected() {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        suite
+            .create_deferred_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("recipient"),
+                100u128,
+                DENOM,
+                Duration::Time(100),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        // A pending stream refunds the sender in full
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128
+        );
+
+        let err = suite
+            .activate_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+}
+
+mod recipient_management {
+    use cosmwasm_std::{to_binary, Addr, Coin, Uint128};
+    use cw_utils::Expiration;
+
+    use crate::{
+        msg::StreamOptions,
+        tests::{
+            mocks::SavingsExecuteMsg,
+            suite::{Suite, SuiteBuilder},
+        },
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_stream(sender_can_transfer: bool) -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                100u128,
+                DENOM,
+                suite.get_time_as_timestamp().seconds(),
+                suite.get_time_as_timestamp().plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    sender_can_transfer: Some(sender_can_transfer),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_recipient_transfers_stream() {
+        let mut suite = setup_with_stream(false);
+        suite.update_time(20);
+
+        // The sender was not allowed to transfer at creation
+        let err = suite
+            .transfer_stream(Addr::unchecked("funder"), 1u64, Addr::unchecked("bob"))
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite
+            .transfer_stream(Addr::unchecked("alice"), 1u64, Addr::unchecked("bob"))
+            .unwrap();
+
+        // The recipient index follows the new recipient
+        let streams = suite
+            .query_streams_by_payee(Addr::unchecked("alice"))
+            .unwrap()
+            .streams;
+        assert!(streams.is_empty());
+        let streams = suite
+            .query_streams_by_payee(Addr::unchecked("bob"))
+            .unwrap()
+            .streams;
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].recipient, Addr::unchecked("bob"));
+
+        // Only the new recipient can claim now
+        let err = suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 20u128, DENOM, Some(1u64))
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite
+            .withdraw_from_stream(Addr::unchecked("bob"), 20u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 20u128);
+    }
+
+    #[test]
+    fn test_sender_transfers_stream_when_allowed() {
+        let mut suite = setup_with_stream(true);
+        suite
+            .transfer_stream(Addr::unchecked("funder"), 1u64, Addr::unchecked("bob"))
+            .unwrap();
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.recipient, Addr::unchecked("bob"));
+    }
+
+    #[test]
+    fn test_claim_operator_claims_for_recipient() {
+        let mut suite = setup_with_stream(false);
+        let bot = Addr::unchecked("bot");
+        suite.update_time(20);
+
+        // Only the recipient can set an operator
+        let err = suite
+            .set_claim_operator(Addr::unchecked("funder"), 1u64, Some(bot.clone()), None)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        let expires = Expiration::AtTime(suite.get_time_as_timestamp().plus_seconds(30));
+        suite
+            .set_claim_operator(
+                Addr::unchecked("alice"),
+                1u64,
+                Some(bot.clone()),
+                Some(expires),
+            )
+            .unwrap();
+
+        // The operator claims but the funds go to the recipient
+        suite
+            .claim_from_stream_as(bot.clone(), 20u128, DENOM, 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 20u128);
+        assert_eq!(suite.query_balance("bot", DENOM).unwrap(), 0u128);
+
+        // With a withdrawal address set the funds go there instead
+        suite
+            .set_withdrawal_address(
+                Addr::unchecked("alice"),
+                1u64,
+                Some(Addr::unchecked("savings")),
+            )
+            .unwrap();
+        suite.update_time(10);
+        suite
+            .claim_from_stream_as(bot.clone(), 10u128, DENOM, 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("savings", DENOM).unwrap(), 10u128);
+
+        // Once expired the operator can no longer claim
+        suite.update_time(30);
+        let err = suite
+            .claim_from_stream_as(bot, 10u128, DENOM, 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_claim_to_another_address() {
+        let mut suite = setup_with_stream(false);
+        suite.update_time(20);
+
+        suite
+            .claim_from_stream_to(
+                Addr::unchecked("alice"),
+                20u128,
+                DENOM,
+                1u64,
+                Some(Addr::unchecked("cold_wallet")),
+                None,
+            )
+            .unwrap();
+        assert_eq!(suite.query_balance("cold_wallet", DENOM).unwrap(), 20u128);
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 0u128);
+
+        // An operator can not redirect funds
+        let bot = Addr::unchecked("bot");
+        suite
+            .set_claim_operator(Addr::unchecked("alice"), 1u64, Some(bot.clone()), None)
+            .unwrap();
+        suite.update_time(10);
+        let err = suite
+            .claim_from_stream_to(bot, 10u128, DENOM, 1u64, Some(Addr::unchecked("bot")), None)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_claim_with_callback_into_contract() {
+        let mut suite = setup_with_stream(false);
+        let savings = suite.instantiate_savings_contract();
+        suite.update_time(40);
+
+        suite
+            .claim_from_stream_to(
+                Addr::unchecked("alice"),
+                40u128,
+                DENOM,
+                1u64,
+                Some(savings.clone()),
+                Some(
+                    to_binary(&SavingsExecuteMsg::Deposit {
+                        depositor: "alice".to_string(),
+                    })
+                    .unwrap(),
+                ),
+            )
+            .unwrap();
+
+        // The funds were attached to the callback and credited by the savings contract
+        assert_eq!(
+            suite.query_balance(savings.as_str(), DENOM).unwrap(),
+            40u128
+        );
+        assert_eq!(
+            suite.query_savings_deposits(&savings, &Addr::unchecked("alice")),
+            40u128
+        );
+    }
+}
+
+mod stream_hooks {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_asset::AssetInfo;
+    use paystreams::{StreamEvent, StreamReceiveMsg};
+
+    use crate::{
+        msg::StreamOptions,
+        tests::suite::{Suite, SuiteBuilder},
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_receiver(notify_recipient: bool) -> (Suite, Addr) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        let receiver = suite.instantiate_stream_receiver();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                receiver.clone(),
+                100u128,
+                DENOM,
+                suite.get_time_as_timestamp().seconds(),
+                suite.get_time_as_timestamp().plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    notify_recipient: Some(notify_recipient),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        (suite, receiver)
+    }
+
+    fn event(event: StreamEvent) -> StreamReceiveMsg {
+        StreamReceiveMsg {
+            stream_idx: 1,
+            sender: "funder".to_string(),
+            asset: AssetInfo::Native(DENOM.to_string()),
+            event,
+        }
+    }
+
+    #[test]
+    fn test_recipient_is_notified_of_create_claim_and_cancel() {
+        let (mut suite, receiver) = setup_with_receiver(true);
+        suite.update_time(30);
+        suite
+            .claim_from_stream_as(receiver.clone(), 30u128, DENOM, 1u64)
+            .unwrap();
+        suite.update_time(20);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+
+        assert_eq!(
+            suite.query_received_events(&receiver),
+            vec![
+                event(StreamEvent::Created {
+                    deposit: Uint128::new(100)
+                }),
+                event(StreamEvent::Claimed {
+                    amount: Uint128::new(30),
+                    to: receiver.to_string(),
+                }),
+                event(StreamEvent::Cancelled {
+                    recipient_amount: Uint128::new(20),
+                    sender_amount: Uint128::new(50),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recipient_is_not_notified_without_opting_in() {
+        let (mut suite, receiver) = setup_with_receiver(false);
+        suite.update_time(30);
+        suite
+            .claim_from_stream_as(receiver.clone(), 30u128, DENOM, 1u64)
+            .unwrap();
+
+        assert!(suite.query_received_events(&receiver).is_empty());
+    }
+
+    #[test]
+    fn test_failing_recipient_can_not_block_a_cancel() {
+        let (mut suite, receiver) = setup_with_receiver(true);
+        suite.reject_stream_events(&receiver).unwrap();
+        suite.update_time(40);
+
+        // A claim is the recipient's own call so a failing hook fails it
+        suite
+            .claim_from_stream_as(receiver.clone(), 10u128, DENOM, 1u64)
+            .unwrap_err();
+
+        // The sender can still cancel and both sides are paid out
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(
+            suite.query_balance(receiver.as_str(), DENOM).unwrap(),
+            40u128
+        );
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128 - 40u128
+        );
+    }
+
+    #[test]
+    fn test_transfer_to_plain_account_stops_notifications() {
+        let (mut suite, receiver) = setup_with_receiver(true);
+        let bob = Addr::unchecked("bob");
+        suite
+            .transfer_stream(receiver.clone(), 1u64, bob.clone())
+            .unwrap();
+        suite.update_time(30);
+
+        // Bob does not implement the receive hook, claiming still works
+        suite
+            .claim_from_stream_as(bob.clone(), 30u128, DENOM, 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance(bob.as_str(), DENOM).unwrap(), 30u128);
+        assert!(!suite.query_stream_by_index(1u64).unwrap().streams[0].notify_recipient);
+        // Only the creation reached the previous recipient
+        assert_eq!(suite.query_received_events(&receiver).len(), 1);
+    }
+}
+
+mod split_recipients {
+    use std::str::FromStr;
+
+    use cosmwasm_std::{Addr, Coin, Decimal, Uint128, Uint256};
+
+    use crate::{
+        msg::StreamOptions,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn funded_suite() -> Suite {
+        SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build()
+    }
+
+    fn create_split_stream(
+        suite: &mut Suite,
+        shares: &[(&str, &str)],
+    ) -> anyhow::Result<cw_multi_test::AppResponse> {
+        let start = suite.get_time_as_timestamp().seconds();
+        suite.create_stream_with_options(
+            Addr::unchecked("funder"),
+            Addr::unchecked("alice"),
+            100u128,
+            DENOM,
+            start,
+            start + 100,
+            &[Coin {
+                denom: DENOM.to_string(),
+                amount: Uint128::from(100u128),
+            }],
+            StreamOptions {
+                shares: Some(
+                    shares
+                        .iter()
+                        .map(|(addr, share)| (addr.to_string(), Decimal::from_str(share).unwrap()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn team_stream() -> Suite {
+        let mut suite = funded_suite();
+        create_split_stream(
+            &mut suite,
+            &[("alice", "0.5"), ("bob", "0.3"), ("carol", "0.2")],
+        )
+        .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_each_recipient_claims_their_share() {
+        let mut suite = team_stream();
+        suite.update_time(33);
+
+        // 33 vested, alice is owed 16.5 and bob 9.9, both rounded down
+        suite
+            .claim_from_stream_as(Addr::unchecked("alice"), 16u128, DENOM, 1u64)
+            .unwrap();
+        let err = suite
+            .claim_from_stream_as(Addr::unchecked("bob"), 10u128, DENOM, 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NotEnoughAvailableBalance {},
+            err.downcast().unwrap()
+        );
+        suite
+            .claim_from_stream_as(Addr::unchecked("bob"), 9u128, DENOM, 1u64)
+            .unwrap();
+
+        // Once fully vested everyone gets exactly their share, the last share absorbs the rounding
+        suite.update_time(67);
+        suite
+            .claim_from_stream_as(Addr::unchecked("alice"), 34u128, DENOM, 1u64)
+            .unwrap();
+        suite
+            .claim_from_stream_as(Addr::unchecked("bob"), 21u128, DENOM, 1u64)
+            .unwrap();
+        suite
+            .claim_from_stream_as(Addr::unchecked("carol"), 20u128, DENOM, 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 50u128);
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 30u128);
+        assert_eq!(suite.query_balance("carol", DENOM).unwrap(), 20u128);
+
+        let stream = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.remaining_balance, Uint256::zero());
+    }
+
+    #[test]
+    fn test_cancel_settles_every_recipient() {
+        let mut suite = team_stream();
+        suite.update_time(50);
+        suite
+            .claim_from_stream_as(Addr::unchecked("bob"), 15u128, DENOM, 1u64)
+            .unwrap();
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 25u128);
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 15u128);
+        assert_eq!(suite.query_balance("carol", DENOM).unwrap(), 10u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128 - 50u128
+        );
+    }
+
+    #[test]
+    fn test_share_holders_see_the_stream() {
+        let mut suite = team_stream();
+        let streams = suite
+            .query_streams_by_payee(Addr::unchecked("carol"))
+            .unwrap();
+        assert_eq!(streams.streams.len(), 1);
+        assert_eq!(streams.streams[0].stream_idx, 1u64);
+
+        // Only share holders can claim and nobody can take the stream over on their own
+        suite.update_time(10);
+        let err = suite
+            .claim_from_stream_as(Addr::unchecked("dave"), 1u128, DENOM, 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        let err = suite
+            .transfer_stream(Addr::unchecked("alice"), 1u64, Addr::unchecked("dave"))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::SplitStream { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_shares() {
+        let mut suite = funded_suite();
+        for shares in [
+            vec![("alice", "0.5"), ("bob", "0.4")],
+            vec![("alice", "0.5"), ("alice", "0.5")],
+            vec![("bob", "0.5"), ("carol", "0.5")],
+            vec![("alice", "1"), ("bob", "0")],
+        ] {
+            let err = create_split_stream(&mut suite, &shares).unwrap_err();
+            assert_eq!(ContractError::InvalidShares {}, err.downcast().unwrap());
+        }
+    }
+}
+
+mod split_streams {
+    use std::str::FromStr;
+
+    use cosmwasm_std::{Addr, Coin, Decimal, Uint128, Uint256};
+
+    use crate::{
+        msg::AmountOrRatio,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_stream() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        suite
+            .create_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                100u128,
+                DENOM,
+                suite.get_time_as_timestamp().seconds(),
+                suite.get_time_as_timestamp().plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+        suite
+    }
+
+    fn claimable(suite: &mut Suite, stream_idx: u64) -> u128 {
+        suite.query_stream_claimable_amount(stream_idx).unwrap()
+    }
+
+    #[test]
+    fn test_split_vests_proportionally() {
+        let mut suite = setup_with_stream();
+        suite.update_time(40);
+        suite
+            .split_stream(
+                Addr::unchecked("alice"),
+                1u64,
+                AmountOrRatio::Amount(Uint128::new(30)),
+                Addr::unchecked("bob"),
+            )
+            .unwrap();
+
+        // Alice keeps what vested so far, half of the remaining 60 now goes to bob
+        assert_eq!(claimable(&mut suite, 1), 40u128);
+        assert_eq!(claimable(&mut suite, 2), 0u128);
+
+        suite.update_time(30);
+        assert_eq!(claimable(&mut suite, 1), 55u128);
+        assert_eq!(claimable(&mut suite, 2), 15u128);
+
+        // Together both streams vest exactly the original deposit
+        suite.update_time(30);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 70u128, DENOM, Some(1u64))
+            .unwrap();
+        suite
+            .withdraw_from_stream(Addr::unchecked("bob"), 30u128, DENOM, Some(2u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 70u128);
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 30u128);
+    }
+
+    #[test]
+    fn test_split_by_ratio() {
+        let mut suite = setup_with_stream();
+        suite.update_time(40);
+        suite
+            .split_stream(
+                Addr::unchecked("alice"),
+                1u64,
+                AmountOrRatio::Ratio(Decimal::from_str("0.25").unwrap()),
+                Addr::unchecked("escrow"),
+            )
+            .unwrap();
+
+        let split = suite
+            .query_stream_by_index(2u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(split.deposit, Uint256::from(15u128));
+        assert_eq!(split.recipient, Addr::unchecked("escrow"));
+        let original = suite
+            .query_stream_by_index(1u64)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(original.deposit, Uint256::from(85u128));
+        assert_eq!(original.remaining_balance, Uint256::from(85u128));
+    }
+
+    #[test]
+    fn test_split_is_limited_to_unvested_and_recipient() {
+        let mut suite = setup_with_stream();
+        suite.update_time(40);
+
+        let err = suite
+            .split_stream(
+                Addr::unchecked("alice"),
+                1u64,
+                AmountOrRatio::Amount(Uint128::new(61)),
+                Addr::unchecked("bob"),
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+
+        let err = suite
+            .split_stream(
+                Addr::unchecked("funder"),
+                1u64,
+                AmountOrRatio::Amount(Uint128::new(10)),
+                Addr::unchecked("bob"),
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    }
+}
+
+mod merge_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_utils::Expiration;
+
+    use crate::{
+        msg::StreamOptions,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn create(suite: &mut Suite, recipient: &str, deposit: u128, start: u64, stop: u64) {
+        suite
+            .create_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked(recipient),
+                deposit,
+                DENOM,
+                start,
+                stop,
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(deposit),
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    fn create_with_options(suite: &mut Suite, recipient: &Addr, options: StreamOptions) {
+        let now = suite.get_time_as_timestamp().seconds();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                recipient.clone(),
+                100u128,
+                DENOM,
+                now,
+                now + 100,
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                options,
+            )
+            .unwrap();
+    }
+
+    fn assert_incompatible(suite: &mut Suite, recipient: &Addr, stream_idxs: Vec<u64>) {
+        let err = suite
+            .merge_streams(recipient.clone(), stream_idxs)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::IncompatibleStreams {},
+            err.downcast().unwrap()
+        );
+    }
+
+    fn setup_with_top_ups() -> (Suite, u64) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build();
+        let now = suite.get_time_as_timestamp().seconds();
+        create(&mut suite, "alice", 100, now, now + 100);
+        create(&mut suite, "alice", 50, now + 50, now + 150);
+        (suite, now)
+    }
+
+    #[test]
+    fn test_merged_stream_vests_the_sum() {
+        let (mut suite, _) = setup_with_top_ups();
+        suite.update_time(60);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 20u128, DENOM, Some(1u64))
+            .unwrap();
+        suite
+            .merge_streams(Addr::unchecked("alice"), vec![1, 2])
+            .unwrap();
+
+        // 60 + 5 vested, 20 of which were already claimed
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 45u128);
+        suite.query_stream_by_index(2).unwrap_err();
+        assert_eq!(
+            suite
+                .query_streams_by_payee(Addr::unchecked("alice"))
+                .unwrap()
+                .streams
+                .len(),
+            1
+        );
+
+        suite.update_time(40);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 105u128);
+        suite.update_time(50);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 130u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 150u128);
+    }
+
+    #[test]
+    fn test_only_compatible_streams_merge() {
+        let (mut suite, now) = setup_with_top_ups();
+        create(&mut suite, "bob", 100, now, now + 100);
+
+        let err = suite
+            .merge_streams(Addr::unchecked("alice"), vec![1, 3])
+            .unwrap_err();
+        assert_eq!(
+            ContractError::IncompatibleStreams {},
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .merge_streams(Addr::unchecked("alice"), vec![1, 1])
+            .unwrap_err();
+        assert_eq!(
+            ContractError::IncompatibleStreams {},
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .merge_streams(Addr::unchecked("funder"), vec![1, 2])
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_streams_with_different_terms_do_not_merge() {
+        let (mut suite, _) = setup_with_top_ups();
+        let alice = Addr::unchecked("alice");
+        let height = suite.get_block_height();
+
+        // A claim deadline, a transfer right or a pending acceptance must not be dropped or added by a merge
+        create_with_options(
+            &mut suite,
+            &alice,
+            StreamOptions {
+                claim_deadline: Some(Expiration::AtHeight(height + 1000)),
+                ..Default::default()
+            },
+        );
+        assert_incompatible(&mut suite, &alice, vec![1, 3]);
+        assert_incompatible(&mut suite, &alice, vec![3, 1]);
+
+        create_with_options(
+            &mut suite,
+            &alice,
+            StreamOptions {
+                sender_can_transfer: Some(true),
+                ..Default::default()
+            },
+        );
+        assert_incompatible(&mut suite, &alice, vec![1, 4]);
+
+        create_with_options(
+            &mut suite,
+            &alice,
+            StreamOptions {
+                requires_acceptance: Some(true),
+                acceptance_deadline: Some(Expiration::AtHeight(height + 1000)),
+                ..Default::default()
+            },
+        );
+        assert_incompatible(&mut suite, &alice, vec![1, 5]);
+
+        // The original streams still merge
+        suite.merge_streams(alice, vec![1, 2]).unwrap();
+    }
+
+    #[test]
+    fn test_notifying_and_silent_streams_do_not_merge() {
+        let (mut suite, _) = setup_with_top_ups();
+        let receiver = suite.instantiate_stream_receiver();
+        create_with_options(
+            &mut suite,
+            &receiver,
+            StreamOptions {
+                notify_recipient: Some(true),
+                ..Default::default()
+            },
+        );
+        create_with_options(&mut suite, &receiver, StreamOptions::default());
+
+        assert_incompatible(&mut suite, &receiver, vec![3, 4]);
+        assert_incompatible(&mut suite, &receiver, vec![4, 3]);
+    }
+}
+
+mod recurring_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_utils::Duration;
+
+    use crate::{
+        msg::StreamOptions,
+        state::Recurrence,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn funded_suite() -> Suite {
+        SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000000000u128),
+                }],
+            )
+            .build()
+    }
+
+    fn subscribe(
+        suite: &mut Suite,
+        deposit: u128,
+        stop_after: Option<u64>,
+        max_periods: Option<u64>,
+    ) -> anyhow::Result<cw_multi_test::AppResponse> {
+        let start = suite.get_time_as_timestamp().seconds();
+        suite.create_recurring_stream(
+            Addr::unchecked("funder"),
+            Addr::unchecked("saas"),
+            deposit,
+            DENOM,
+            start,
+            stop_after.map(|stop| start + stop),
+            &[Coin {
+                denom: DENOM.to_string(),
+                amount: Uint128::from(deposit),
+            }],
+            Recurrence {
+                period: Duration::Time(10),
+                amount_per_period: Uint128::new(25),
+                max_periods,
+            },
+            None,
+        )
+    }
+
+    fn coins(amount: u128) -> Vec<Coin> {
+        vec![Coin {
+            denom: DENOM.to_string(),
+            amount: Uint128::from(amount),
+        }]
+    }
+
+    #[test]
+    fn test_each_period_vests_at_its_start() {
+        let mut suite = funded_suite();
+        subscribe(&mut suite, 100, None, Some(4)).unwrap();
+
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.stop_time.value() - stream.start_time.value(), 40);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 25u128);
+
+        suite.update_time(9);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 25u128);
+        suite.update_time(1);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 50u128);
+        suite.update_time(25);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 100u128);
+        suite.update_time(100);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 100u128);
+    }
+
+    #[test]
+    fn test_underfunded_stream_lapses() {
+        let mut suite = funded_suite();
+        // Ten periods are scheduled but only two are paid for
+        subscribe(&mut suite, 60, Some(100), None).unwrap();
+
+        suite.update_time(50);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 50u128);
+        suite
+            .withdraw_from_stream(Addr::unchecked("saas"), 50u128, DENOM, Some(1u64))
+            .unwrap();
+
+        // The lapsed stream keeps what could not pay for a period, the sender gets it back on cancel
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("saas", DENOM).unwrap(), 50u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1000000000u128 - 50u128
+        );
+    }
+
+    #[test]
+    fn test_deposit_must_cover_a_period() {
+        let mut suite = funded_suite();
+        let err = subscribe(&mut suite, 20, None, Some(4)).unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        let err = subscribe(&mut suite, 100, None, None).unwrap_err();
+        assert_eq!(ContractError::InvalidSchedule {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_top_ups_pay_for_more_periods() {
+        let mut suite = funded_suite();
+        // Ten periods are scheduled and two paid for
+        subscribe(&mut suite, 50, Some(100), None).unwrap();
+
+        let err = suite
+            .top_up_stream(Addr::unchecked("funder"), 1, None, &[])
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        // Only the periods still scheduled can be paid for
+        let err = suite
+            .top_up_stream(Addr::unchecked("funder"), 1, None, &coins(225))
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+
+        suite.update_time(15);
+        suite
+            .top_up_stream(Addr::unchecked("funder"), 1, None, &coins(50))
+            .unwrap();
+        suite.update_time(20);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 100u128);
+
+        // The fifth period started without funds, the stream lapsed for good
+        suite.update_time(10);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 100u128);
+        let err = suite
+            .top_up_stream(Addr::unchecked("funder"), 1, None, &coins(50))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamLapsed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_top_ups_from_the_prepaid_balance() {
+        let mut suite = funded_suite();
+        suite
+            .deposit(Addr::unchecked("funder"), &coins(100))
+            .unwrap();
+        let start = suite.get_time_as_timestamp().seconds();
+        suite
+            .create_recurring_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("saas"),
+                25,
+                DENOM,
+                start,
+                Some(start + 100),
+                &[],
+                Recurrence {
+                    period: Duration::Time(10),
+                    amount_per_period: Uint128::new(25),
+                    max_periods: None,
+                },
+                Some(StreamOptions {
+                    from_balance: Some(true),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("funder", DENOM), (75, 25));
+
+        let err = suite
+            .top_up_stream(Addr::unchecked("saas"), 1, Some(25), &[])
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        // The top-up comes out of the balance, not from funds sent along
+        let err = suite
+            .top_up_stream(Addr::unchecked("funder"), 1, Some(25), &coins(25))
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        let err = suite
+            .top_up_stream(Addr::unchecked("funder"), 1, Some(100), &[])
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NotEnoughAvailableFunds {},
+            err.downcast().unwrap()
+        );
+        suite
+            .top_up_stream(Addr::unchecked("funder"), 1, Some(50), &[])
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("funder", DENOM), (25, 75));
+
+        suite.update_time(25);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 75u128);
+        suite
+            .withdraw_from_stream(Addr::unchecked("saas"), 75u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("funder", DENOM), (25, 0));
+    }
+}
+
+mod prepaid_balances {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+
+    use crate::{
+        msg::StreamOptions,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn stream_from_balance(
+        suite: &mut Suite,
+        deposit: u128,
+    ) -> anyhow::Result<cw_multi_test::AppResponse> {
+        let start = suite.get_time_as_timestamp().seconds();
+        suite.create_stream_with_options(
+            Addr::unchecked("treasury"),
+            Addr::unchecked("alice"),
+            deposit,
+            DENOM,
+            start,
+            start + 100,
+            &[],
+            StreamOptions {
+                from_balance: Some(true),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn setup_with_balance() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "treasury",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        suite
+            .deposit(
+                Addr::unchecked("treasury"),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(500u128),
+                }],
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_streams_draw_from_the_balance() {
+        let mut suite = setup_with_balance();
+        stream_from_balance(&mut suite, 100).unwrap();
+        stream_from_balance(&mut suite, 100).unwrap();
+        assert_eq!(suite.query_sender_balance("treasury", DENOM), (300, 200));
+
+        suite.update_time(50);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 30u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("treasury", DENOM), (300, 170));
+
+        // What is left of a cancelled stream returns to the balance instead of being sent out
+        suite
+            .cancel_stream(Addr::unchecked("treasury"), 2u64)
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("treasury", DENOM), (350, 70));
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 80u128);
+        assert_eq!(suite.query_balance("treasury", DENOM).unwrap(), 500u128);
+    }
+
+    #[test]
+    fn test_only_free_balance_can_be_used() {
+        let mut suite = setup_with_balance();
+        stream_from_balance(&mut suite, 400).unwrap();
+
+        let err = stream_from_balance(&mut suite, 101).unwrap_err();
+        assert_eq!(
+            ContractError::NotEnoughAvailableFunds {},
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .withdraw_balance(Addr::unchecked("treasury"), 101u128, DENOM)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NotEnoughAvailableFunds {},
+            err.downcast().unwrap()
+        );
+
+        suite
+            .withdraw_balance(Addr::unchecked("treasury"), 100u128, DENOM)
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("treasury", DENOM), (0, 400));
+        assert_eq!(suite.query_balance("treasury", DENOM).unwrap(), 600u128);
+    }
+
+    #[test]
+    fn test_funds_sent_with_a_stream_from_balance_are_rejected() {
+        let mut suite = setup_with_balance();
+        let start = suite.get_time_as_timestamp().seconds();
+        let err = suite
+            .create_stream_with_options(
+                Addr::unchecked("treasury"),
+                Addr::unchecked("alice"),
+                100u128,
+                DENOM,
+                start,
+                start + 100,
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    from_balance: Some(true),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        assert_eq!(suite.query_sender_balance("treasury", DENOM), (500, 0));
+        assert_eq!(suite.query_balance("treasury", DENOM).unwrap(), 500u128);
+    }
+}
+
+mod stream_acceptance {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+    use cw_utils::Expiration;
+
+    use crate::{
+        msg::StreamOptions,
+        state::StreamStatus,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_offer() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("dao"),
+                100u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    requires_acceptance: Some(true),
+                    acceptance_deadline: Some(Expiration::AtTime(now.plus_seconds(50))),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_stream_starts_once_accepted() {
+        let mut suite = setup_with_offer();
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.status, StreamStatus::Pending);
+        assert!(stream.awaiting_acceptance.is_some());
+
+        suite.update_time(20);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 0u128);
+        let err = suite
+            .accept_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite.accept_stream(Addr::unchecked("dao"), 1u64).unwrap();
+
+        // Once accepted the stream follows its schedule
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.status, StreamStatus::Active);
+        assert_eq!(stream.awaiting_acceptance, None);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 20u128);
+
+        let err = suite
+            .reclaim_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NotAwaitingAcceptance { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sender_reclaims_after_the_deadline() {
+        let mut suite = setup_with_offer();
+        suite.update_time(20);
+        let err = suite
+            .reclaim_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::AcceptanceOpen {}, err.downcast().unwrap());
+
+        suite.update_time(40);
+        let err = suite
+            .accept_stream(Addr::unchecked("dao"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::AcceptanceExpired {}, err.downcast().unwrap());
+
+        suite
+            .reclaim_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 1000u128);
+        assert_eq!(suite.query_balance("dao", DENOM).unwrap(), 0u128);
+        let err = suite
+            .accept_stream(Addr::unchecked("dao"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_acceptance_needs_a_deadline() {
+        let mut suite = setup_with_offer();
+        let now = suite.get_time_as_timestamp();
+        for deadline in [
+            None,
+            Some(Expiration::Never {}),
+            Some(Expiration::AtTime(now.minus_seconds(1))),
+        ] {
+            let err = suite
+                .create_stream_with_options(
+                    Addr::unchecked("funder"),
+                    Addr::unchecked("dao"),
+                    100u128,
+                    DENOM,
+                    now.seconds(),
+                    now.plus_seconds(100).seconds(),
+                    &[Coin {
+                        denom: DENOM.to_string(),
+                        amount: Uint128::from(100u128),
+                    }],
+                    StreamOptions {
+                        requires_acceptance: Some(true),
+                        acceptance_deadline: deadline,
+                        ..Default::default()
+                    },
+                )
+                .unwrap_err();
+            assert_eq!(
+                ContractError::InvalidAcceptanceDeadline {},
+                err.downcast().unwrap()
+            );
+        }
+    }
+}
+
+mod claim_deadlines {
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
+    use cw_utils::Expiration;
+
+    use crate::{
+        msg::StreamOptions,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup_with_deadline() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                100u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    claim_deadline: Some(Expiration::AtTime(now.plus_seconds(150))),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_sender_sweeps_unclaimed_funds() {
+        let mut suite = setup_with_deadline();
+        suite.update_time(60);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 20u128, DENOM, Some(1u64))
+            .unwrap();
+        let claimable = suite.query_stream_claimable(1).unwrap();
+        assert_eq!(claimable.amount_available, Uint256::from(40u128));
+        assert_eq!(claimable.amount_sweepable, Uint256::zero());
+
+        suite.update_time(100);
+        let claimable = suite.query_stream_claimable(1).unwrap();
+        assert_eq!(claimable.amount_available, Uint256::zero());
+        assert_eq!(claimable.amount_sweepable, Uint256::from(80u128));
+        assert!(claimable.claim_deadline.is_some());
+        let err = suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 10u128, DENOM, Some(1u64))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::ClaimDeadlinePassed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+
+        let err = suite
+            .sweep_expired(Addr::unchecked("alice"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite
+            .sweep_expired(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 980u128);
+        assert_eq!(
+            suite.query_stream_claimable(1).unwrap().amount_sweepable,
+            Uint256::zero()
+        );
+    }
+
+    #[test]
+    fn test_no_sweep_before_the_deadline() {
+        let mut suite = setup_with_deadline();
+        suite.update_time(120);
+        let err = suite
+            .sweep_expired(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::ClaimDeadlineNotPassed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+        // Until then the recipient claims as usual
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 100u128, DENOM, Some(1u64))
+            .unwrap();
+    }
+}
+
+mod airdrop_streams {
+    use cosmwasm_std::{Addr, Coin, Uint128, Uint256};
+    use cw_utils::Expiration;
+    use sha2::{Digest, Sha256};
+    use wynd_utils::Curve;
+
+    use crate::{
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn leaf(address: &str, amount: u128) -> [u8; 32] {
+        Sha256::digest(format!("{}{}", address, amount).as_bytes()).into()
+    }
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        let mut hasher = Sha256::new();
+        hasher.update(first);
+        hasher.update(second);
+        hasher.finalize().into()
+    }
+
+    fn coins(amount: u128) -> Vec<Coin> {
+        vec![Coin {
+            denom: DENOM.to_string(),
+            amount: Uint128::from(amount),
+        }]
+    }
+
+    // A campaign giving alice 100 and bob 50, vesting over 100 seconds and claimable for 1000 seconds
+    fn setup_with_airdrop() -> (Suite, u64) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "foundation",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        let root = parent(leaf("alice", 100), leaf("bob", 50));
+        let start = suite.get_time_as_timestamp().seconds();
+        suite
+            .register_airdrop(
+                Addr::unchecked("foundation"),
+                &hex::encode(root),
+                150,
+                DENOM,
+                start,
+                start + 100,
+                Some(Curve::saturating_linear((start, 0), (start + 100, 1000))),
+                Some(Expiration::AtTime(
+                    suite.get_time_as_timestamp().plus_seconds(1000),
+                )),
+                &coins(150),
+            )
+            .unwrap();
+        (suite, start)
+    }
+
+    #[test]
+    fn test_claims_vest_under_the_template() {
+        let (mut suite, _) = setup_with_airdrop();
+        suite.update_time(50);
+
+        suite
+            .claim_airdrop_stream(
+                Addr::unchecked("alice"),
+                1,
+                100,
+                vec![hex::encode(leaf("bob", 50))],
+            )
+            .unwrap();
+        suite
+            .claim_airdrop_stream(
+                Addr::unchecked("bob"),
+                1,
+                50,
+                vec![hex::encode(leaf("alice", 100))],
+            )
+            .unwrap();
+
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.recipient, Addr::unchecked("alice"));
+        assert_eq!(stream.sender, Addr::unchecked("foundation"));
+        assert_eq!(stream.deposit, Uint256::from(100u128));
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 50u128);
+        assert_eq!(suite.query_stream_claimable_amount(2).unwrap(), 25u128);
+
+        suite.update_time(50);
+        suite
+            .withdraw_from_stream(Addr::unchecked("bob"), 50u128, DENOM, Some(2u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 50u128);
+    }
+
+    #[test]
+    fn test_claims_need_a_valid_proof_once() {
+        let (mut suite, _) = setup_with_airdrop();
+
+        let err = suite
+            .claim_airdrop_stream(
+                Addr::unchecked("alice"),
+                1,
+                150,
+                vec![hex::encode(leaf("bob", 50))],
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidProof {}, err.downcast().unwrap());
+        let err = suite
+            .claim_airdrop_stream(
+                Addr::unchecked("carol"),
+                1,
+                50,
+                vec![hex::encode(leaf("alice", 100))],
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidProof {}, err.downcast().unwrap());
+
+        suite
+            .claim_airdrop_stream(
+                Addr::unchecked("alice"),
+                1,
+                100,
+                vec![hex::encode(leaf("bob", 50))],
+            )
+            .unwrap();
+        let err = suite
+            .claim_airdrop_stream(
+                Addr::unchecked("alice"),
+                1,
+                100,
+                vec![hex::encode(leaf("bob", 50))],
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::AlreadyClaimed {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_creator_reclaims_what_is_left_once_expired() {
+        let (mut suite, _) = setup_with_airdrop();
+        suite
+            .claim_airdrop_stream(
+                Addr::unchecked("alice"),
+                1,
+                100,
+                vec![hex::encode(leaf("bob", 50))],
+            )
+            .unwrap();
+        let err = suite
+            .reclaim_airdrop(Addr::unchecked("foundation"), 1)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::AirdropNotExpired { airdrop_id: 1 },
+            err.downcast().unwrap()
+        );
+
+        suite.update_time(1000);
+        let err = suite
+            .claim_airdrop_stream(
+                Addr::unchecked("bob"),
+                1,
+                50,
+                vec![hex::encode(leaf("alice", 100))],
+            )
+            .unwrap_err();
+        assert_eq!(
+            ContractError::AirdropExpired { airdrop_id: 1 },
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .reclaim_airdrop(Addr::unchecked("bob"), 1)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite
+            .reclaim_airdrop(Addr::unchecked("foundation"), 1)
+            .unwrap();
+        assert_eq!(suite.query_balance("foundation", DENOM).unwrap(), 900u128);
+        // Only once
+        let err = suite
+            .reclaim_airdrop(Addr::unchecked("foundation"), 1)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_registration_takes_exactly_what_is_sent() {
+        let (mut suite, start) = setup_with_airdrop();
+        let root = parent(leaf("alice", 100), leaf("bob", 50));
+        let err = suite
+            .register_airdrop(
+                Addr::unchecked("foundation"),
+                &hex::encode(root),
+                150,
+                DENOM,
+                start,
+                start + 100,
+                None,
+                None,
+                &coins(200),
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        assert_eq!(suite.query_balance("foundation", DENOM).unwrap(), 850u128);
+    }
+}
+
+mod signed_offers {
+    use cosmwasm_std::{to_binary, Addr, Coin, Uint128, Uint256};
+    use cw_asset::Asset;
+    use cw_utils::Expiration;
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    use crate::{
+        msg::StreamOffer,
+        state::StreamTime,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&[7u8; 32]).unwrap()
+    }
+
+    fn sign(key: &SigningKey, offer: &StreamOffer) -> Vec<u8> {
+        let signature: Signature = key.sign(to_binary(offer).unwrap().as_slice());
+        signature.to_bytes().to_vec()
+    }
+
+    // The employer prepaid 1000 and registered its key
+    fn setup() -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "employer",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        suite
+            .deposit(
+                Addr::unchecked("employer"),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .unwrap();
+        let public_key = signing_key().verifying_key().to_encoded_point(true);
+        suite
+            .register_offer_key(Addr::unchecked("employer"), public_key.as_bytes())
+            .unwrap();
+        suite
+    }
+
+    fn offer(suite: &Suite, nonce: u64, amount: u128) -> StreamOffer {
+        let now = suite.get_time_as_timestamp();
+        StreamOffer {
+            sender: "employer".to_string(),
+            recipient: "employee".to_string(),
+            asset: Asset::native(DENOM, amount),
+            start_time: Some(StreamTime::Time(now)),
+            stop_time: Some(StreamTime::Time(now.plus_seconds(100))),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            nonce,
+            expires: Expiration::AtTime(now.plus_seconds(1000)),
+            contract: suite.paystreams_addr.to_string(),
+            chain_id: suite.get_chain_id(),
+        }
+    }
+
+    #[test]
+    fn test_anyone_can_submit_a_signed_offer() {
+        let mut suite = setup();
+        let offer = offer(&suite, 1, 400);
+        let signature = sign(&signing_key(), &offer);
+
+        suite
+            .submit_signed_offer(Addr::unchecked("employee"), offer, &signature)
+            .unwrap();
+
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.sender, Addr::unchecked("employer"));
+        assert_eq!(stream.recipient, Addr::unchecked("employee"));
+        assert_eq!(stream.deposit, Uint256::from(400u128));
+        assert!(stream.funded_from_balance);
+        assert_eq!(suite.query_sender_balance("employer", DENOM), (600, 400));
+
+        suite.update_time(100);
+        suite
+            .withdraw_from_stream(Addr::unchecked("employee"), 400u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("employee", DENOM).unwrap(), 400u128);
+    }
+
+    #[test]
+    fn test_offers_cannot_be_replayed() {
+        let mut suite = setup();
+        let offer = offer(&suite, 1, 400);
+        let signature = sign(&signing_key(), &offer);
+
+        suite
+            .submit_signed_offer(Addr::unchecked("employee"), offer.clone(), &signature)
+            .unwrap();
+        let err = suite
+            .submit_signed_offer(Addr::unchecked("employee"), offer, &signature)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NonceUsed { nonce: 1 },
+            err.downcast().unwrap()
+        );
+        assert_eq!(suite.query_sender_balance("employer", DENOM), (600, 400));
+    }
+
+    #[test]
+    fn test_tampered_offer_is_rejected() {
+        let mut suite = setup();
+        let offer = offer(&suite, 1, 400);
+        let signature = sign(&signing_key(), &offer);
+
+        let tampered = StreamOffer {
+            asset: Asset::native(DENOM, 900u128),
+            ..offer
+        };
+        let err = suite
+            .submit_signed_offer(Addr::unchecked("employee"), tampered, &signature)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidSignature {}, err.downcast().unwrap());
+
+        // Signed by another key
+        let other = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let offer = self::offer(&suite, 2, 400);
+        let signature = sign(&other, &offer);
+        let err = suite
+            .submit_signed_offer(Addr::unchecked("employee"), offer, &signature)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidSignature {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_expired_and_revoked_offers() {
+        let mut suite = setup();
+        let expired = offer(&suite, 1, 400);
+        let signature = sign(&signing_key(), &expired);
+        suite.update_time(1000);
+        let err = suite
+            .submit_signed_offer(Addr::unchecked("employee"), expired, &signature)
+            .unwrap_err();
+        assert_eq!(ContractError::OfferExpired {}, err.downcast().unwrap());
+
+        let revoked = offer(&suite, 2, 400);
+        let signature = sign(&signing_key(), &revoked);
+        suite.revoke_offer(Addr::unchecked("employer"), 2).unwrap();
+        let err = suite
+            .submit_signed_offer(Addr::unchecked("employee"), revoked, &signature)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::NonceUsed { nonce: 2 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_offer_needs_enough_prepaid_balance() {
+        let mut suite = setup();
+        let offer = offer(&suite, 1, 1500);
+        let signature = sign(&signing_key(), &offer);
+
+        suite
+            .submit_signed_offer(Addr::unchecked("employee"), offer, &signature)
+            .unwrap_err();
+        assert_eq!(suite.query_sender_balance("employer", DENOM), (1000, 0));
+    }
+
+    #[test]
+    fn test_offer_submission_rejects_funds() {
+        let mut suite = setup();
+        suite
+            .withdraw_balance(Addr::unchecked("employer"), 100u128, DENOM)
+            .unwrap();
+        let offer = offer(&suite, 1, 400);
+        let signature = sign(&signing_key(), &offer);
+
+        let err = suite
+            .submit_signed_offer_with_funds(
+                Addr::unchecked("employer"),
+                offer,
+                &signature,
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+        assert_eq!(suite.query_sender_balance("employer", DENOM), (900, 0));
+        assert_eq!(suite.query_balance("employer", DENOM).unwrap(), 100u128);
+    }
+}
+
+mod config {
+    use std::str::FromStr;
+
+    use cosmwasm_std::{Addr, Decimal};
+
+    use crate::{tests::suite::SuiteBuilder, ContractError};
+
+    #[test]
+    fn test_owner_updates_config() {
+        let mut suite = SuiteBuilder::new().build();
+        let fees = vec![Decimal::from_str("0.01").unwrap()];
+
+        let err = suite
+            .update_config(Addr::unchecked("someone"), None, Some(fees.clone()))
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite
+            .update_config(
+                Addr::unchecked("owner"),
+                Some("new_owner".to_string()),
+                Some(fees.clone()),
+            )
+            .unwrap();
+        let config = suite.query_config().unwrap();
+        assert_eq!(config.owner, Addr::unchecked("new_owner"));
+        assert_eq!(config.fees, Some(fees));
+        assert_eq!(config.fee_asset, None);
+
+        // The previous owner lost its rights
+        suite
+            .update_config(Addr::unchecked("owner"), None, None)
+            .unwrap_err();
+    }
+}
+
+mod voting_power {
+    use cosmwasm_std::{Addr, Coin, Uint128};
+
+    use crate::tests::suite::{Suite, SuiteBuilder};
+
+    const DENOM: &str = "ujuno";
+    const OTHER_DENOM: &str = "ibc/something/axlusdc";
+
+    fn setup() -> Suite {
+        SuiteBuilder::new()
+            .with_governance_denom(DENOM)
+            .with_funds(
+                "dao",
+                &[
+                    Coin {
+                        denom: DENOM.to_string(),
+                        amount: Uint128::from(1000u128),
+                    },
+                    Coin {
+                        denom: OTHER_DENOM.to_string(),
+                        amount: Uint128::from(1000u128),
+                    },
+                ],
+            )
+            .build()
+    }
+
+    fn create_stream(suite: &mut Suite, recipient: &str, amount: u128, denom: &str) {
+        let start = suite.get_time_as_timestamp().seconds();
+        suite
+            .create_stream(
+                Addr::unchecked("dao"),
+                Addr::unchecked(recipient),
+                amount,
+                denom,
+                start,
+                start + 100,
+                &[Coin {
+                    denom: denom.to_string(),
+                    amount: Uint128::from(amount),
+                }],
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_locked_funds_carry_voting_power() {
+        let mut suite = setup();
+        let created_at = suite.get_block_height();
+        create_stream(&mut suite, "alice", 400, DENOM);
+        create_stream(&mut suite, "bob", 100, DENOM);
+        // Streams of other assets don't count
+        create_stream(&mut suite, "alice", 300, OTHER_DENOM);
+        suite.advance_blocks(1);
+
+        assert_eq!(suite.query_voting_power("alice", None), 400);
+        assert_eq!(suite.query_voting_power("bob", None), 100);
+        assert_eq!(suite.query_total_power(None), 500);
+        // Power is only there from the block after the streams were created
+        assert_eq!(suite.query_voting_power("alice", Some(created_at)), 0);
+        assert_eq!(suite.query_total_power(Some(created_at)), 0);
+
+        // Vesting alone moves nothing, claiming does
+        suite.update_time(50);
+        assert_eq!(suite.query_voting_power("alice", None), 400);
+        let claimed_at = suite.get_block_height();
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 150u128, DENOM, Some(1u64))
+            .unwrap();
+        suite.advance_blocks(1);
+        assert_eq!(suite.query_voting_power("alice", None), 250);
+        assert_eq!(suite.query_total_power(None), 350);
+        assert_eq!(suite.query_voting_power("alice", Some(claimed_at)), 400);
+    }
+
+    #[test]
+    fn test_power_follows_transfers_and_cancels() {
+        let mut suite = setup();
+        create_stream(&mut suite, "alice", 400, DENOM);
+        suite.advance_blocks(1);
+
+        suite
+            .transfer_stream(Addr::unchecked("alice"), 1, Addr::unchecked("carol"))
+            .unwrap();
+        suite.advance_blocks(1);
+        assert_eq!(suite.query_voting_power("alice", None), 0);
+        assert_eq!(suite.query_voting_power("carol", None), 400);
+        assert_eq!(suite.query_total_power(None), 400);
+
+        suite.cancel_stream(Addr::unchecked("carol"), 1).unwrap();
+        suite.advance_blocks(1);
+        assert_eq!(suite.query_voting_power("carol", None), 0);
+        assert_eq!(suite.query_total_power(None), 0);
+    }
+}
+
+mod staked_streams {
+    use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+
+    use crate::{
+        msg::{StakeOptions, StreamOptions},
+        state::RewardsTo,
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ustake";
+    const VALIDATOR: &str = "validator";
+    const UNBONDING_TIME: u64 = 1_000;
+    const YEAR: u64 = 365 * 24 * 60 * 60;
+
+    fn setup_staked_stream(duration: u64, rewards_to: Option<RewardsTo>) -> Suite {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(2_000_000u128),
+                }],
+            )
+            .with_staking(DENOM, VALIDATOR, UNBONDING_TIME)
+            .build();
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                1_000_000u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(duration).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1_000_000u128),
+                }],
+                StreamOptions {
+                    stake: Some(StakeOptions {
+                        validator: VALIDATOR.to_string(),
+                        rewards_to,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        suite
+    }
+
+    #[test]
+    fn test_vested_funds_are_claimable_once_unbonded() {
+        let mut suite = setup_staked_stream(100, None);
+        assert_eq!(suite.query_delegated(VALIDATOR), 1_000_000u128);
+
+        suite.update_time(40);
+        // Everything is still delegated so nothing can be claimed yet
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 0u128);
+        suite
+            .undelegate_vested(Addr::unchecked("anyone"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_delegated(VALIDATOR), 600_000u128);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 0u128);
+        assert!(suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400_000u128, DENOM, Some(1u64))
+            .is_err());
+        // Nothing more vested since
+        let err = suite
+            .undelegate_vested(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+
+        suite.update_time(UNBONDING_TIME);
+        suite.process_unbonding();
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 400_000u128);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400_000u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400_000u128);
+    }
+
+    #[test]
+    fn test_rewards_go_where_configured() {
+        let mut suite = setup_staked_stream(YEAR, Some(RewardsTo::Sender));
+        suite.update_time(YEAR / 2);
+        suite
+            .claim_staking_rewards(Addr::unchecked("anyone"), 1u64)
+            .unwrap();
+        // 10% a year on the whole deposit for half a year
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1_000_000u128 + 50_000u128
+        );
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 0u128);
+
+        let err = suite
+            .claim_staking_rewards(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_cancel_settles_after_unbonding() {
+        let mut suite = setup_staked_stream(100, None);
+        suite.update_time(40);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_delegated(VALIDATOR), 0u128);
+
+        let err = suite
+            .settle_unbonded(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast().unwrap(),
+            ContractError::StillUnbonding { .. }
+        ));
+
+        suite.update_time(UNBONDING_TIME);
+        suite.process_unbonding();
+        suite
+            .settle_unbonded(Addr::unchecked("anyone"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400_000u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1_000_000u128 + 600_000u128
+        );
+        // Paid once only
+        assert!(suite
+            .settle_unbonded(Addr::unchecked("anyone"), 1u64)
+            .is_err());
+    }
+
+    #[test]
+    fn test_slashes_come_out_of_the_sender_part() {
+        let mut suite = setup_staked_stream(100, None);
+        suite.update_time(40);
+        suite.slash(VALIDATOR, Decimal::percent(10));
+        assert_eq!(suite.query_delegated(VALIDATOR), 900_000u128);
+
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_delegated(VALIDATOR), 0u128);
+
+        suite.update_time(UNBONDING_TIME);
+        suite.process_unbonding();
+        suite
+            .settle_unbonded(Addr::unchecked("anyone"), 1u64)
+            .unwrap();
+        // Alice still gets everything vested, the sender is short of what was slashed
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400_000u128);
+        assert_eq!(
+            suite.query_balance("funder", DENOM).unwrap(),
+            1_000_000u128 + 500_000u128
+        );
+    }
+
+    #[test]
+    fn test_only_the_staking_denom_can_be_staked() {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: "uatom".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+            )
+            .with_staking(DENOM, VALIDATOR, UNBONDING_TIME)
+            .build();
+        let now = suite.get_time_as_timestamp();
+        let err = suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                100u128,
+                "uatom",
+                now.seconds(),
+                now.plus_seconds(100).seconds(),
+                &[Coin {
+                    denom: "uatom".to_string(),
+                    amount: Uint128::from(100u128),
+                }],
+                StreamOptions {
+                    stake: Some(StakeOptions {
+                        validator: VALIDATOR.to_string(),
+                        rewards_to: None,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidStaking {}, err.downcast().unwrap());
+    }
+}
+
+mod vault_streams {
+    use cosmwasm_std::{Addr, Coin, Decimal, Uint128, Uint256};
+
+    use crate::{
+        msg::{StreamOptions, VaultOptions},
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    fn coins(amount: u128) -> Vec<Coin> {
+        vec![Coin {
+            denom: DENOM.to_string(),
+            amount: Uint128::from(amount),
+        }]
+    }
+
+    fn setup_vault() -> (Suite, Addr) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds("funder", &coins(2000))
+            .build();
+        let vault = suite.instantiate_vault();
+        suite
+            .update_vault_allowlist(
+                Addr::unchecked(suite.owner.clone()),
+                vec![vault.to_string()],
+                vec![],
+            )
+            .unwrap();
+        (suite, vault)
+    }
+
+    fn create_vault_stream(suite: &mut Suite, vault: &Addr) -> anyhow::Result<()> {
+        let now = suite.get_time_as_timestamp().seconds();
+        create_vault_stream_to(suite, vault, "alice", now)
+    }
+
+    fn create_vault_stream_to(
+        suite: &mut Suite,
+        vault: &Addr,
+        recipient: &str,
+        start: u64,
+    ) -> anyhow::Result<()> {
+        suite.create_stream_with_options(
+            Addr::unchecked("funder"),
+            Addr::unchecked(recipient),
+            1000u128,
+            DENOM,
+            start,
+            start + 100,
+            &coins(1000),
+            StreamOptions {
+                vault: Some(VaultOptions {
+                    vault: vault.to_string(),
+                    recipient_yield: Some(Decimal::percent(50)),
+                }),
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_funds_come_back_from_the_vault_when_paid_out() {
+        let (mut suite, vault) = setup_vault();
+        create_vault_stream(&mut suite, &vault).unwrap();
+        assert_eq!(suite.query_vault_balance(&vault), 1000u128);
+        assert_eq!(
+            suite
+                .query_balance(suite.paystreams_addr.as_str(), DENOM)
+                .unwrap(),
+            0u128
+        );
+
+        suite.update_time(40);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400u128);
+        assert_eq!(suite.query_vault_balance(&vault), 600u128);
+
+        // The position earns 100 which is split evenly
+        suite
+            .add_vault_yield(Addr::unchecked("funder"), &vault, &coins(100))
+            .unwrap();
+        suite
+            .harvest_yield(Addr::unchecked("anyone"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 450u128);
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 950u128);
+        let err = suite
+            .harvest_yield(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+
+        suite.update_time(10);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_vault_balance(&vault), 0u128);
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 550u128);
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 1450u128);
+        let err = suite
+            .harvest_yield(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StreamClosed { stream_idx: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_only_what_is_left_to_vest_goes_into_the_vault() {
+        let (mut suite, vault) = setup_vault();
+        let now = suite.get_time_as_timestamp().seconds();
+        create_vault_stream_to(&mut suite, &vault, "alice", now - 40).unwrap();
+        assert_eq!(suite.query_vault_balance(&vault), 600u128);
+        assert_eq!(
+            suite
+                .query_balance(suite.paystreams_addr.as_str(), DENOM)
+                .unwrap(),
+            400u128
+        );
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_vault_balance(&vault), 600u128);
+    }
+
+    #[test]
+    fn test_vault_loss_stays_with_each_stream() {
+        let (mut suite, vault) = setup_vault();
+        let now = suite.get_time_as_timestamp().seconds();
+        create_vault_stream_to(&mut suite, &vault, "alice", now).unwrap();
+        create_vault_stream_to(&mut suite, &vault, "bob", now).unwrap();
+        // A quarter of the position is lost, each stream is left with 750
+        suite.lose_vault_funds(&vault, 500).unwrap();
+
+        suite.update_time(100);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 1000u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 750u128);
+        // The loss of alice's stream did not come out of bob's
+        suite
+            .withdraw_from_stream(Addr::unchecked("bob"), 1000u128, DENOM, Some(2u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("bob", DENOM).unwrap(), 750u128);
+        assert_eq!(suite.query_vault_balance(&vault), 0u128);
+
+        // Nothing is left in the vault to harvest from
+        let err = suite
+            .harvest_yield(Addr::unchecked("anyone"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+    }
+
+    #[test]
+    fn test_vault_loss_is_written_off_before_each_claim() {
+        let (mut suite, vault) = setup_vault();
+        create_vault_stream(&mut suite, &vault).unwrap();
+        suite.update_time(40);
+        suite.lose_vault_funds(&vault, 500).unwrap();
+
+        // The position is worth 500 instead of 1000, the loss leaves the stream before the 400 are withdrawn
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400u128);
+        assert_eq!(suite.query_vault_balance(&vault), 100u128);
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.remaining_balance, Uint256::from(100u128));
+
+        suite.update_time(60);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 100u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 500u128);
+        assert_eq!(suite.query_vault_balance(&vault), 0u128);
+    }
+
+    #[test]
+    fn test_vault_shortfall_only_debits_what_was_paid() {
+        let (mut suite, vault) = setup_vault();
+        create_vault_stream(&mut suite, &vault).unwrap();
+        suite.update_time(40);
+        suite.lose_vault_funds(&vault, 700).unwrap();
+
+        // Only 300 are left of the stream, the claim pays them and the stream is done
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 400u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 300u128);
+        assert_eq!(suite.query_vault_balance(&vault), 0u128);
+        let stream = suite
+            .query_stream_by_index(1)
+            .unwrap()
+            .streams
+            .pop()
+            .unwrap();
+        assert_eq!(stream.remaining_balance, Uint256::zero());
+        assert!(stream.is_closed);
+    }
+
+    #[test]
+    fn test_cancel_takes_a_vault_loss_from_the_sender_first() {
+        let (mut suite, vault) = setup_vault();
+        create_vault_stream(&mut suite, &vault).unwrap();
+        suite.update_time(40);
+        suite.lose_vault_funds(&vault, 500).unwrap();
+
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        // Alice still gets the 400 vested, the sender gets the 100 left
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 400u128);
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 1100u128);
+        assert_eq!(
+            suite
+                .query_balance(suite.paystreams_addr.as_str(), DENOM)
+                .unwrap(),
+            0u128
+        );
+    }
+
+    #[test]
+    fn test_only_allowlisted_vaults() {
+        let (mut suite, vault) = setup_vault();
+        assert_eq!(suite.query_allowed_vaults(), vec![vault.clone()]);
+
+        let err = suite
+            .update_vault_allowlist(Addr::unchecked("funder"), vec![], vec![vault.to_string()])
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite
+            .update_vault_allowlist(
+                Addr::unchecked(suite.owner.clone()),
+                vec![],
+                vec![vault.to_string()],
+            )
+            .unwrap();
+        assert!(suite.query_allowed_vaults().is_empty());
+        let err = create_vault_stream(&mut suite, &vault).unwrap_err();
+        assert_eq!(
+            ContractError::VaultNotAllowed {
+                vault: vault.to_string()
+            },
+            err.downcast().unwrap()
+        );
+    }
+}
+
+mod fiat_streams {
+    use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128, Uint256};
+
+    use crate::{
+        fiat,
+        msg::{FiatOptions, StreamOptions},
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ujuno";
+
+    fn coins(amount: u128) -> Vec<Coin> {
+        vec![Coin {
+            denom: DENOM.to_string(),
+            amount: Uint128::from(amount),
+        }]
+    }
+
+    // 1000 uUSD over 100 seconds collateralized with 600 ujuno, at 2 uUSD per ujuno
+    fn setup_fiat_stream() -> (Suite, Addr) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds("funder", &coins(1000))
+            .build();
+        let oracle = suite.instantiate_oracle();
+        suite.set_price(
+            &oracle,
+            DENOM,
+            "USD",
+            Decimal::from_ratio(2u128, 1u128),
+            Decimal::zero(),
+        );
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                600u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(100).seconds(),
+                &coins(600),
+                StreamOptions {
+                    fiat: Some(FiatOptions {
+                        oracle: oracle.to_string(),
+                        currency: "USD".to_string(),
+                        amount: Uint128::new(1000),
+                        max_staleness: 50,
+                        tolerance: Decimal::percent(10),
+                    }),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        (suite, oracle)
+    }
+
+    #[test]
+    fn test_payouts_follow_the_oracle_price() {
+        let (mut suite, oracle) = setup_fiat_stream();
+        let start = suite.get_time_as_timestamp();
+        suite.update_time(40);
+        // 400 uUSD vested, worth 200 ujuno
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 200u128);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 200u128, DENOM, Some(1u64))
+            .unwrap();
+        let status = suite.query_fiat_status(1);
+        assert_eq!(status.paid, Uint128::new(400));
+        assert_eq!(status.owed, Uint128::zero());
+        assert_eq!(status.shortfall, Uint128::zero());
+
+        // At half the price the 400 ujuno left no longer cover the 600 uUSD to go
+        suite.set_price(&oracle, DENOM, "USD", Decimal::one(), Decimal::zero());
+        let status = suite.query_fiat_status(1);
+        assert_eq!(status.collateral, Uint256::from(400u128));
+        assert_eq!(status.shortfall, Uint128::new(200));
+
+        suite.update_time(60);
+        let err = suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 100u128, DENOM, Some(1u64))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StalePrice {
+                updated_at: Timestamp::from_seconds(start.seconds() + 40)
+            },
+            err.downcast().unwrap()
+        );
+        assert_eq!(suite.query_fiat_status(1).price, None);
+
+        suite.set_price(&oracle, DENOM, "USD", Decimal::one(), Decimal::percent(50));
+        let err = suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 100u128, DENOM, Some(1u64))
+            .unwrap_err();
+        assert_eq!(
+            ContractError::PriceOutOfTolerance {},
+            err.downcast().unwrap()
+        );
+
+        suite.set_price(&oracle, DENOM, "USD", Decimal::one(), Decimal::percent(5));
+        // The collateral only covers 400 of the 600 ujuno owed until the sender tops it up
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 400u128);
+        let err = suite
+            .add_collateral(Addr::unchecked("alice"), 1u64, &[])
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite
+            .add_collateral(Addr::unchecked("funder"), 1u64, &coins(200))
+            .unwrap();
+        assert_eq!(suite.query_fiat_status(1).shortfall, Uint128::zero());
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 600u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 800u128);
+        assert_eq!(suite.query_fiat_status(1).paid, Uint128::new(1000));
+    }
+
+    #[test]
+    fn test_cancel_settles_at_the_oracle_price() {
+        let (mut suite, _) = setup_fiat_stream();
+        suite.update_time(50);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        // 500 uUSD vested, paid with 250 ujuno and the rest of the collateral goes back
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 250u128);
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 750u128);
+    }
+
+    #[test]
+    fn test_cancel_falls_back_to_the_last_price_once_the_oracle_is_down() {
+        let (mut suite, _) = setup_fiat_stream();
+        let start = suite.get_time_as_timestamp();
+        suite.update_time(60);
+        let err = suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::StalePrice { updated_at: start },
+            err.downcast().unwrap()
+        );
+
+        // Past the staleness limit and the grace period the stream settles at the price it was created with
+        suite.update_time(fiat::ORACLE_GRACE_PERIOD);
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        // All 1000 uUSD vested, paid with 500 ujuno and the rest of the collateral goes back
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 500u128);
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 500u128);
+    }
+
+    #[test]
+    fn test_collateral_of_a_stream_from_balance_is_committed() {
+        let mut suite = SuiteBuilder::new()
+            .with_funds("funder", &coins(1000))
+            .build();
+        let oracle = suite.instantiate_oracle();
+        suite.set_price(
+            &oracle,
+            DENOM,
+            "USD",
+            Decimal::from_ratio(2u128, 1u128),
+            Decimal::zero(),
+        );
+        suite
+            .deposit(Addr::unchecked("funder"), &coins(600))
+            .unwrap();
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_stream_with_options(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                400u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(100).seconds(),
+                &[],
+                StreamOptions {
+                    from_balance: Some(true),
+                    fiat: Some(FiatOptions {
+                        oracle: oracle.to_string(),
+                        currency: "USD".to_string(),
+                        amount: Uint128::new(1000),
+                        max_staleness: 50,
+                        tolerance: Decimal::percent(10),
+                    }),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        suite
+            .add_collateral(Addr::unchecked("funder"), 1u64, &coins(200))
+            .unwrap();
+        assert_eq!(suite.query_sender_balance("funder", DENOM), (200, 600));
+
+        suite.update_time(50);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 250u128, DENOM, Some(1u64))
+            .unwrap();
+        suite
+            .cancel_stream(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        // Nothing more vested, the 350 ujuno left of deposit and collateral return to the balance
+        assert_eq!(suite.query_sender_balance("funder", DENOM), (550, 0));
+        assert_eq!(suite.query_balance("alice", DENOM).unwrap(), 250u128);
+    }
+
+    #[test]
+    fn test_sender_withdraws_excess_collateral_once_ended() {
+        let (mut suite, oracle) = setup_fiat_stream();
+        suite.update_time(50);
+        let err = suite
+            .withdraw_collateral(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::FiatStreamNotEnded {},
+            err.downcast().unwrap()
+        );
+
+        suite.update_time(50);
+        suite.set_price(
+            &oracle,
+            DENOM,
+            "USD",
+            Decimal::from_ratio(2u128, 1u128),
+            Decimal::zero(),
+        );
+        let err = suite
+            .withdraw_collateral(Addr::unchecked("alice"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        // The 1000 uUSD owed are worth 500 ujuno, the other 100 ujuno go back
+        suite
+            .withdraw_collateral(Addr::unchecked("funder"), 1u64)
+            .unwrap();
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 500u128);
+        assert_eq!(
+            suite.query_fiat_status(1).collateral,
+            Uint256::from(500u128)
+        );
+
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 500u128, DENOM, Some(1u64))
+            .unwrap();
+        assert_eq!(suite.query_fiat_status(1).paid, Uint128::new(1000));
+        let err = suite
+            .withdraw_collateral(Addr::unchecked("funder"), 1u64)
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidAmount {}, err.downcast().unwrap());
+    }
+}
+
+mod milestone_streams {
+    use cosmwasm_std::{Addr, Coin, Timestamp, Uint128, Uint256};
+    use cw_utils::{Duration, Expiration};
+
+    use crate::{
+        state::{MilestoneStatus, MilestoneTerms},
+        tests::suite::{Suite, SuiteBuilder},
+        ContractError,
+    };
+
+    const DENOM: &str = "ibc/something/axlusdc";
+
+    // 300 unlocked by a single reviewer, then 700 vesting over 100 seconds once two of three board members approve
+    fn setup_milestones() -> (Suite, Timestamp) {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        let now = suite.get_time_as_timestamp();
+        suite
+            .create_milestone_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                1000u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(500).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+                vec![
+                    MilestoneTerms {
+                        amount: Uint128::new(300),
+                        approvers: vec!["reviewer".to_string()],
+                        threshold: None,
+                        vesting: None,
+                        deadline: Expiration::AtTime(now.plus_seconds(100)),
+                    },
+                    MilestoneTerms {
+                        amount: Uint128::new(700),
+                        approvers: vec!["bob".to_string(), "carol".to_string(), "dave".to_string()],
+                        threshold: Some(2),
+                        vesting: Some(Duration::Time(100)),
+                        deadline: Expiration::AtTime(now.plus_seconds(200)),
+                    },
+                ],
+            )
+            .unwrap();
+        (suite, now)
+    }
+
+    #[test]
+    fn test_milestones_vest_once_approved() {
+        let (mut suite, _) = setup_milestones();
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 0u128);
+        let err = suite
+            .approve_milestone(Addr::unchecked("alice"), 1u64, 0)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+        suite
+            .approve_milestone(Addr::unchecked("reviewer"), 1u64, 0)
+            .unwrap();
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 300u128);
+        suite
+            .withdraw_from_stream(Addr::unchecked("alice"), 300u128, DENOM, Some(1u64))
+            .unwrap();
+
+        suite
+            .approve_milestone(Addr::unchecked("bob"), 1u64, 1)
+            .unwrap();
+        let err = suite
+            .approve_milestone(Addr::unchecked("bob"), 1u64, 1)
+            .unwrap_err();
+        assert_eq!(ContractError::AlreadyApproved {}, err.downcast().unwrap());
+        // One approval out of two is not enough
+        suite.update_time(10);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 0u128);
+
+        suite
+            .approve_milestone(Addr::unchecked("carol"), 1u64, 1)
+            .unwrap();
+        let stream = suite.query_stream_by_index(1).unwrap().streams[0].clone();
+        assert!(matches!(
+            stream.milestones[1].status,
+            MilestoneStatus::Approved { .. }
+        ));
+        assert_eq!(
+            stream.milestones[1].approvals,
+            vec![Addr::unchecked("bob"), Addr::unchecked("carol")]
+        );
+        suite.update_time(50);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 350u128);
+        let err = suite
+            .approve_milestone(Addr::unchecked("dave"), 1u64, 1)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::MilestoneNotPending { milestone: 1 },
+            err.downcast().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sender_reclaims_unapproved_milestones() {
+        let (mut suite, _) = setup_milestones();
+        suite.update_time(50);
+        let err = suite
+            .reclaim_milestone(Addr::unchecked("funder"), 1u64, 0)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::MilestoneDeadlineNotPassed { milestone: 0 },
+            err.downcast().unwrap()
+        );
+
+        suite.update_time(60);
+        let err = suite
+            .approve_milestone(Addr::unchecked("reviewer"), 1u64, 0)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::MilestoneDeadlinePassed { milestone: 0 },
+            err.downcast().unwrap()
+        );
+        let err = suite
+            .reclaim_milestone(Addr::unchecked("alice"), 1u64, 0)
+            .unwrap_err();
+        assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+        suite
+            .reclaim_milestone(Addr::unchecked("funder"), 1u64, 0)
+            .unwrap();
+        assert_eq!(suite.query_balance("funder", DENOM).unwrap(), 300u128);
+        let err = suite
+            .reclaim_milestone(Addr::unchecked("funder"), 1u64, 0)
+            .unwrap_err();
+        assert_eq!(
+            ContractError::MilestoneNotPending { milestone: 0 },
+            err.downcast().unwrap()
+        );
+
+        // The other milestone is unaffected
+        suite
+            .approve_milestone(Addr::unchecked("bob"), 1u64, 1)
+            .unwrap();
+        suite
+            .approve_milestone(Addr::unchecked("dave"), 1u64, 1)
+            .unwrap();
+        suite.update_time(100);
+        assert_eq!(suite.query_stream_claimable_amount(1).unwrap(), 700u128);
+        let stream = suite.query_stream_by_index(1).unwrap().streams[0].clone();
+        assert_eq!(stream.milestones[0].status, MilestoneStatus::Reclaimed);
+        assert_eq!(stream.deposit, Uint256::from(700u128));
+    }
+
+    #[test]
+    fn test_milestones_must_add_up_to_the_deposit() {
+        let mut suite = SuiteBuilder::new()
+            .with_funds(
+                "funder",
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+            )
+            .build();
+        let now = suite.get_time_as_timestamp();
+        let err = suite
+            .create_milestone_stream(
+                Addr::unchecked("funder"),
+                Addr::unchecked("alice"),
+                1000u128,
+                DENOM,
+                now.seconds(),
+                now.plus_seconds(500).seconds(),
+                &[Coin {
+                    denom: DENOM.to_string(),
+                    amount: Uint128::from(1000u128),
+                }],
+                vec![MilestoneTerms {
+                    amount: Uint128::new(900),
+                    approvers: vec!["reviewer".to_string()],
+                    threshold: None,
+                    vesting: None,
+                    deadline: Expiration::AtTime(now.plus_seconds(100)),
+                }],
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::InvalidMilestones {}, err.downcast().unwrap());
+    }
+}
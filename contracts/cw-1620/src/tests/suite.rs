@@ -1,23 +1,75 @@
 use anyhow::Result as AnyResult;
-use cosmwasm_std::{Addr, Coin, StdResult, Timestamp};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Empty, StdResult, Timestamp, Uint128, Validator};
 use cw_asset::{Asset, AssetInfo};
-use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+use cw_multi_test::{
+    App, AppResponse, ContractWrapper, Executor, StakingInfo, StakingSudo, SudoMsg,
+};
+use cw_utils::{Duration, Expiration};
+use paystreams::StreamReceiveMsg;
 use wynd_utils::Curve;
 
-use crate::{msg::StreamsResponse, state::StreamType};
+use crate::{
+    msg::{
+        AllowedVaultsResponse, AmountOrRatio, ConfigResponse, FiatStatusResponse,
+        SenderBalanceResponse, StreamOffer, StreamOptions, StreamsResponse,
+        TotalPowerAtHeightResponse, VotingPowerAtHeightResponse,
+    },
+    state::{MilestoneTerms, Recurrence, StreamTime, StreamType},
+    tests::mocks,
+};
 pub fn store_streaming_contract(app: &mut App) -> u64 {
     let contract = Box::new(
         ContractWrapper::new_with_empty(
             crate::contract::execute,
             crate::contract::instantiate,
             crate::contract::query,
-        ), // .with_reply(crate::contract::reply)
-           // .with_migrate(crate::contract::migrate),
+        )
+        .with_reply(crate::contract::reply), // .with_migrate(crate::contract::migrate),
     );
 
     app.store_code(contract)
 }
 
+fn store_savings_contract(app: &mut App) -> u64 {
+    let contract = Box::new(ContractWrapper::new_with_empty(
+        mocks::savings_execute,
+        mocks::savings_instantiate,
+        mocks::savings_query,
+    ));
+
+    app.store_code(contract)
+}
+
+fn store_stream_receiver(app: &mut App) -> u64 {
+    let contract = Box::new(ContractWrapper::new_with_empty(
+        mocks::receiver_execute,
+        mocks::receiver_instantiate,
+        mocks::receiver_query,
+    ));
+
+    app.store_code(contract)
+}
+
+fn store_vault(app: &mut App) -> u64 {
+    let contract = Box::new(ContractWrapper::new_with_empty(
+        mocks::vault_execute,
+        mocks::vault_instantiate,
+        mocks::vault_query,
+    ));
+
+    app.store_code(contract)
+}
+
+fn store_oracle(app: &mut App) -> u64 {
+    let contract = Box::new(ContractWrapper::new_with_empty(
+        mocks::oracle_execute,
+        mocks::oracle_instantiate,
+        mocks::oracle_query,
+    ));
+
+    app.store_code(contract)
+}
+
 fn store_cw20(app: &mut App) -> u64 {
     let contract = Box::new(ContractWrapper::new(
         cw20_base::contract::execute,
@@ -31,11 +83,28 @@ fn store_cw20(app: &mut App) -> u64 {
 #[derive(Debug)]
 pub struct SuiteBuilder {
     funds: Vec<(Addr, Vec<Coin>)>,
+    governance_asset: Option<AssetInfo>,
+    // Bonded denom, validator and unbonding time in seconds
+    staking: Option<(String, String, u64)>,
 }
 
 impl SuiteBuilder {
     pub fn new() -> Self {
-        Self { funds: vec![] }
+        Self {
+            funds: vec![],
+            governance_asset: None,
+            staking: None,
+        }
+    }
+
+    pub fn with_staking(mut self, denom: &str, validator: &str, unbonding_time: u64) -> Self {
+        self.staking = Some((denom.to_string(), validator.to_string(), unbonding_time));
+        self
+    }
+
+    pub fn with_governance_denom(mut self, denom: &str) -> Self {
+        self.governance_asset = Some(AssetInfo::Native(denom.to_string()));
+        self
     }
 
     pub fn with_funds(mut self, addr: &str, funds: &[Coin]) -> Self {
@@ -52,7 +121,10 @@ impl SuiteBuilder {
         let _cw20_id = store_cw20(&mut app);
         let _id = store_streaming_contract(&mut app);
 
-        let msg = crate::msg::InstantiateMsg { count: 0 };
+        let msg = crate::msg::InstantiateMsg {
+            count: 0,
+            governance_asset: self.governance_asset,
+        };
 
         let paystreams_addr = app
             .instantiate_contract(
@@ -66,13 +138,47 @@ impl SuiteBuilder {
             .unwrap();
 
         let funds = self.funds;
-        app.init_modules(|router, _, storage| -> AnyResult<()> {
+        let block = app.block_info();
+        let staking = self.staking.clone();
+        app.init_modules(|router, api, storage| -> AnyResult<()> {
             for (addr, coin) in funds {
                 router.bank.init_balance(storage, &addr, coin)?;
             }
+            if let Some((bonded_denom, validator, unbonding_time)) = staking {
+                router.staking.setup(
+                    storage,
+                    StakingInfo {
+                        bonded_denom,
+                        unbonding_time,
+                        apr: Decimal::percent(10),
+                    },
+                )?;
+                router.staking.add_validator(
+                    api,
+                    storage,
+                    &block,
+                    Validator {
+                        address: validator,
+                        commission: Decimal::zero(),
+                        max_commission: Decimal::one(),
+                        max_change_rate: Decimal::one(),
+                    },
+                )?;
+            }
             Ok(())
         })
         .unwrap();
+        // The contract has to know how long the chain takes to unbond
+        if let Some((_, _, unbonding_time)) = self.staking {
+            let msg = crate::msg::ExecuteMsg::UpdateConfig {
+                owner: None,
+                fee_asset: None,
+                fees: None,
+                unbonding_period: Some(unbonding_time),
+            };
+            app.execute_contract(owner.clone(), paystreams_addr.clone(), &msg, &[])
+                .unwrap();
+        }
 
         Suite {
             owner: owner.to_string(),
@@ -108,6 +214,18 @@ impl Suite {
             })
     }
 
+    /// produce blocks at 5 seconds each, useful for height based streams
+    pub fn advance_blocks(&mut self, blocks: u64) {
+        self.app.update_block(|block| {
+            block.time = block.time.plus_seconds(blocks * 5);
+            block.height += blocks;
+        })
+    }
+
+    pub fn get_block_height(&self) -> u64 {
+        self.app.block_info().height
+    }
+
     pub fn query_balance(&self, user: &str, denom: &str) -> AnyResult<u128> {
         Ok(self.app.wrap().query_balance(user, denom)?.amount.u128())
     }
@@ -125,6 +243,32 @@ impl Suite {
         funds: &[Coin],
         stream_type: Option<StreamType>,
         curve: Option<Curve>,
+    ) -> AnyResult<AppResponse> {
+        self.create_stream_between(
+            sender,
+            recipient,
+            deposit,
+            token_addr,
+            StreamTime::Time(Timestamp::from_seconds(start_time)),
+            StreamTime::Time(Timestamp::from_seconds(stop_time)),
+            funds,
+            stream_type,
+            curve,
+        )
+    }
+
+    /// Same as create_stream but start and stop can be given as block heights as well as timestamps
+    pub fn create_stream_between(
+        &mut self,
+        sender: Addr,
+        recipient: Addr,
+        deposit: u128,
+        token_addr: &str,
+        start_time: StreamTime,
+        stop_time: StreamTime,
+        funds: &[Coin],
+        stream_type: Option<StreamType>,
+        curve: Option<Curve>,
     ) -> AnyResult<AppResponse> {
         let msg = crate::msg::ExecuteMsg::CreateStream {
             recipient: recipient.to_string(),
@@ -132,16 +276,187 @@ impl Suite {
                 amount: deposit.into(),
                 info: AssetInfo::Native(token_addr.to_string()),
             },
-            start_time: start_time,
-            stop_time: stop_time,
+            start_time: Some(start_time),
+            stop_time: Some(stop_time),
+            duration: None,
             stream_type: stream_type,
             curve: curve,
+            options: None,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    /// Create a recurring stream starting at `start_time`, without a stop time it runs for its maximum number of periods
+    pub fn create_recurring_stream(
+        &mut self,
+        sender: Addr,
+        recipient: Addr,
+        deposit: u128,
+        token_addr: &str,
+        start_time: u64,
+        stop_time: Option<u64>,
+        funds: &[Coin],
+        recurrence: Recurrence,
+        options: Option<StreamOptions>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            recipient: recipient.to_string(),
+            asset: Asset {
+                amount: deposit.into(),
+                info: AssetInfo::Native(token_addr.to_string()),
+            },
+            start_time: Some(StreamTime::Time(Timestamp::from_seconds(start_time))),
+            stop_time: stop_time.map(|stop| StreamTime::Time(Timestamp::from_seconds(stop))),
+            duration: None,
+            stream_type: Some(StreamType::Recurring(recurrence)),
+            curve: None,
+            options,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    /// Create a time based stream released by milestones instead of over time
+    pub fn create_milestone_stream(
+        &mut self,
+        sender: Addr,
+        recipient: Addr,
+        deposit: u128,
+        token_addr: &str,
+        start_time: u64,
+        stop_time: u64,
+        funds: &[Coin],
+        milestones: Vec<MilestoneTerms>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            recipient: recipient.to_string(),
+            asset: Asset {
+                amount: deposit.into(),
+                info: AssetInfo::Native(token_addr.to_string()),
+            },
+            start_time: Some(StreamTime::Time(Timestamp::from_seconds(start_time))),
+            stop_time: Some(StreamTime::Time(Timestamp::from_seconds(stop_time))),
+            duration: None,
+            stream_type: Some(StreamType::Milestones(milestones)),
+            curve: None,
+            options: None,
         };
 
         self.app
             .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
     }
 
+    pub fn approve_milestone(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        milestone: u32,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ApproveMilestone {
+            stream_idx,
+            milestone,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn reclaim_milestone(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        milestone: u32,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ReclaimMilestone {
+            stream_idx,
+            milestone,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    /// Create a stream with only a duration, it stays Pending until activated
+    pub fn create_deferred_stream(
+        &mut self,
+        sender: Addr,
+        recipient: Addr,
+        deposit: u128,
+        token_addr: &str,
+        duration: Duration,
+        funds: &[Coin],
+        stream_type: Option<StreamType>,
+        curve: Option<Curve>,
+        activator: Option<Addr>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            recipient: recipient.to_string(),
+            asset: Asset {
+                amount: deposit.into(),
+                info: AssetInfo::Native(token_addr.to_string()),
+            },
+            start_time: None,
+            stop_time: None,
+            duration: Some(duration),
+            stream_type,
+            curve,
+            options: Some(StreamOptions {
+                activator: activator.map(|activator| activator.to_string()),
+                ..Default::default()
+            }),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    /// Create a time based stream with any of the optional settings
+    pub fn create_stream_with_options(
+        &mut self,
+        sender: Addr,
+        recipient: Addr,
+        deposit: u128,
+        token_addr: &str,
+        start_time: u64,
+        stop_time: u64,
+        funds: &[Coin],
+        options: StreamOptions,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            recipient: recipient.to_string(),
+            asset: Asset {
+                amount: deposit.into(),
+                info: AssetInfo::Native(token_addr.to_string()),
+            },
+            start_time: Some(StreamTime::Time(Timestamp::from_seconds(start_time))),
+            stop_time: Some(StreamTime::Time(Timestamp::from_seconds(stop_time))),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            options: Some(options),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn cancel_stream(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CancelStream { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn activate_stream(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ActivateStream { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
     pub fn withdraw_from_stream(
         &mut self,
         recipient: Addr,
@@ -150,16 +465,561 @@ impl Suite {
         stream_idx: Option<u64>,
     ) -> AnyResult<AppResponse> {
         let msg = crate::msg::ExecuteMsg::ClaimFromStream {
-            recipient: recipient.to_string(),
             amount: amount.into(),
             denom: denom.to_string(),
             stream_idx: stream_idx,
+            to: None,
+            msg: None,
         };
 
         self.app
             .execute_contract(recipient, self.paystreams_addr.clone(), &msg, &[])
     }
 
+    /// claim from a stream as any address, e.g. a claim operator
+    pub fn claim_from_stream_as(
+        &mut self,
+        caller: Addr,
+        amount: u128,
+        denom: &str,
+        stream_idx: u64,
+    ) -> AnyResult<AppResponse> {
+        self.claim_from_stream_to(caller, amount, denom, stream_idx, None, None)
+    }
+
+    /// claim from a stream into another address, optionally calling it with a message
+    pub fn claim_from_stream_to(
+        &mut self,
+        caller: Addr,
+        amount: u128,
+        denom: &str,
+        stream_idx: u64,
+        to: Option<Addr>,
+        msg: Option<Binary>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ClaimFromStream {
+            amount: amount.into(),
+            denom: denom.to_string(),
+            stream_idx: Some(stream_idx),
+            to: to.map(|to| to.to_string()),
+            msg,
+        };
+
+        self.app
+            .execute_contract(caller, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn split_stream(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        amount_or_ratio: AmountOrRatio,
+        new_recipient: Addr,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SplitStream {
+            stream_idx,
+            amount_or_ratio,
+            new_recipient: new_recipient.to_string(),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn merge_streams(&mut self, sender: Addr, stream_idxs: Vec<u64>) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::MergeStreams { stream_idxs };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn accept_stream(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::AcceptStream { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn reclaim_stream(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ReclaimStream { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn sweep_expired(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SweepExpired { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn register_airdrop(
+        &mut self,
+        sender: Addr,
+        merkle_root: &str,
+        total: u128,
+        denom: &str,
+        start_time: u64,
+        stop_time: u64,
+        curve: Option<Curve>,
+        expiration: Option<Expiration>,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::RegisterAirdrop {
+            merkle_root: merkle_root.to_string(),
+            asset: Asset::native(denom, total),
+            start_time: StreamTime::Time(Timestamp::from_seconds(start_time)),
+            stop_time: StreamTime::Time(Timestamp::from_seconds(stop_time)),
+            curve,
+            expiration,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn reclaim_airdrop(&mut self, sender: Addr, airdrop_id: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ReclaimAirdrop { airdrop_id };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn claim_airdrop_stream(
+        &mut self,
+        sender: Addr,
+        airdrop_id: u64,
+        amount: u128,
+        proof: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ClaimAirdropStream {
+            airdrop_id,
+            amount: amount.into(),
+            proof,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn get_chain_id(&self) -> String {
+        self.app.block_info().chain_id
+    }
+
+    pub fn register_offer_key(
+        &mut self,
+        sender: Addr,
+        public_key: &[u8],
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::RegisterOfferKey {
+            public_key: Binary::from(public_key),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn submit_signed_offer(
+        &mut self,
+        submitter: Addr,
+        offer: StreamOffer,
+        signature: &[u8],
+    ) -> AnyResult<AppResponse> {
+        self.submit_signed_offer_with_funds(submitter, offer, signature, &[])
+    }
+
+    pub fn submit_signed_offer_with_funds(
+        &mut self,
+        submitter: Addr,
+        offer: StreamOffer,
+        signature: &[u8],
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SubmitSignedOffer {
+            offer,
+            signature: Binary::from(signature),
+        };
+
+        self.app
+            .execute_contract(submitter, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn revoke_offer(&mut self, sender: Addr, nonce: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::RevokeOffer { nonce };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn undelegate_vested(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::UndelegateVested { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn claim_staking_rewards(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::ClaimStakingRewards { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn settle_unbonded(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SettleUnbonded { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    // Pays out matured undelegations, the chain does this at the end of every block
+    pub fn process_unbonding(&mut self) {
+        self.app
+            .sudo(SudoMsg::Staking(StakingSudo::ProcessQueue {}))
+            .unwrap();
+    }
+
+    pub fn slash(&mut self, validator: &str, percentage: Decimal) {
+        self.app
+            .sudo(SudoMsg::Staking(StakingSudo::Slash {
+                validator: validator.to_string(),
+                percentage,
+            }))
+            .unwrap();
+    }
+
+    pub fn query_delegated(&self, validator: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_delegation(&self.paystreams_addr, validator)
+            .unwrap()
+            .map(|delegation| delegation.amount.amount.u128())
+            .unwrap_or_default()
+    }
+
+    /// top up the prepaid balance of a sender with native funds
+    pub fn deposit(&mut self, sender: Addr, funds: &[Coin]) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::Deposit {};
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn withdraw_balance(
+        &mut self,
+        sender: Addr,
+        amount: u128,
+        denom: &str,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::WithdrawBalance {
+            asset: Asset::native(denom, amount),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    /// free and committed prepaid balance of a sender
+    pub fn query_sender_balance(&self, sender: &str, denom: &str) -> (u128, u128) {
+        let balance: SenderBalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.paystreams_addr,
+                &crate::msg::QueryMsg::SenderBalance {
+                    sender: sender.to_string(),
+                    asset: AssetInfo::Native(denom.to_string()),
+                },
+            )
+            .unwrap();
+        (balance.free.u128(), balance.committed.u128())
+    }
+
+    /// store and instantiate a mock contract which records the stream events it is notified of
+    pub fn instantiate_stream_receiver(&mut self) -> Addr {
+        let code_id = store_stream_receiver(&mut self.app);
+        self.app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked(self.owner.clone()),
+                &Empty {},
+                &[],
+                "Receiver",
+                None,
+            )
+            .unwrap()
+    }
+
+    /// make the mock receiver fail on every stream event it is sent
+    pub fn reject_stream_events(&mut self, receiver: &Addr) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(self.owner.clone()),
+            receiver.clone(),
+            &mocks::ReceiverExecuteMsg::Reject {},
+            &[],
+        )
+    }
+
+    pub fn query_received_events(&self, receiver: &Addr) -> Vec<StreamReceiveMsg> {
+        self.app
+            .wrap()
+            .query_wasm_smart(receiver, &mocks::ReceiverQueryMsg::Events {})
+            .unwrap()
+    }
+
+    /// store and instantiate the mock savings contract used to receive claim callbacks
+    pub fn instantiate_savings_contract(&mut self) -> Addr {
+        let code_id = store_savings_contract(&mut self.app);
+        self.app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked(self.owner.clone()),
+                &Empty {},
+                &[],
+                "Savings",
+                None,
+            )
+            .unwrap()
+    }
+
+    pub fn instantiate_vault(&mut self) -> Addr {
+        let code_id = store_vault(&mut self.app);
+        self.app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked(self.owner.clone()),
+                &Empty {},
+                &[],
+                "Vault",
+                None,
+            )
+            .unwrap()
+    }
+
+    // Credits the position of the streams contract in the vault with the funds sent along
+    pub fn add_vault_yield(
+        &mut self,
+        sender: Addr,
+        vault: &Addr,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let msg = mocks::VaultExecuteMsg::AddYield {
+            depositor: self.paystreams_addr.to_string(),
+        };
+
+        self.app
+            .execute_contract(sender, vault.clone(), &msg, funds)
+    }
+
+    // Burns part of the position of the streams contract in the vault
+    pub fn lose_vault_funds(&mut self, vault: &Addr, amount: u128) -> AnyResult<AppResponse> {
+        let msg = mocks::VaultExecuteMsg::Lose {
+            depositor: self.paystreams_addr.to_string(),
+            amount: amount.into(),
+        };
+
+        self.app.execute_contract(
+            Addr::unchecked(self.owner.clone()),
+            vault.clone(),
+            &msg,
+            &[],
+        )
+    }
+
+    pub fn update_vault_allowlist(
+        &mut self,
+        sender: Addr,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::UpdateVaultAllowlist { add, remove };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn query_allowed_vaults(&self) -> Vec<Addr> {
+        let res: AllowedVaultsResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.paystreams_addr,
+                &crate::msg::QueryMsg::AllowedVaults {},
+            )
+            .unwrap();
+        res.vaults
+    }
+
+    pub fn harvest_yield(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::HarvestYield { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn query_vault_balance(&self, vault: &Addr) -> u128 {
+        let res: paystreams::VaultBalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                vault,
+                &paystreams::VaultQueryMsg::Balance {
+                    depositor: self.paystreams_addr.to_string(),
+                },
+            )
+            .unwrap();
+        res.amount.u128()
+    }
+
+    pub fn instantiate_oracle(&mut self) -> Addr {
+        let code_id = store_oracle(&mut self.app);
+        self.app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked(self.owner.clone()),
+                &Empty {},
+                &[],
+                "Oracle",
+                None,
+            )
+            .unwrap()
+    }
+
+    pub fn set_price(
+        &mut self,
+        oracle: &Addr,
+        base: &str,
+        quote: &str,
+        price: Decimal,
+        confidence: Decimal,
+    ) {
+        let msg = mocks::OracleExecuteMsg::SetPrice {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            price,
+            confidence,
+        };
+        self.app
+            .execute_contract(
+                Addr::unchecked(self.owner.clone()),
+                oracle.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+    }
+
+    pub fn add_collateral(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::AddCollateral { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn withdraw_collateral(&mut self, sender: Addr, stream_idx: u64) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::WithdrawCollateral { stream_idx };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn top_up_stream(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        amount: Option<u128>,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::TopUpStream {
+            stream_idx,
+            amount: amount.map(Uint128::from),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, funds)
+    }
+
+    pub fn query_fiat_status(&self, stream_idx: u64) -> FiatStatusResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.paystreams_addr,
+                &crate::msg::QueryMsg::FiatStatus { stream_idx },
+            )
+            .unwrap()
+    }
+
+    pub fn query_savings_deposits(&self, savings: &Addr, depositor: &Addr) -> u128 {
+        let deposited: Uint128 = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                savings,
+                &mocks::SavingsQueryMsg::Deposited {
+                    depositor: depositor.to_string(),
+                },
+            )
+            .unwrap();
+        deposited.u128()
+    }
+
+    pub fn transfer_stream(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        new_recipient: Addr,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::TransferStream {
+            stream_idx,
+            new_recipient: new_recipient.to_string(),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn set_claim_operator(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        operator: Option<Addr>,
+        expires: Option<Expiration>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SetClaimOperator {
+            stream_idx,
+            operator: operator.map(|operator| operator.to_string()),
+            expires,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn set_withdrawal_address(
+        &mut self,
+        sender: Addr,
+        stream_idx: u64,
+        address: Option<Addr>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::SetWithdrawalAddress {
+            stream_idx,
+            address: address.map(|address| address.to_string()),
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
     pub fn query_stream_count(&mut self) -> u64 {
         let msg = crate::msg::QueryMsg::StreamCount {};
         let count: crate::msg::CountResponse = self
@@ -170,6 +1030,56 @@ impl Suite {
         count.count as u64
     }
 
+    pub fn query_voting_power(&self, address: &str, height: Option<u64>) -> u128 {
+        let res: VotingPowerAtHeightResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.paystreams_addr,
+                &crate::msg::QueryMsg::VotingPowerAtHeight {
+                    address: address.to_string(),
+                    height,
+                },
+            )
+            .unwrap();
+        res.power.u128()
+    }
+
+    pub fn query_total_power(&self, height: Option<u64>) -> u128 {
+        let res: TotalPowerAtHeightResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.paystreams_addr,
+                &crate::msg::QueryMsg::TotalPowerAtHeight { height },
+            )
+            .unwrap();
+        res.power.u128()
+    }
+
+    pub fn update_config(
+        &mut self,
+        sender: Addr,
+        owner: Option<String>,
+        fees: Option<Vec<Decimal>>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            owner,
+            fee_asset: None,
+            fees,
+            unbonding_period: None,
+        };
+
+        self.app
+            .execute_contract(sender, self.paystreams_addr.clone(), &msg, &[])
+    }
+
+    pub fn query_config(&self) -> StdResult<ConfigResponse> {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.paystreams_addr, &crate::msg::QueryMsg::Config {})
+    }
+
     pub fn query_streams_by_payee(&mut self, payee: Addr) -> StdResult<StreamsResponse> {
         let msg = crate::msg::QueryMsg::StreamsByRecipient {
             payee: payee.to_string(),
@@ -225,6 +1135,16 @@ impl Suite {
         Ok(streams)
     }
 
+    pub fn query_stream_claimable(
+        &self,
+        index: u64,
+    ) -> StdResult<crate::msg::StreamClaimableAmtResponse> {
+        let msg = crate::msg::QueryMsg::StreamClaimableAmount { index };
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.paystreams_addr, &msg)
+    }
+
     pub fn query_stream_claimable_amount(&mut self, index: u64) -> StdResult<u128> {
         let msg = crate::msg::QueryMsg::StreamClaimableAmount { index: index };
         let claimable_amt: crate::msg::StreamClaimableAmtResponse = self
@@ -232,6 +1152,6 @@ impl Suite {
             .wrap()
             .query_wasm_smart(&self.paystreams_addr, &msg)?;
 
-        Ok(claimable_amt.amount_available.u128())
+        Ok(Uint128::try_from(claimable_amt.amount_available)?.u128())
     }
 }
@@ -0,0 +1,305 @@
+// Routing of idle stream deposits into allowlisted vaults
+// The contract holds one position per vault, shared by every stream using it. A stream owns shares of that position
+// and its funds are withdrawn right before they are paid out. Whatever a stream's part of the position is worth above
+// its principal is yield, split between recipient and sender as chosen at creation. When it is worth less the vault
+// lost funds, the loss is written off against the balance of that stream alone
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order,
+    Response, StdResult, Uint128, Uint256, WasmMsg,
+};
+use cw_asset::AssetInfo;
+use paystreams::{VaultBalanceResponse, VaultExecuteMsg, VaultQueryMsg};
+
+use crate::contract::{ensure_open, payout_msg, release_stream_funds, save_stream};
+use crate::curve_helpers;
+use crate::msg::{AllowedVaultsResponse, VaultOptions};
+use crate::state::{
+    payment_streams, PaymentStream, StreamVault, ALLOWED_VAULTS, STATE, VAULT_SHARES,
+};
+use crate::ContractError;
+
+// Owner only, adds and removes vaults streams can be deposited in
+pub fn update_vault_allowlist(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    for vault in add {
+        let vault = deps.api.addr_validate(&vault)?;
+        ALLOWED_VAULTS.save(deps.storage, &vault, &Empty {})?;
+    }
+    // Streams already in a removed vault stay there until they are paid out
+    for vault in remove {
+        let vault = deps.api.addr_validate(&vault)?;
+        ALLOWED_VAULTS.remove(deps.storage, &vault);
+    }
+
+    Ok(Response::new().add_attribute("method", "update_vault_allowlist"))
+}
+
+pub fn query_allowed_vaults(deps: Deps) -> StdResult<AllowedVaultsResponse> {
+    let vaults = ALLOWED_VAULTS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    Ok(AllowedVaultsResponse { vaults })
+}
+
+// What the position of the contract in a vault is worth
+fn position_value(deps: &DepsMut, env: &Env, vault: &Addr) -> StdResult<Uint128> {
+    let balance: VaultBalanceResponse = deps.querier.query_wasm_smart(
+        vault,
+        &VaultQueryMsg::Balance {
+            depositor: env.contract.address.to_string(),
+        },
+    )?;
+    Ok(balance.amount)
+}
+
+// The shares to burn to withdraw `amount` from a position worth `value`, rounded up so the shares left never
+// claim more than is left
+fn shares_for(
+    amount: Uint128,
+    total_shares: Uint128,
+    value: Uint128,
+) -> Result<Uint128, ContractError> {
+    if value.is_zero() {
+        return Ok(total_shares);
+    }
+    let shares = (Uint256::from(amount) * Uint256::from(total_shares) + Uint256::from(value)
+        - Uint256::one())
+        / Uint256::from(value);
+    Ok(Uint128::try_from(shares)?)
+}
+
+fn withdraw_msg(vault: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: vault.to_string(),
+        msg: to_binary(&VaultExecuteMsg::Withdraw { amount })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+/// Deposits what a new stream has still to vest into an allowlisted vault, what vested already stays liquid.
+/// Only native streams which are not staked can use one
+pub fn deposit_stream(
+    deps: DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+    options: VaultOptions,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let vault = deps.api.addr_validate(&options.vault)?;
+    if !ALLOWED_VAULTS.has(deps.storage, &vault) {
+        return Err(ContractError::VaultNotAllowed {
+            vault: vault.to_string(),
+        });
+    }
+    let recipient_yield = options.recipient_yield.unwrap_or_default();
+    let denom = match &stream.token_addr {
+        AssetInfo::Native(denom)
+            if stream.staking.is_none() && recipient_yield <= Decimal::one() =>
+        {
+            denom.clone()
+        }
+        _ => return Err(ContractError::InvalidVaultStream {}),
+    };
+    let amount = Uint128::try_from(
+        stream
+            .deposit
+            .checked_sub(curve_helpers::vested_amount(stream, env)?)?,
+    )?;
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    let value = position_value(&deps, env, &vault)?;
+    let total_shares = VAULT_SHARES
+        .may_load(deps.storage, &vault)?
+        .unwrap_or_default();
+    let shares = if total_shares.is_zero() || value.is_zero() {
+        amount
+    } else {
+        amount.multiply_ratio(total_shares, value)
+    };
+    VAULT_SHARES.save(deps.storage, &vault, &total_shares.checked_add(shares)?)?;
+    stream.vault = Some(StreamVault {
+        vault: vault.clone(),
+        principal: amount,
+        shares,
+        recipient_yield,
+    });
+
+    Ok(vec![WasmMsg::Execute {
+        contract_addr: vault.to_string(),
+        msg: to_binary(&VaultExecuteMsg::Deposit {})?,
+        funds: vec![Coin { denom, amount }],
+    }
+    .into()])
+}
+
+// Writes off what a vault lost of the principal of a stream, those funds leave the stream as if paid out
+fn write_off(
+    deps: &mut DepsMut,
+    stream: &mut PaymentStream,
+    lost: Uint128,
+) -> Result<(), ContractError> {
+    if lost.is_zero() {
+        return Ok(());
+    }
+    stream.remaining_balance = stream.remaining_balance.saturating_sub(lost.into());
+    release_stream_funds(deps.storage, stream, lost, Uint128::zero())?;
+    Ok(())
+}
+
+/// Withdraws what the contract is missing to pay `amount` out of a stream, the part of the balance outside
+/// the vault is used first. The position is checked on every pull, whatever the vault lost of it is written off
+/// before anything is withdrawn so the balance of the stream only counts funds which are there. Returns the loss
+/// written off, the caller can pay at most what is left of the balance
+pub fn pull(
+    mut deps: DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+    amount: Uint128,
+) -> Result<(Option<CosmosMsg>, Uint128), ContractError> {
+    let position = match stream.vault.as_mut() {
+        Some(position) => position,
+        None => return Ok((None, Uint128::zero())),
+    };
+    if position.shares.is_zero() {
+        return Ok((None, Uint128::zero()));
+    }
+    let value = position_value(&deps, env, &position.vault)?;
+    let total_shares = VAULT_SHARES.load(deps.storage, &position.vault)?;
+    let worth = value.multiply_ratio(position.shares, total_shares);
+    let lost = position.principal.saturating_sub(worth);
+    position.principal = position.principal.checked_sub(lost)?;
+
+    let liquid = stream
+        .remaining_balance
+        .saturating_sub(position.principal.checked_add(lost)?.into());
+    let withdrawn = Uint128::try_from(liquid).map_or(Uint128::zero(), |liquid| {
+        amount.saturating_sub(liquid).min(position.principal)
+    });
+    let withdraw = if withdrawn.is_zero() {
+        None
+    } else {
+        // Taking out all that is left of the position burns every share so nothing is stranded by rounding
+        let burned = if withdrawn == position.principal {
+            position.shares
+        } else {
+            shares_for(withdrawn, total_shares, value)?.min(position.shares)
+        };
+        VAULT_SHARES.save(
+            deps.storage,
+            &position.vault,
+            &total_shares.checked_sub(burned)?,
+        )?;
+        position.shares = position.shares.checked_sub(burned)?;
+        position.principal = position.principal.checked_sub(withdrawn)?;
+        Some(withdraw_msg(&position.vault, withdrawn)?)
+    };
+    write_off(&mut deps, stream, lost)?;
+    Ok((withdraw, lost))
+}
+
+/// Withdraws the whole position of a stream and pays out what it earned, used whenever a stream is closed early.
+/// What the vault lost is written off so the balance of the stream only counts funds which are there.
+/// The messages have to run before anything is paid out of the stream
+pub fn release(
+    mut deps: DepsMut,
+    env: &Env,
+    stream: &mut PaymentStream,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let position = match stream.vault.as_mut() {
+        Some(position) => position,
+        None => return Ok(vec![]),
+    };
+    if position.shares.is_zero() {
+        return Ok(vec![]);
+    }
+    let value = position_value(&deps, env, &position.vault)?;
+    let total_shares = VAULT_SHARES.load(deps.storage, &position.vault)?;
+    let worth = value.multiply_ratio(position.shares, total_shares);
+    VAULT_SHARES.save(
+        deps.storage,
+        &position.vault,
+        &total_shares.checked_sub(position.shares)?,
+    )?;
+    let earned = worth.saturating_sub(position.principal);
+    let lost = position.principal.saturating_sub(worth);
+    position.shares = Uint128::zero();
+    position.principal = Uint128::zero();
+
+    let mut messages = vec![];
+    if !worth.is_zero() {
+        messages.push(withdraw_msg(&position.vault, worth)?);
+    }
+    messages.extend(yield_payouts(stream, earned)?);
+    write_off(&mut deps, stream, lost)?;
+    Ok(messages)
+}
+
+// Splits what a position earned between recipient and sender
+fn yield_payouts(stream: &PaymentStream, earned: Uint128) -> Result<Vec<CosmosMsg>, ContractError> {
+    let position = match &stream.vault {
+        Some(position) => position,
+        None => return Ok(vec![]),
+    };
+    let to_recipient = earned * position.recipient_yield;
+    let to_sender = earned.checked_sub(to_recipient)?;
+    let mut messages = vec![];
+    if !to_recipient.is_zero() {
+        messages.push(payout_msg(
+            &stream.token_addr,
+            &stream.recipient,
+            to_recipient,
+        )?);
+    }
+    if !to_sender.is_zero() {
+        messages.push(payout_msg(&stream.token_addr, &stream.sender, to_sender)?);
+    }
+    Ok(messages)
+}
+
+// Pays out what the position of a stream earned so far while the principal stays in the vault, anyone can do this
+pub fn harvest_yield(deps: DepsMut, env: Env, stream_idx: u64) -> Result<Response, ContractError> {
+    let mut stream = payment_streams().load(deps.storage, &stream_idx.to_string())?;
+    ensure_open(&stream)?;
+    let position = stream
+        .vault
+        .as_mut()
+        .ok_or(ContractError::InvalidVaultStream {})?;
+    // Once the whole position was withdrawn there is nothing left to earn on
+    if position.shares.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let value = position_value(&deps, &env, &position.vault)?;
+    let total_shares = VAULT_SHARES.load(deps.storage, &position.vault)?;
+    let worth = value.multiply_ratio(position.shares, total_shares);
+    let earned = worth.saturating_sub(position.principal);
+    if earned.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let burned = shares_for(earned, total_shares, value)?.min(position.shares);
+    VAULT_SHARES.save(
+        deps.storage,
+        &position.vault,
+        &total_shares.checked_sub(burned)?,
+    )?;
+    position.shares = position.shares.checked_sub(burned)?;
+    let withdraw = withdraw_msg(&position.vault, earned)?;
+    let payouts = yield_payouts(&stream, earned)?;
+    save_stream(deps.storage, env.block.height, &stream)?;
+
+    Ok(Response::new()
+        .add_message(withdraw)
+        .add_messages(payouts)
+        .add_attribute("method", "harvest_yield")
+        .add_attribute("stream_idx", stream_idx.to_string())
+        .add_attribute("amount", earned))
+}
@@ -0,0 +1,212 @@
+// DAO DAO voting module interface over the funds locked in streams
+// A holder's power is what its streams still owe it, unvested plus vested but unclaimed, so contributors can vote
+// without claiming early. Powers are snapshotted whenever a stream changes so past heights can be queried.
+// A migration rebuilds them page by page, the powers are incomplete until the rebuild is done
+use cosmwasm_std::{Addr, Deps, Env, Order, StdResult, Storage, Uint128, Uint256};
+use cw_asset::AssetInfo;
+use cw_storage_plus::Bound;
+
+use crate::curve_helpers::vested_share;
+use crate::msg::{TotalPowerAtHeightResponse, VotingPowerAtHeightResponse};
+use crate::state::{
+    payment_streams, PaymentStream, StreamStatus, VotingRebuild, GOVERNANCE_ASSET, TOTAL_POWER,
+    VOTING_POWER, VOTING_REBUILD,
+};
+use crate::ContractError;
+
+// Holders or streams a page of the rebuild goes through
+const DEFAULT_REBUILD_LIMIT: u32 = 30;
+const MAX_REBUILD_LIMIT: u32 = 100;
+
+// What a stream still owes each of its holders, nothing for streams outside the governance asset, cancelled
+// streams and streams the recipient has not accepted yet. Without a governance asset every stream counts
+fn locked_by_holder(
+    stream: &PaymentStream,
+    governance_asset: Option<&AssetInfo>,
+) -> Result<Vec<(Addr, Uint128)>, ContractError> {
+    if governance_asset.map_or(false, |asset| *asset != stream.token_addr)
+        || matches!(stream.status, StreamStatus::Cancelled { .. })
+        || stream.awaiting_acceptance.is_some()
+    {
+        return Ok(vec![]);
+    }
+    if stream.shares.is_empty() {
+        return Ok(vec![(
+            stream.recipient.clone(),
+            power_of(stream.remaining_balance),
+        )]);
+    }
+    stream
+        .shares
+        .iter()
+        .enumerate()
+        .map(|(position, share)| {
+            let allotted = vested_share(stream, power_of(stream.deposit), position)?;
+            Ok((
+                share.recipient.clone(),
+                allotted.saturating_sub(share.claimed),
+            ))
+        })
+        .collect()
+}
+
+// Voting power is a Uint128, a balance beyond that counts for the most power there is
+fn power_of(balance: Uint256) -> Uint128 {
+    Uint128::try_from(balance).unwrap_or(Uint128::MAX)
+}
+
+/// Moves voting power from what a stream locked `before` a change to what it locks `after`,
+/// either side is None when the stream is created or removed
+pub fn sync_voting_power(
+    storage: &mut dyn Storage,
+    height: u64,
+    before: Option<&PaymentStream>,
+    after: Option<&PaymentStream>,
+) -> Result<(), ContractError> {
+    // A stream the rebuild did not reach yet is counted as it is once it does
+    if let Some(stream) = after.or(before) {
+        if !counted(storage, stream)? {
+            return Ok(());
+        }
+    }
+    let governance_asset = GOVERNANCE_ASSET.may_load(storage)?;
+    let locked = |stream: Option<&PaymentStream>| match stream {
+        Some(stream) => locked_by_holder(stream, governance_asset.as_ref()),
+        None => Ok(vec![]),
+    };
+    let removed = locked(before)?;
+    let added = locked(after)?;
+    if removed == added {
+        return Ok(());
+    }
+
+    let mut total = TOTAL_POWER.may_load(storage)?.unwrap_or_default();
+    for (holder, amount) in removed {
+        VOTING_POWER.update(storage, &holder, height, |power| -> StdResult<_> {
+            Ok(power.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        total = total.checked_sub(amount)?;
+    }
+    for (holder, amount) in added {
+        VOTING_POWER.update(storage, &holder, height, |power| -> StdResult<_> {
+            Ok(power.unwrap_or_default().checked_add(amount)?)
+        })?;
+        total = total.checked_add(amount)?;
+    }
+    TOTAL_POWER.save(storage, &total, height)?;
+    Ok(())
+}
+
+// Whether the power of a stream is part of the current totals, which is always the case outside a rebuild
+fn counted(storage: &dyn Storage, stream: &PaymentStream) -> StdResult<bool> {
+    Ok(match VOTING_REBUILD.may_load(storage)? {
+        None => true,
+        Some(VotingRebuild::ResetHolders { .. }) => false,
+        Some(VotingRebuild::CountStreams { last_stream }) => last_stream.map_or(false, |last| {
+            stream.stream_idx.to_string().as_str() <= last.as_str()
+        }),
+    })
+}
+
+/// Starts recomputing every holder's power at `height`, for streams created before voting power was tracked
+/// or a change of the governance asset. `rebuild_voting_power` carries it on page by page
+pub fn start_rebuild(storage: &mut dyn Storage, height: u64) -> Result<(), ContractError> {
+    TOTAL_POWER.save(storage, &Uint128::zero(), height)?;
+    VOTING_REBUILD.save(storage, &VotingRebuild::ResetHolders { last_holder: None })?;
+    Ok(())
+}
+
+/// Resets the power of the next `limit` holders, or once they are all reset counts the next `limit` streams.
+/// Returns whether the rebuild is done
+pub fn rebuild_voting_power(
+    storage: &mut dyn Storage,
+    height: u64,
+    limit: Option<u32>,
+) -> Result<bool, ContractError> {
+    let rebuild = VOTING_REBUILD
+        .may_load(storage)?
+        .ok_or(ContractError::NoVotingRebuild {})?;
+    let limit = limit
+        .unwrap_or(DEFAULT_REBUILD_LIMIT)
+        .clamp(1, MAX_REBUILD_LIMIT) as usize;
+    let next = match rebuild {
+        VotingRebuild::ResetHolders { last_holder } => {
+            let mut holders = VOTING_POWER
+                .keys(
+                    storage,
+                    last_holder.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?;
+            for holder in &holders {
+                VOTING_POWER.save(storage, holder, &Uint128::zero(), height)?;
+            }
+            if holders.len() < limit {
+                VotingRebuild::CountStreams { last_stream: None }
+            } else {
+                VotingRebuild::ResetHolders {
+                    last_holder: holders.pop(),
+                }
+            }
+        }
+        VotingRebuild::CountStreams { last_stream } => {
+            let governance_asset = GOVERNANCE_ASSET.may_load(storage)?;
+            let mut streams = payment_streams()
+                .range(
+                    storage,
+                    last_stream.as_deref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?;
+            let mut total = TOTAL_POWER.may_load(storage)?.unwrap_or_default();
+            for (_, stream) in &streams {
+                for (holder, amount) in locked_by_holder(stream, governance_asset.as_ref())? {
+                    VOTING_POWER.update(storage, &holder, height, |power| -> StdResult<_> {
+                        Ok(power.unwrap_or_default().checked_add(amount)?)
+                    })?;
+                    total = total.checked_add(amount)?;
+                }
+            }
+            TOTAL_POWER.save(storage, &total, height)?;
+            if streams.len() < limit {
+                VOTING_REBUILD.remove(storage);
+                return Ok(true);
+            }
+            VotingRebuild::CountStreams {
+                last_stream: streams.pop().map(|(key, _)| key),
+            }
+        }
+    };
+    VOTING_REBUILD.save(storage, &next)?;
+    Ok(false)
+}
+
+pub fn query_voting_power_at_height(
+    deps: Deps,
+    env: Env,
+    address: String,
+    height: Option<u64>,
+) -> StdResult<VotingPowerAtHeightResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let height = height.unwrap_or(env.block.height);
+    let power = VOTING_POWER
+        .may_load_at_height(deps.storage, &address, height)?
+        .unwrap_or_default();
+    Ok(VotingPowerAtHeightResponse { power, height })
+}
+
+pub fn query_total_power_at_height(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalPowerAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    let power = TOTAL_POWER
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    Ok(TotalPowerAtHeightResponse { power, height })
+}
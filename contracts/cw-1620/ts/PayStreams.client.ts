@@ -6,7 +6,7 @@
 
 import { CosmWasmClient, SigningCosmWasmClient, ExecuteResult } from "@cosmjs/cosmwasm-stargate";
 import { Coin, StdFee } from "@cosmjs/amino";
-import { InstantiateMsg, ExecuteMsg, Curve, Uint128, Timestamp, Uint64, StreamType, SaturatingLinear, PiecewiseLinear, QueryMsg, Addr, LookupStreamResponse, PaymentStream, CountResponse, StreamsResponse } from "./PayStreams.types";
+import { AssetInfoBaseForAddr, Addr, InstantiateMsg, ExecuteMsg, Uint128, Binary, Curve, Duration, Expiration, Timestamp, Uint64, CancelableBy, Decimal, RewardsTo, StreamTime, StreamType, AmountOrRatio, AssetInfo, Cw20ReceiveMsg, AssetBaseForAddr, SaturatingLinear, PiecewiseLinear, StreamOptions, CancelPolicy, FiatOptions, StakeOptions, VaultOptions, Recurrence, MilestoneTerms, StreamOffer, ChannelOffer, StreamPacket, Asset, QueryMsg, MigrateMsg, AirdropResponse, Airdrop, AllowedVaultsResponse, ConfigResponse, Uint256, FiatStatusResponse, InfoResponse, ContractVersion, MilestoneStatus, Decimal256, StreamStatus, LookupStreamResponse, PaymentStream, ClaimOperator, FiatTerms, Milestone, RecipientShare, StreamStaking, Unbonding, StreamVault, SenderBalanceResponse, StreamClaimableAmtResponse, CountResponse, StreamsResponse, TotalPowerAtHeightResponse, VotingPowerAtHeightResponse } from "./PayStreams.types";
 export interface PayStreamsReadOnlyInterface {
   contractAddress: string;
   lookupStream: ({
@@ -17,12 +17,12 @@ export interface PayStreamsReadOnlyInterface {
     payer: string;
   }) => Promise<LookupStreamResponse>;
   streamCount: () => Promise<CountResponse>;
-  streamsByPayee: ({
+  streamsByRecipient: ({
     limit,
     payee,
     reverse
   }: {
-    limit?: number;
+    limit?: Uint128;
     payee: string;
     reverse?: boolean;
   }) => Promise<StreamsResponse>;
@@ -31,7 +31,7 @@ export interface PayStreamsReadOnlyInterface {
     reverse,
     sender
   }: {
-    limit?: number;
+    limit?: Uint128;
     reverse?: boolean;
     sender: string;
   }) => Promise<StreamsResponse>;
@@ -40,6 +40,44 @@ export interface PayStreamsReadOnlyInterface {
   }: {
     index: number;
   }) => Promise<StreamsResponse>;
+  streamClaimableAmount: ({
+    index
+  }: {
+    index: number;
+  }) => Promise<StreamClaimableAmtResponse>;
+  senderBalance: ({
+    asset,
+    sender
+  }: {
+    asset: AssetInfoBaseForAddr;
+    sender: string;
+  }) => Promise<SenderBalanceResponse>;
+  airdrop: ({
+    airdropId
+  }: {
+    airdropId: number;
+  }) => Promise<AirdropResponse>;
+  config: () => Promise<ConfigResponse>;
+  votingPowerAtHeight: ({
+    address,
+    height
+  }: {
+    address: string;
+    height?: number;
+  }) => Promise<VotingPowerAtHeightResponse>;
+  totalPowerAtHeight: ({
+    height
+  }: {
+    height?: number;
+  }) => Promise<TotalPowerAtHeightResponse>;
+  dao: () => Promise<Addr>;
+  info: () => Promise<InfoResponse>;
+  allowedVaults: () => Promise<AllowedVaultsResponse>;
+  fiatStatus: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }) => Promise<FiatStatusResponse>;
 }
 export class PayStreamsQueryClient implements PayStreamsReadOnlyInterface {
   client: CosmWasmClient;
@@ -50,9 +88,19 @@ export class PayStreamsQueryClient implements PayStreamsReadOnlyInterface {
     this.contractAddress = contractAddress;
     this.lookupStream = this.lookupStream.bind(this);
     this.streamCount = this.streamCount.bind(this);
-    this.streamsByPayee = this.streamsByPayee.bind(this);
+    this.streamsByRecipient = this.streamsByRecipient.bind(this);
     this.streamsBySender = this.streamsBySender.bind(this);
     this.streamsByIndex = this.streamsByIndex.bind(this);
+    this.streamClaimableAmount = this.streamClaimableAmount.bind(this);
+    this.senderBalance = this.senderBalance.bind(this);
+    this.airdrop = this.airdrop.bind(this);
+    this.config = this.config.bind(this);
+    this.votingPowerAtHeight = this.votingPowerAtHeight.bind(this);
+    this.totalPowerAtHeight = this.totalPowerAtHeight.bind(this);
+    this.dao = this.dao.bind(this);
+    this.info = this.info.bind(this);
+    this.allowedVaults = this.allowedVaults.bind(this);
+    this.fiatStatus = this.fiatStatus.bind(this);
   }
 
   lookupStream = async ({
@@ -74,17 +122,17 @@ export class PayStreamsQueryClient implements PayStreamsReadOnlyInterface {
       stream_count: {}
     });
   };
-  streamsByPayee = async ({
+  streamsByRecipient = async ({
     limit,
     payee,
     reverse
   }: {
-    limit?: number;
+    limit?: Uint128;
     payee: string;
     reverse?: boolean;
   }): Promise<StreamsResponse> => {
     return this.client.queryContractSmart(this.contractAddress, {
-      streams_by_payee: {
+      streams_by_recipient: {
         limit,
         payee,
         reverse
@@ -96,7 +144,7 @@ export class PayStreamsQueryClient implements PayStreamsReadOnlyInterface {
     reverse,
     sender
   }: {
-    limit?: number;
+    limit?: Uint128;
     reverse?: boolean;
     sender: string;
   }): Promise<StreamsResponse> => {
@@ -119,37 +167,376 @@ export class PayStreamsQueryClient implements PayStreamsReadOnlyInterface {
       }
     });
   };
+  streamClaimableAmount = async ({
+    index
+  }: {
+    index: number;
+  }): Promise<StreamClaimableAmtResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      stream_claimable_amount: {
+        index
+      }
+    });
+  };
+  senderBalance = async ({
+    asset,
+    sender
+  }: {
+    asset: AssetInfoBaseForAddr;
+    sender: string;
+  }): Promise<SenderBalanceResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      sender_balance: {
+        asset,
+        sender
+      }
+    });
+  };
+  airdrop = async ({
+    airdropId
+  }: {
+    airdropId: number;
+  }): Promise<AirdropResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      airdrop: {
+        airdrop_id: airdropId
+      }
+    });
+  };
+  config = async (): Promise<ConfigResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      config: {}
+    });
+  };
+  votingPowerAtHeight = async ({
+    address,
+    height
+  }: {
+    address: string;
+    height?: number;
+  }): Promise<VotingPowerAtHeightResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      voting_power_at_height: {
+        address,
+        height
+      }
+    });
+  };
+  totalPowerAtHeight = async ({
+    height
+  }: {
+    height?: number;
+  }): Promise<TotalPowerAtHeightResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      total_power_at_height: {
+        height
+      }
+    });
+  };
+  dao = async (): Promise<Addr> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      dao: {}
+    });
+  };
+  info = async (): Promise<InfoResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      info: {}
+    });
+  };
+  allowedVaults = async (): Promise<AllowedVaultsResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      allowed_vaults: {}
+    });
+  };
+  fiatStatus = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }): Promise<FiatStatusResponse> => {
+    return this.client.queryContractSmart(this.contractAddress, {
+      fiat_status: {
+        stream_idx: streamIdx
+      }
+    });
+  };
 }
 export interface PayStreamsInterface extends PayStreamsReadOnlyInterface {
   contractAddress: string;
   sender: string;
+  receive: ({
+    amount,
+    msg,
+    sender
+  }: {
+    amount: Uint128;
+    msg: Binary;
+    sender: string;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
   createStream: ({
+    asset,
     curve,
-    deposit,
+    duration,
+    options,
     recipient,
     startTime,
     stopTime,
-    streamType,
-    tokenAddr
+    streamType
   }: {
+    asset: AssetBaseForAddr;
     curve?: Curve;
-    deposit: Uint128;
+    duration?: Duration;
+    options?: StreamOptions;
     recipient: string;
-    startTime: Timestamp;
-    stopTime: Timestamp;
+    startTime?: StreamTime;
+    stopTime?: StreamTime;
     streamType?: StreamType;
-    tokenAddr: string;
   }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
-  withdrawFromStream: ({
+  claimFromStream: ({
     amount,
     denom,
-    recipient,
-    streamIdx
+    msg,
+    streamIdx,
+    to
   }: {
     amount: Uint128;
     denom: string;
-    recipient: string;
+    msg?: Binary;
     streamIdx?: number;
+    to?: string;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  activateStream: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  cancelStream: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  transferStream: ({
+    newRecipient,
+    streamIdx
+  }: {
+    newRecipient: string;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  setClaimOperator: ({
+    expires,
+    operator,
+    streamIdx
+  }: {
+    expires?: Expiration;
+    operator?: string;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  setWithdrawalAddress: ({
+    address,
+    streamIdx
+  }: {
+    address?: string;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  splitStream: ({
+    amountOrRatio,
+    newRecipient,
+    streamIdx
+  }: {
+    amountOrRatio: AmountOrRatio;
+    newRecipient: string;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  mergeStreams: ({
+    streamIdxs
+  }: {
+    streamIdxs: number[];
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  acceptStream: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  reclaimStream: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  sweepExpired: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  registerAirdrop: ({
+    asset,
+    curve,
+    expiration,
+    merkleRoot,
+    startTime,
+    stopTime
+  }: {
+    asset: AssetBaseForAddr;
+    curve?: Curve;
+    expiration?: Expiration;
+    merkleRoot: string;
+    startTime: StreamTime;
+    stopTime: StreamTime;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  claimAirdropStream: ({
+    airdropId,
+    amount,
+    proof
+  }: {
+    airdropId: number;
+    amount: Uint128;
+    proof: string[];
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  reclaimAirdrop: ({
+    airdropId
+  }: {
+    airdropId: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  registerOfferKey: ({
+    publicKey
+  }: {
+    publicKey: Binary;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  submitSignedOffer: ({
+    offer,
+    signature
+  }: {
+    offer: StreamOffer;
+    signature: Binary;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  revokeOffer: ({
+    nonce
+  }: {
+    nonce: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  deposit: (fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  withdrawBalance: ({
+    asset
+  }: {
+    asset: AssetBaseForAddr;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  createStreamFromIbc: ({
+    channelId,
+    curve,
+    duration,
+    options,
+    originalSender,
+    recipient,
+    sender,
+    startTime,
+    stopTime,
+    streamType
+  }: {
+    channelId: string;
+    curve?: Curve;
+    duration?: Duration;
+    options?: StreamOptions;
+    originalSender: string;
+    recipient: string;
+    sender?: string;
+    startTime?: StreamTime;
+    stopTime?: StreamTime;
+    streamType?: StreamType;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  offerChannelLiquidity: ({
+    channelId,
+    offer
+  }: {
+    channelId: string;
+    offer?: ChannelOffer;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  receiveStreamPacket: ({
+    channelId,
+    packet,
+    provider
+  }: {
+    channelId: string;
+    packet: StreamPacket;
+    provider: string;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  returnIbcBalance: ({
+    denom,
+    sender
+  }: {
+    denom: string;
+    sender: string;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  undelegateVested: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  claimStakingRewards: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  settleUnbonded: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  rebuildVotingPower: ({
+    limit
+  }: {
+    limit?: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  harvestYield: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  approveMilestone: ({
+    milestone,
+    streamIdx
+  }: {
+    milestone: number;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  reclaimMilestone: ({
+    milestone,
+    streamIdx
+  }: {
+    milestone: number;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  topUpStream: ({
+    amount,
+    streamIdx
+  }: {
+    amount?: Uint128;
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  addCollateral: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  withdrawCollateral: ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  updateVaultAllowlist: ({
+    add,
+    remove
+  }: {
+    add: string[];
+    remove: string[];
+  }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
+  updateConfig: ({
+    feeAsset,
+    fees,
+    owner,
+    unbondingPeriod
+  }: {
+    feeAsset?: Asset;
+    fees?: Decimal[];
+    owner?: string;
+    unbondingPeriod?: number;
   }, fee?: number | StdFee | "auto", memo?: string, _funds?: Coin[]) => Promise<ExecuteResult>;
 }
 export class PayStreamsClient extends PayStreamsQueryClient implements PayStreamsInterface {
@@ -162,57 +549,585 @@ export class PayStreamsClient extends PayStreamsQueryClient implements PayStream
     this.client = client;
     this.sender = sender;
     this.contractAddress = contractAddress;
+    this.receive = this.receive.bind(this);
     this.createStream = this.createStream.bind(this);
-    this.withdrawFromStream = this.withdrawFromStream.bind(this);
+    this.claimFromStream = this.claimFromStream.bind(this);
+    this.activateStream = this.activateStream.bind(this);
+    this.cancelStream = this.cancelStream.bind(this);
+    this.transferStream = this.transferStream.bind(this);
+    this.setClaimOperator = this.setClaimOperator.bind(this);
+    this.setWithdrawalAddress = this.setWithdrawalAddress.bind(this);
+    this.splitStream = this.splitStream.bind(this);
+    this.mergeStreams = this.mergeStreams.bind(this);
+    this.acceptStream = this.acceptStream.bind(this);
+    this.reclaimStream = this.reclaimStream.bind(this);
+    this.sweepExpired = this.sweepExpired.bind(this);
+    this.registerAirdrop = this.registerAirdrop.bind(this);
+    this.claimAirdropStream = this.claimAirdropStream.bind(this);
+    this.reclaimAirdrop = this.reclaimAirdrop.bind(this);
+    this.registerOfferKey = this.registerOfferKey.bind(this);
+    this.submitSignedOffer = this.submitSignedOffer.bind(this);
+    this.revokeOffer = this.revokeOffer.bind(this);
+    this.deposit = this.deposit.bind(this);
+    this.withdrawBalance = this.withdrawBalance.bind(this);
+    this.createStreamFromIbc = this.createStreamFromIbc.bind(this);
+    this.offerChannelLiquidity = this.offerChannelLiquidity.bind(this);
+    this.receiveStreamPacket = this.receiveStreamPacket.bind(this);
+    this.returnIbcBalance = this.returnIbcBalance.bind(this);
+    this.undelegateVested = this.undelegateVested.bind(this);
+    this.claimStakingRewards = this.claimStakingRewards.bind(this);
+    this.settleUnbonded = this.settleUnbonded.bind(this);
+    this.rebuildVotingPower = this.rebuildVotingPower.bind(this);
+    this.harvestYield = this.harvestYield.bind(this);
+    this.approveMilestone = this.approveMilestone.bind(this);
+    this.reclaimMilestone = this.reclaimMilestone.bind(this);
+    this.topUpStream = this.topUpStream.bind(this);
+    this.addCollateral = this.addCollateral.bind(this);
+    this.withdrawCollateral = this.withdrawCollateral.bind(this);
+    this.updateVaultAllowlist = this.updateVaultAllowlist.bind(this);
+    this.updateConfig = this.updateConfig.bind(this);
   }
 
+  receive = async ({
+    amount,
+    msg,
+    sender
+  }: {
+    amount: Uint128;
+    msg: Binary;
+    sender: string;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      receive: {
+        amount,
+        msg,
+        sender
+      }
+    }, fee, memo, _funds);
+  };
   createStream = async ({
+    asset,
     curve,
-    deposit,
+    duration,
+    options,
     recipient,
     startTime,
     stopTime,
-    streamType,
-    tokenAddr
+    streamType
   }: {
+    asset: AssetBaseForAddr;
     curve?: Curve;
-    deposit: Uint128;
+    duration?: Duration;
+    options?: StreamOptions;
     recipient: string;
-    startTime: Timestamp;
-    stopTime: Timestamp;
+    startTime?: StreamTime;
+    stopTime?: StreamTime;
     streamType?: StreamType;
-    tokenAddr: string;
   }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
     return await this.client.execute(this.sender, this.contractAddress, {
       create_stream: {
+        asset,
         curve,
-        deposit,
+        duration,
+        options,
         recipient,
         start_time: startTime,
         stop_time: stopTime,
-        stream_type: streamType,
-        token_addr: tokenAddr
+        stream_type: streamType
       }
     }, fee, memo, _funds);
   };
-  withdrawFromStream = async ({
+  claimFromStream = async ({
     amount,
     denom,
-    recipient,
-    streamIdx
+    msg,
+    streamIdx,
+    to
   }: {
     amount: Uint128;
     denom: string;
-    recipient: string;
+    msg?: Binary;
     streamIdx?: number;
+    to?: string;
   }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
     return await this.client.execute(this.sender, this.contractAddress, {
-      withdraw_from_stream: {
+      claim_from_stream: {
         amount,
         denom,
+        msg,
+        stream_idx: streamIdx,
+        to
+      }
+    }, fee, memo, _funds);
+  };
+  activateStream = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      activate_stream: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  cancelStream = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      cancel_stream: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  transferStream = async ({
+    newRecipient,
+    streamIdx
+  }: {
+    newRecipient: string;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      transfer_stream: {
+        new_recipient: newRecipient,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  setClaimOperator = async ({
+    expires,
+    operator,
+    streamIdx
+  }: {
+    expires?: Expiration;
+    operator?: string;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      set_claim_operator: {
+        expires,
+        operator,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  setWithdrawalAddress = async ({
+    address,
+    streamIdx
+  }: {
+    address?: string;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      set_withdrawal_address: {
+        address,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  splitStream = async ({
+    amountOrRatio,
+    newRecipient,
+    streamIdx
+  }: {
+    amountOrRatio: AmountOrRatio;
+    newRecipient: string;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      split_stream: {
+        amount_or_ratio: amountOrRatio,
+        new_recipient: newRecipient,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  mergeStreams = async ({
+    streamIdxs
+  }: {
+    streamIdxs: number[];
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      merge_streams: {
+        stream_idxs: streamIdxs
+      }
+    }, fee, memo, _funds);
+  };
+  acceptStream = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      accept_stream: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  reclaimStream = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      reclaim_stream: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  sweepExpired = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      sweep_expired: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  registerAirdrop = async ({
+    asset,
+    curve,
+    expiration,
+    merkleRoot,
+    startTime,
+    stopTime
+  }: {
+    asset: AssetBaseForAddr;
+    curve?: Curve;
+    expiration?: Expiration;
+    merkleRoot: string;
+    startTime: StreamTime;
+    stopTime: StreamTime;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      register_airdrop: {
+        asset,
+        curve,
+        expiration,
+        merkle_root: merkleRoot,
+        start_time: startTime,
+        stop_time: stopTime
+      }
+    }, fee, memo, _funds);
+  };
+  claimAirdropStream = async ({
+    airdropId,
+    amount,
+    proof
+  }: {
+    airdropId: number;
+    amount: Uint128;
+    proof: string[];
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      claim_airdrop_stream: {
+        airdrop_id: airdropId,
+        amount,
+        proof
+      }
+    }, fee, memo, _funds);
+  };
+  reclaimAirdrop = async ({
+    airdropId
+  }: {
+    airdropId: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      reclaim_airdrop: {
+        airdrop_id: airdropId
+      }
+    }, fee, memo, _funds);
+  };
+  registerOfferKey = async ({
+    publicKey
+  }: {
+    publicKey: Binary;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      register_offer_key: {
+        public_key: publicKey
+      }
+    }, fee, memo, _funds);
+  };
+  submitSignedOffer = async ({
+    offer,
+    signature
+  }: {
+    offer: StreamOffer;
+    signature: Binary;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      submit_signed_offer: {
+        offer,
+        signature
+      }
+    }, fee, memo, _funds);
+  };
+  revokeOffer = async ({
+    nonce
+  }: {
+    nonce: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      revoke_offer: {
+        nonce
+      }
+    }, fee, memo, _funds);
+  };
+  deposit = async (fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      deposit: {}
+    }, fee, memo, _funds);
+  };
+  withdrawBalance = async ({
+    asset
+  }: {
+    asset: AssetBaseForAddr;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      withdraw_balance: {
+        asset
+      }
+    }, fee, memo, _funds);
+  };
+  createStreamFromIbc = async ({
+    channelId,
+    curve,
+    duration,
+    options,
+    originalSender,
+    recipient,
+    sender,
+    startTime,
+    stopTime,
+    streamType
+  }: {
+    channelId: string;
+    curve?: Curve;
+    duration?: Duration;
+    options?: StreamOptions;
+    originalSender: string;
+    recipient: string;
+    sender?: string;
+    startTime?: StreamTime;
+    stopTime?: StreamTime;
+    streamType?: StreamType;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      create_stream_from_ibc: {
+        channel_id: channelId,
+        curve,
+        duration,
+        options,
+        original_sender: originalSender,
         recipient,
+        sender,
+        start_time: startTime,
+        stop_time: stopTime,
+        stream_type: streamType
+      }
+    }, fee, memo, _funds);
+  };
+  offerChannelLiquidity = async ({
+    channelId,
+    offer
+  }: {
+    channelId: string;
+    offer?: ChannelOffer;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      offer_channel_liquidity: {
+        channel_id: channelId,
+        offer
+      }
+    }, fee, memo, _funds);
+  };
+  receiveStreamPacket = async ({
+    channelId,
+    packet,
+    provider
+  }: {
+    channelId: string;
+    packet: StreamPacket;
+    provider: string;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      receive_stream_packet: {
+        channel_id: channelId,
+        packet,
+        provider
+      }
+    }, fee, memo, _funds);
+  };
+  returnIbcBalance = async ({
+    denom,
+    sender
+  }: {
+    denom: string;
+    sender: string;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      return_ibc_balance: {
+        denom,
+        sender
+      }
+    }, fee, memo, _funds);
+  };
+  undelegateVested = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      undelegate_vested: {
         stream_idx: streamIdx
       }
     }, fee, memo, _funds);
   };
+  claimStakingRewards = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      claim_staking_rewards: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  settleUnbonded = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      settle_unbonded: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  rebuildVotingPower = async ({
+    limit
+  }: {
+    limit?: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      rebuild_voting_power: {
+        limit
+      }
+    }, fee, memo, _funds);
+  };
+  harvestYield = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      harvest_yield: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  approveMilestone = async ({
+    milestone,
+    streamIdx
+  }: {
+    milestone: number;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      approve_milestone: {
+        milestone,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  reclaimMilestone = async ({
+    milestone,
+    streamIdx
+  }: {
+    milestone: number;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      reclaim_milestone: {
+        milestone,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  topUpStream = async ({
+    amount,
+    streamIdx
+  }: {
+    amount?: Uint128;
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      top_up_stream: {
+        amount,
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  addCollateral = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      add_collateral: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  withdrawCollateral = async ({
+    streamIdx
+  }: {
+    streamIdx: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      withdraw_collateral: {
+        stream_idx: streamIdx
+      }
+    }, fee, memo, _funds);
+  };
+  updateVaultAllowlist = async ({
+    add,
+    remove
+  }: {
+    add: string[];
+    remove: string[];
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      update_vault_allowlist: {
+        add,
+        remove
+      }
+    }, fee, memo, _funds);
+  };
+  updateConfig = async ({
+    feeAsset,
+    fees,
+    owner,
+    unbondingPeriod
+  }: {
+    feeAsset?: Asset;
+    fees?: Decimal[];
+    owner?: string;
+    unbondingPeriod?: number;
+  }, fee: number | StdFee | "auto" = "auto", memo?: string, _funds?: Coin[]): Promise<ExecuteResult> => {
+    return await this.client.execute(this.sender, this.contractAddress, {
+      update_config: {
+        fee_asset: feeAsset,
+        fees,
+        owner,
+        unbonding_period: unbondingPeriod
+      }
+    }, fee, memo, _funds);
+  };
 }
\ No newline at end of file
@@ -4,27 +4,210 @@
 * and run the @cosmwasm/ts-codegen generate command to regenerate this file.
 */
 
+export type AssetInfoBaseForAddr = {
+  native: string;
+} | {
+  cw20: Addr;
+} | {
+  cw1155: [Addr, string];
+};
+export type Addr = string;
 export interface InstantiateMsg {
   count: number;
+  governance_asset?: AssetInfoBaseForAddr | null;
 }
 export type ExecuteMsg = {
+  receive: Cw20ReceiveMsg;
+} | {
   create_stream: {
+    asset: AssetBaseForAddr;
     curve?: Curve | null;
-    deposit: Uint128;
+    duration?: Duration | null;
+    options?: StreamOptions | null;
     recipient: string;
-    start_time: Timestamp;
-    stop_time: Timestamp;
+    start_time?: StreamTime | null;
+    stop_time?: StreamTime | null;
     stream_type?: StreamType | null;
-    token_addr: string;
   };
 } | {
-  withdraw_from_stream: {
+  claim_from_stream: {
     amount: Uint128;
     denom: string;
-    recipient: string;
+    msg?: Binary | null;
     stream_idx?: number | null;
+    to?: string | null;
+  };
+} | {
+  activate_stream: {
+    stream_idx: number;
+  };
+} | {
+  cancel_stream: {
+    stream_idx: number;
+  };
+} | {
+  transfer_stream: {
+    new_recipient: string;
+    stream_idx: number;
+  };
+} | {
+  set_claim_operator: {
+    expires?: Expiration | null;
+    operator?: string | null;
+    stream_idx: number;
+  };
+} | {
+  set_withdrawal_address: {
+    address?: string | null;
+    stream_idx: number;
+  };
+} | {
+  split_stream: {
+    amount_or_ratio: AmountOrRatio;
+    new_recipient: string;
+    stream_idx: number;
+  };
+} | {
+  merge_streams: {
+    stream_idxs: number[];
+  };
+} | {
+  accept_stream: {
+    stream_idx: number;
+  };
+} | {
+  reclaim_stream: {
+    stream_idx: number;
+  };
+} | {
+  sweep_expired: {
+    stream_idx: number;
+  };
+} | {
+  register_airdrop: {
+    asset: AssetBaseForAddr;
+    curve?: Curve | null;
+    expiration?: Expiration | null;
+    merkle_root: string;
+    start_time: StreamTime;
+    stop_time: StreamTime;
+  };
+} | {
+  claim_airdrop_stream: {
+    airdrop_id: number;
+    amount: Uint128;
+    proof: string[];
+  };
+} | {
+  reclaim_airdrop: {
+    airdrop_id: number;
+  };
+} | {
+  register_offer_key: {
+    public_key: Binary;
+  };
+} | {
+  submit_signed_offer: {
+    offer: StreamOffer;
+    signature: Binary;
+  };
+} | {
+  revoke_offer: {
+    nonce: number;
+  };
+} | {
+  deposit: {};
+} | {
+  withdraw_balance: {
+    asset: AssetBaseForAddr;
+  };
+} | {
+  create_stream_from_ibc: {
+    channel_id: string;
+    curve?: Curve | null;
+    duration?: Duration | null;
+    options?: StreamOptions | null;
+    original_sender: string;
+    recipient: string;
+    sender?: string | null;
+    start_time?: StreamTime | null;
+    stop_time?: StreamTime | null;
+    stream_type?: StreamType | null;
+  };
+} | {
+  offer_channel_liquidity: {
+    channel_id: string;
+    offer?: ChannelOffer | null;
+  };
+} | {
+  receive_stream_packet: {
+    channel_id: string;
+    packet: StreamPacket;
+    provider: string;
+  };
+} | {
+  return_ibc_balance: {
+    denom: string;
+    sender: string;
+  };
+} | {
+  undelegate_vested: {
+    stream_idx: number;
+  };
+} | {
+  claim_staking_rewards: {
+    stream_idx: number;
+  };
+} | {
+  settle_unbonded: {
+    stream_idx: number;
+  };
+} | {
+  rebuild_voting_power: {
+    limit?: number | null;
+  };
+} | {
+  harvest_yield: {
+    stream_idx: number;
+  };
+} | {
+  approve_milestone: {
+    milestone: number;
+    stream_idx: number;
+  };
+} | {
+  reclaim_milestone: {
+    milestone: number;
+    stream_idx: number;
+  };
+} | {
+  top_up_stream: {
+    amount?: Uint128 | null;
+    stream_idx: number;
+  };
+} | {
+  add_collateral: {
+    stream_idx: number;
+  };
+} | {
+  withdraw_collateral: {
+    stream_idx: number;
+  };
+} | {
+  update_vault_allowlist: {
+    add: string[];
+    remove: string[];
+  };
+} | {
+  update_config: {
+    fee_asset?: Asset | null;
+    fees?: Decimal[] | null;
+    owner?: string | null;
+    unbonding_period?: number | null;
   };
 };
+export type Uint128 = string;
+export type Binary = string;
 export type Curve = {
   constant: {
     y: Uint128;
@@ -35,10 +218,58 @@ export type Curve = {
 } | {
   piecewise_linear: PiecewiseLinear;
 };
-export type Uint128 = string;
+export type Duration = {
+  height: number;
+} | {
+  time: number;
+};
+export type Expiration = {
+  at_height: number;
+} | {
+  at_time: Timestamp;
+} | {
+  never: {};
+};
 export type Timestamp = Uint64;
 export type Uint64 = string;
-export type StreamType = "basic" | "linear_curve_based" | "cliff_curve_based" | "dynamic_curve_based" | "exponential_curve_based" | "exponential_curve_based_with_cliff" | "traditional_unlock_step_curve";
+export type CancelableBy = "sender" | "recipient" | "both" | "none" | {
+  admin: Addr;
+};
+export type Decimal = string;
+export type RewardsTo = "recipient" | "sender";
+export type StreamTime = {
+  height: number;
+} | {
+  time: Timestamp;
+};
+export type StreamType = "basic" | "linear_curve_based" | "cliff_curve_based" | "dynamic_curve_based" | "exponential_curve_based" | "exponential_curve_based_with_cliff" | "traditional_unlock_step_curve" | {
+  recurring: Recurrence;
+} | {
+  milestones: MilestoneTerms[];
+};
+export type AmountOrRatio = {
+  amount: Uint128;
+} | {
+  ratio: Decimal;
+};
+export type AssetInfo = {
+  token: {
+    contract_addr: string;
+  };
+} | {
+  native_token: {
+    denom: string;
+  };
+};
+export interface Cw20ReceiveMsg {
+  amount: Uint128;
+  msg: Binary;
+  sender: string;
+}
+export interface AssetBaseForAddr {
+  amount: Uint128;
+  info: AssetInfoBaseForAddr;
+}
 export interface SaturatingLinear {
   max_x: number;
   max_y: Uint128;
@@ -50,6 +281,84 @@ export interface PiecewiseLinear {
   steps: [number, Uint128][];
   [k: string]: unknown;
 }
+export interface StreamOptions {
+  acceptance_deadline?: Expiration | null;
+  activator?: string | null;
+  cancel_policy?: CancelPolicy | null;
+  claim_deadline?: Expiration | null;
+  fiat?: FiatOptions | null;
+  from_balance?: boolean | null;
+  notify_recipient?: boolean | null;
+  requires_acceptance?: boolean | null;
+  sender_can_transfer?: boolean | null;
+  shares?: [string, Decimal][] | null;
+  stake?: StakeOptions | null;
+  vault?: VaultOptions | null;
+}
+export interface CancelPolicy {
+  cancelable_by: CancelableBy;
+  notice_period?: Duration | null;
+}
+export interface FiatOptions {
+  amount: Uint128;
+  currency: string;
+  max_staleness: number;
+  oracle: string;
+  tolerance: Decimal;
+}
+export interface StakeOptions {
+  rewards_to?: RewardsTo | null;
+  validator: string;
+}
+export interface VaultOptions {
+  recipient_yield?: Decimal | null;
+  vault: string;
+}
+export interface Recurrence {
+  amount_per_period: Uint128;
+  max_periods?: number | null;
+  period: Duration;
+}
+export interface MilestoneTerms {
+  amount: Uint128;
+  approvers: string[];
+  deadline: Expiration;
+  threshold?: number | null;
+  vesting?: Duration | null;
+}
+export interface StreamOffer {
+  asset: AssetBaseForAddr;
+  chain_id: string;
+  contract: string;
+  curve?: Curve | null;
+  duration?: Duration | null;
+  expires: Expiration;
+  nonce: number;
+  recipient: string;
+  sender: string;
+  start_time?: StreamTime | null;
+  stop_time?: StreamTime | null;
+  stream_type?: StreamType | null;
+}
+export interface ChannelOffer {
+  denom: string;
+  source_address: string;
+  source_denom: string;
+}
+export interface StreamPacket {
+  amount: Uint128;
+  cancel_policy?: CancelPolicy | null;
+  recipient: string;
+  remote_sender?: string | null;
+  sender: string;
+  source_denom: string;
+  start_time: Timestamp;
+  stop_time: Timestamp;
+}
+export interface Asset {
+  amount: Uint128;
+  info: AssetInfo;
+}
 export type QueryMsg = {
   lookup_stream: {
     payee: string;
@@ -58,14 +367,14 @@ export type QueryMsg = {
 } | {
   stream_count: {};
 } | {
-  streams_by_payee: {
-    limit?: number | null;
+  streams_by_recipient: {
+    limit?: Uint128 | null;
     payee: string;
     reverse?: boolean | null;
   };
 } | {
   streams_by_sender: {
-    limit?: number | null;
+    limit?: Uint128 | null;
     reverse?: boolean | null;
     sender: string;
   };
@@ -73,28 +382,205 @@ export type QueryMsg = {
   streams_by_index: {
     index: number;
   };
+} | {
+  stream_claimable_amount: {
+    index: number;
+  };
+} | {
+  sender_balance: {
+    asset: AssetInfoBaseForAddr;
+    sender: string;
+  };
+} | {
+  airdrop: {
+    airdrop_id: number;
+  };
+} | {
+  config: {};
+} | {
+  voting_power_at_height: {
+    address: string;
+    height?: number | null;
+  };
+} | {
+  total_power_at_height: {
+    height?: number | null;
+  };
+} | {
+  dao: {};
+} | {
+  info: {};
+} | {
+  allowed_vaults: {};
+} | {
+  fiat_status: {
+    stream_idx: number;
+  };
+};
+export interface MigrateMsg {
+  governance_asset?: AssetInfoBaseForAddr | null;
+}
+export interface AirdropResponse {
+  airdrop: Airdrop;
+}
+export interface Airdrop {
+  airdrop_id: number;
+  asset: AssetInfoBaseForAddr;
+  claimed: Uint128;
+  curve?: Curve | null;
+  expiration?: Expiration | null;
+  merkle_root: string;
+  reclaimed: Uint128;
+  sender: Addr;
+  start_time: StreamTime;
+  stop_time: StreamTime;
+  total: Uint128;
+}
+export interface AllowedVaultsResponse {
+  vaults: Addr[];
+}
+export interface ConfigResponse {
+  fee_asset?: Asset | null;
+  fees?: Decimal[] | null;
+  owner: Addr;
+}
+export type Uint256 = string;
+export interface FiatStatusResponse {
+  amount_available: Uint128;
+  collateral: Uint256;
+  currency: string;
+  owed: Uint128;
+  paid: Uint128;
+  price?: Decimal | null;
+  shortfall: Uint128;
+  vested: Uint128;
+}
+export interface InfoResponse {
+  info: ContractVersion;
+}
+export interface ContractVersion {
+  contract: string;
+  version: string;
+}
+export type MilestoneStatus = "pending" | {
+  approved: {
+    at: number;
+  };
+} | "reclaimed";
+export type Decimal256 = string;
+export type StreamStatus = "pending" | "active" | {
+  cancelled: {
+    cancelled_at: Timestamp;
+    recipient_amount: Uint128;
+    sender_amount: Uint128;
+  };
 };
-export type Addr = string;
 export interface LookupStreamResponse {
   stream: PaymentStream;
 }
 export interface PaymentStream {
+  activator?: Addr | null;
+  awaiting_acceptance?: Expiration | null;
+  cancel_policy: CancelPolicy;
+  cancels_at?: StreamTime | null;
+  claim_deadline?: Expiration | null;
+  claim_operator?: ClaimOperator | null;
   curve?: Curve | null;
-  deposit: Uint128;
-  is_entity: boolean;
-  rate_per_second: Uint128;
+  deposit: Uint256;
+  fiat?: FiatTerms | null;
+  funded_from_balance: boolean;
+  is_closed: boolean;
+  milestones: Milestone[];
+  notify_recipient: boolean;
+  pending_duration?: Duration | null;
+  rate_per_second: Decimal256;
   recipient: Addr;
-  remaining_balance: Uint128;
+  recurrence?: Recurrence | null;
+  remaining_balance: Uint256;
   sender: Addr;
-  start_time: Timestamp;
-  stop_time: Timestamp;
+  sender_can_transfer: boolean;
+  shares: RecipientShare[];
+  staking?: StreamStaking | null;
+  start_time: StreamTime;
+  status: StreamStatus;
+  stop_time: StreamTime;
   stream_idx: number;
-  token_addr: Addr;
+  token_addr: AssetInfoBaseForAddr;
+  vault?: StreamVault | null;
+  withdrawal_address?: Addr | null;
   [k: string]: unknown;
 }
+export interface ClaimOperator {
+  address: Addr;
+  expires: Expiration;
+}
+export interface FiatTerms {
+  amount: Uint128;
+  currency: string;
+  last_price: Decimal;
+  last_priced_at: Timestamp;
+  max_staleness: number;
+  oracle: Addr;
+  paid: Uint128;
+  tolerance: Decimal;
+}
+export interface Milestone {
+  amount: Uint128;
+  approvals: Addr[];
+  approvers: Addr[];
+  deadline: Expiration;
+  status: MilestoneStatus;
+  threshold: number;
+  vesting?: Duration | null;
+}
+export interface RecipientShare {
+  claimed: Uint128;
+  recipient: Addr;
+  share: Decimal;
+}
+export interface StreamStaking {
+  delegated: Uint128;
+  pending_rewards: Uint128;
+  reward_index: Decimal;
+  rewards_to: RewardsTo;
+  settlement?: [Uint128, Uint128] | null;
+  slash_factor: Decimal;
+  slashed: Uint128;
+  unbonding: Unbonding[];
+  validator: string;
+}
+export interface Unbonding {
+  amount: Uint128;
+  release_at: Timestamp;
+}
+export interface StreamVault {
+  principal: Uint128;
+  recipient_yield: Decimal;
+  shares: Uint128;
+  vault: Addr;
+}
+export interface SenderBalanceResponse {
+  committed: Uint128;
+  free: Uint128;
+}
+export interface StreamClaimableAmtResponse {
+  amount_available: Uint256;
+  amount_streamed: Uint256;
+  amount_sweepable: Uint256;
+  claim_deadline?: Expiration | null;
+  stream: PaymentStream;
+}
 export interface CountResponse {
   count: number;
 }
 export interface StreamsResponse {
   streams: PaymentStream[];
+}
+export interface TotalPowerAtHeightResponse {
+  height: number;
+  power: Uint128;
+}
+export interface VotingPowerAtHeightResponse {
+  height: number;
+  power: Uint128;
 }
\ No newline at end of file
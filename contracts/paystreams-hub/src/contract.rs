@@ -0,0 +1,412 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order,
+    Reply, Response, StdResult, Storage, SubMsg, WasmMsg,
+};
+use cw2::set_contract_version;
+use cw_1620::ibc_hooks::SudoMsg;
+use cw_asset::AssetInfo;
+use cw_storage_plus::Bound;
+use cw_utils::parse_reply_instantiate_data;
+use white_whale::pool_network::asset::Asset;
+
+use crate::error::ContractError;
+use crate::ibc;
+use crate::msg::{
+    ExecuteMsg, HubStreamsResponse, InstanceStreams, InstancesResponse, InstantiateMsg, QueryMsg,
+};
+use crate::state::{Config, Instance, CONFIG, INSTANCES, ORG_INSTANCES, PENDING_INSTANCE};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:paystreams-hub";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+// Reply id of the instantiation of a new stream contract
+const INSTANTIATE_REPLY_ID: u64 = 1;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let owner = match msg.owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => info.sender,
+    };
+    let config = Config {
+        owner: owner.clone(),
+        stream_code_id: msg.stream_code_id,
+        fee_asset: msg.fee_asset,
+        fees: msg.fees,
+    };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("owner", owner)
+        .add_attribute("stream_code_id", msg.stream_code_id.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreateInstance {
+            org_admin,
+            label,
+            governance_asset,
+        } => create_instance(deps, env, info, org_admin, label, governance_asset),
+        ExecuteMsg::UpdateInstanceFees {
+            instance,
+            fee_asset,
+            fees,
+        } => update_instance_fees(deps, info, instance, fee_asset, fees),
+        ExecuteMsg::UpdateConfig {
+            owner,
+            stream_code_id,
+            fee_asset,
+            fees,
+        } => update_config(deps, info, owner, stream_code_id, fee_asset, fees),
+        ExecuteMsg::PushFees { start_after, limit } => push_fees(deps, info, start_after, limit),
+        ExecuteMsg::SetRemoteContract { channel_id, remote } => {
+            ibc::set_remote_contract(deps, info, channel_id, remote)
+        }
+        ExecuteMsg::CreateRemoteStream {
+            channel_id,
+            sender,
+            recipient,
+            start_time,
+            stop_time,
+            cancel_policy,
+            timeout,
+        } => ibc::create_remote_stream(
+            deps,
+            env,
+            info,
+            channel_id,
+            sender,
+            recipient,
+            start_time,
+            stop_time,
+            cancel_policy,
+            timeout,
+        ),
+    }
+}
+
+fn create_instance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    org_admin: Option<String>,
+    label: String,
+    governance_asset: Option<AssetInfo>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let org_admin = match org_admin {
+        Some(org_admin) => deps.api.addr_validate(&org_admin)?,
+        None => info.sender,
+    };
+    PENDING_INSTANCE.save(deps.storage, &(org_admin.clone(), label.clone()))?;
+
+    // The hub instantiates the instance so it is its owner, and its wasm admin for migrations
+    let instantiate = WasmMsg::Instantiate {
+        admin: Some(env.contract.address.to_string()),
+        code_id: config.stream_code_id,
+        msg: to_binary(&cw_1620::msg::InstantiateMsg {
+            count: 0,
+            governance_asset,
+        })?,
+        funds: vec![],
+        label,
+    };
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(instantiate, INSTANTIATE_REPLY_ID))
+        .add_attribute("method", "create_instance")
+        .add_attribute("org_admin", org_admin))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_REPLY_ID => register_instance(deps, msg),
+        ibc::TRANSFER_REPLY_ID => ibc::register_transfer(deps, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => ibc::transfer_complete(deps, msg),
+    }
+}
+
+// Records the new instance under its organization and hands it the fee settings of the hub
+fn register_instance(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res = parse_reply_instantiate_data(msg)?;
+    let address = deps.api.addr_validate(&res.contract_address)?;
+    let (org_admin, label) = PENDING_INSTANCE.load(deps.storage)?;
+    PENDING_INSTANCE.remove(deps.storage);
+    let config = CONFIG.load(deps.storage)?;
+
+    let instance = Instance {
+        address: address.clone(),
+        org_admin: org_admin.clone(),
+        label,
+        code_id: config.stream_code_id,
+        own_fees: false,
+    };
+    INSTANCES.save(deps.storage, &address, &instance)?;
+    ORG_INSTANCES.save(deps.storage, (&org_admin, &address), &Empty {})?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "register_instance")
+        .add_attribute("instance", address.clone())
+        .add_attribute("org_admin", org_admin);
+    if config.fee_asset.is_some() || config.fees.is_some() {
+        response = response.add_message(update_fees_msg(&address, config.fee_asset, config.fees)?);
+    }
+    Ok(response)
+}
+
+fn update_instance_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    instance: String,
+    fee_asset: Option<Asset>,
+    fees: Option<Vec<Decimal>>,
+) -> Result<Response, ContractError> {
+    let mut instance = load_instance(deps.storage, deps.api.addr_validate(&instance)?)?;
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != instance.org_admin && info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    instance.own_fees = true;
+    INSTANCES.save(deps.storage, &instance.address, &instance)?;
+
+    Ok(Response::new()
+        .add_message(update_fees_msg(&instance.address, fee_asset, fees)?)
+        .add_attribute("method", "update_instance_fees")
+        .add_attribute("instance", instance.address))
+}
+
+fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
+    stream_code_id: Option<u64>,
+    fee_asset: Option<Asset>,
+    fees: Option<Vec<Decimal>>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(owner) = owner {
+        config.owner = deps.api.addr_validate(&owner)?;
+    }
+    if let Some(stream_code_id) = stream_code_id {
+        config.stream_code_id = stream_code_id;
+    }
+    if let Some(fee_asset) = fee_asset {
+        config.fee_asset = Some(fee_asset);
+    }
+    if let Some(fees) = fees {
+        config.fees = Some(fees);
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_attribute("owner", config.owner))
+}
+
+// Pushes the fees of the hub to one page of instances, there can be too many to update in one message
+fn push_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let instances = instances_page(deps.as_ref(), start_after, limit)?;
+
+    let mut messages = vec![];
+    for instance in &instances {
+        if !instance.own_fees {
+            messages.push(update_fees_msg(
+                &instance.address,
+                config.fee_asset.clone(),
+                config.fees.clone(),
+            )?);
+        }
+    }
+    let last_instance = instances
+        .last()
+        .map(|instance| instance.address.to_string())
+        .unwrap_or_default();
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "push_fees")
+        .add_attribute("last_instance", last_instance))
+}
+
+// Up to `limit` instances after `start_after`
+fn instances_page(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Instance>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    INSTANCES
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, instance)| instance))
+        .collect()
+}
+
+fn update_fees_msg(
+    instance: &Addr,
+    fee_asset: Option<Asset>,
+    fees: Option<Vec<Decimal>>,
+) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: instance.to_string(),
+        msg: to_binary(&cw_1620::msg::ExecuteMsg::UpdateConfig {
+            owner: None,
+            fee_asset,
+            fees,
+            unbonding_period: None,
+        })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+fn load_instance(storage: &dyn Storage, address: Addr) -> Result<Instance, ContractError> {
+    INSTANCES
+        .may_load(storage, &address)?
+        .ok_or(ContractError::UnknownInstance {
+            address: address.to_string(),
+        })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Instances {
+            org_admin,
+            start_after,
+            limit,
+        } => to_binary(&query_instances(deps, org_admin, start_after, limit)?),
+        QueryMsg::StreamsByRecipient {
+            payee,
+            start_after,
+            limit,
+        } => to_binary(&query_streams_across(
+            deps,
+            cw_1620::msg::QueryMsg::StreamsByRecipient {
+                payee,
+                reverse: None,
+                limit: None,
+            },
+            start_after,
+            limit,
+        )?),
+        QueryMsg::StreamsBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&query_streams_across(
+            deps,
+            cw_1620::msg::QueryMsg::StreamsBySender {
+                sender,
+                reverse: None,
+                limit: None,
+            },
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+fn query_instances(
+    deps: Deps,
+    org_admin: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InstancesResponse> {
+    let instances = match org_admin {
+        Some(org_admin) => {
+            let org_admin = deps.api.addr_validate(&org_admin)?;
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let start_after = start_after
+                .map(|address| deps.api.addr_validate(&address))
+                .transpose()?;
+            ORG_INSTANCES
+                .prefix(&org_admin)
+                .keys(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|address| INSTANCES.load(deps.storage, &address?))
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => instances_page(deps, start_after, limit)?,
+    };
+    Ok(InstancesResponse { instances })
+}
+
+// Sends the same streams query to one page of instances, leaving out those with nothing to report
+fn query_streams_across(
+    deps: Deps,
+    msg: cw_1620::msg::QueryMsg,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<HubStreamsResponse> {
+    let page = instances_page(deps, start_after, limit)?;
+    let last_instance = page.last().map(|instance| instance.address.clone());
+
+    let mut instances = vec![];
+    for instance in page {
+        let res: cw_1620::msg::StreamsResponse =
+            deps.querier.query_wasm_smart(&instance.address, &msg)?;
+        if !res.streams.is_empty() {
+            instances.push(InstanceStreams {
+                instance: instance.address,
+                streams: res.streams,
+            });
+        }
+    }
+    Ok(HubStreamsResponse {
+        instances,
+        last_instance,
+    })
+}
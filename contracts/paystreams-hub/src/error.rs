@@ -0,0 +1,48 @@
+use cosmwasm_std::StdError;
+use cw_utils::{ParseReplyError, PaymentError};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("{address} is not a stream contract of this hub")]
+    UnknownInstance { address: String },
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("No stream contract is set at the other end of {channel_id}")]
+    NoRemoteContract { channel_id: String },
+
+    #[error("The stream has to start before it stops")]
+    InvalidSchedule {},
+
+    #[error("The reply of a transfer holds no sequence")]
+    InvalidTransferReply {},
+
+    #[error("{address} is not an address of the chain the stream is created on")]
+    InvalidRemoteAddress { address: String },
+
+    #[error("Only a stream with a sender on the other chain can be cancelled")]
+    InvalidCancelPolicy {},
+
+    #[error("The other end of {channel_id} is not the stream contract set for it")]
+    UnexpectedCounterparty { channel_id: String },
+
+    #[error("Only unordered channels are supported")]
+    OnlyUnorderedChannel {},
+
+    #[error("Expected IBC version paystreams-1, got {version}")]
+    InvalidIbcVersion { version: String },
+}
@@ -0,0 +1,1074 @@
+// Streams created on another chain
+// Over a stream channel, opened with the stream contract at the other end, the hub holds what the sender pays and
+// sends a `StreamPacket`. The stream is paid there from the liquidity a provider offers, and the hub pays the
+// provider once the packet is acknowledged. An error, an acknowledgement it can't read or a timeout refunds the
+// sender instead.
+// Over a transfer channel the hub sends what the sender pays over ICS-20 to the stream contract at the other end,
+// with a memo which has ibc-hooks create the stream out of exactly those tokens. The stream is created in the
+// same transaction the transfer is received in, so if it can't be created the transfer fails and ICS-20 refunds
+// the hub. ibc-hooks then reports the outcome through `sudo`, and a failed or timed out transfer is paid back
+// to its sender. A successful one settles nothing, the tokens already are in the stream
+use cosmwasm_schema::cw_serde;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    coins, from_binary, to_binary, BankMsg, Binary, CosmosMsg, DepsMut, Env, IbcBasicResponse,
+    IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg,
+    IbcChannelOpenResponse, IbcMsg, IbcOrder, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout, MessageInfo, Reply, Response, StdError,
+    SubMsg, Timestamp,
+};
+use cw_1620::ibc::{StreamPacket, StreamPacketAck, StreamPacketResult, IBC_APP_VERSION};
+use cw_1620::ibc_hooks::{
+    bech32_prefix, IbcLifecycleComplete, MsgTransfer, MsgTransferResponse, ProtoCoin,
+};
+use cw_1620::state::{CancelPolicy, CancelableBy, StreamTime};
+use cw_utils::one_coin;
+use prost::Message;
+
+use crate::state::{
+    RemoteContract, CONFIG, PENDING_TRANSFER, REMOTE_CONTRACTS, REMOTE_TRANSFERS, STREAM_CHANNELS,
+};
+use crate::ContractError;
+
+// Reply id of the ICS-20 transfer creating a remote stream, its response holds the packet sequence
+pub const TRANSFER_REPLY_ID: u64 = 2;
+// How long a transfer has to be relayed when the sender doesn't say
+const DEFAULT_TIMEOUT_SECONDS: u64 = 60 * 60;
+
+// Memo ibc-hooks reads from the transfer, executing `wasm` and calling the hub back once the transfer completes
+#[cw_serde]
+struct HookMemo {
+    wasm: WasmHook,
+    ibc_callback: String,
+}
+
+#[cw_serde]
+struct WasmHook {
+    contract: String,
+    msg: cw_1620::msg::ExecuteMsg,
+}
+
+// Sets the stream contract at the other end of a stream or transfer channel, owner only.
+// Passing none stops creating streams over that channel
+pub fn set_remote_contract(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel_id: String,
+    remote: Option<RemoteContract>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    match &remote {
+        Some(remote) => REMOTE_CONTRACTS.save(deps.storage, &channel_id, remote)?,
+        None => REMOTE_CONTRACTS.remove(deps.storage, &channel_id),
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_remote_contract")
+        .add_attribute("channel_id", channel_id)
+        .add_attribute(
+            "remote_contract",
+            remote.map(|remote| remote.contract).unwrap_or_default(),
+        ))
+}
+
+// Addresses on the other chain have to be bech32 with the prefix of its stream contract, anything else would
+// only fail once it got there
+fn validate_remote_address(address: &str, remote: &RemoteContract) -> Result<(), ContractError> {
+    match bech32_prefix(address) {
+        Some(prefix)
+            if address == address.to_lowercase()
+                && bech32_prefix(&remote.contract).as_deref() == Some(prefix.as_str()) =>
+        {
+            Ok(())
+        }
+        _ => Err(ContractError::InvalidRemoteAddress {
+            address: address.to_string(),
+        }),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_remote_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    sender: Option<String>,
+    recipient: String,
+    start_time: Timestamp,
+    stop_time: Timestamp,
+    cancel_policy: Option<CancelPolicy>,
+    timeout: Option<u64>,
+) -> Result<Response, ContractError> {
+    let payment = one_coin(&info)?;
+    let remote = REMOTE_CONTRACTS
+        .may_load(deps.storage, &channel_id)?
+        .ok_or_else(|| ContractError::NoRemoteContract {
+            channel_id: channel_id.clone(),
+        })?;
+    if start_time >= stop_time {
+        return Err(ContractError::InvalidSchedule {});
+    }
+    validate_remote_address(&recipient, &remote)?;
+    if let Some(sender) = &sender {
+        validate_remote_address(sender, &remote)?;
+    }
+    if let Some(CancelPolicy {
+        cancelable_by: CancelableBy::Admin(admin),
+        ..
+    }) = &cancel_policy
+    {
+        validate_remote_address(admin.as_str(), &remote)?;
+    }
+    // Without a sender the stream belongs to an address nobody controls, which could never take back what a
+    // cancel returns
+    let cancel_policy = match (&sender, cancel_policy) {
+        (Some(_), cancel_policy) => cancel_policy,
+        (None, None) => Some(CancelPolicy {
+            cancelable_by: CancelableBy::None,
+            notice_period: None,
+        }),
+        (None, Some(_)) => return Err(ContractError::InvalidCancelPolicy {}),
+    };
+    let timeout = env
+        .block
+        .time
+        .plus_seconds(timeout.unwrap_or(DEFAULT_TIMEOUT_SECONDS));
+
+    if let Some(counterparty) = STREAM_CHANNELS.may_load(deps.storage, &channel_id)? {
+        if counterparty.port_id != format!("wasm.{}", remote.contract) {
+            return Err(ContractError::UnexpectedCounterparty { channel_id });
+        }
+        let packet = StreamPacket {
+            sender: info.sender.to_string(),
+            source_denom: payment.denom,
+            amount: payment.amount,
+            recipient,
+            remote_sender: sender,
+            start_time,
+            stop_time,
+            cancel_policy,
+        };
+        return Ok(Response::new()
+            .add_message(IbcMsg::SendPacket {
+                channel_id: channel_id.clone(),
+                data: to_binary(&packet)?,
+                timeout: IbcTimeout::with_timestamp(timeout),
+            })
+            .add_attribute("method", "create_remote_stream")
+            .add_attribute("channel_id", channel_id)
+            .add_attribute("sender", info.sender)
+            .add_attribute("amount", payment.amount));
+    }
+
+    // ibc-hooks executes the memo as an address derived from the hub and the channel it arrives on, the stream is
+    // created for the sender on the other chain instead when there is one
+    let memo = HookMemo {
+        wasm: WasmHook {
+            contract: remote.contract.clone(),
+            msg: cw_1620::msg::ExecuteMsg::CreateStreamFromIbc {
+                channel_id: remote.counterparty_channel_id,
+                original_sender: env.contract.address.to_string(),
+                sender,
+                recipient,
+                start_time: Some(StreamTime::Time(start_time)),
+                stop_time: Some(StreamTime::Time(stop_time)),
+                duration: None,
+                stream_type: None,
+                curve: None,
+                options: Some(cw_1620::msg::StreamOptions {
+                    cancel_policy,
+                    ..Default::default()
+                }),
+            },
+        },
+        ibc_callback: env.contract.address.to_string(),
+    };
+    let memo = String::from_utf8(to_binary(&memo)?.to_vec())
+        .map_err(|err| StdError::invalid_utf8(err.to_string()))?;
+    let transfer = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: channel_id.clone(),
+        token: Some(ProtoCoin {
+            denom: payment.denom.clone(),
+            amount: payment.amount.to_string(),
+        }),
+        sender: env.contract.address.to_string(),
+        receiver: remote.contract,
+        timeout_timestamp: timeout.nanos(),
+        memo,
+    };
+    PENDING_TRANSFER.save(
+        deps.storage,
+        &(info.sender.clone(), channel_id.clone(), payment.clone()),
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Stargate {
+                type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(),
+                value: Binary::from(transfer.encode_to_vec()),
+            },
+            TRANSFER_REPLY_ID,
+        ))
+        .add_attribute("method", "create_remote_stream")
+        .add_attribute("channel_id", channel_id)
+        .add_attribute("sender", info.sender)
+        .add_attribute("amount", payment.amount))
+}
+
+// Remembers the sequence of a transfer so its outcome can be matched in `sudo`
+pub fn register_transfer(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let data = msg
+        .result
+        .into_result()
+        .map_err(|_| ContractError::InvalidTransferReply {})?
+        .data
+        .unwrap_or_default();
+    let response = MsgTransferResponse::decode(data.as_slice())
+        .map_err(|_| ContractError::InvalidTransferReply {})?;
+    let (sender, channel_id, payment) = PENDING_TRANSFER.load(deps.storage)?;
+    PENDING_TRANSFER.remove(deps.storage);
+    REMOTE_TRANSFERS.save(
+        deps.storage,
+        (&channel_id, response.sequence),
+        &(sender, payment),
+    )?;
+
+    Ok(Response::new().add_attribute("sequence", response.sequence.to_string()))
+}
+
+// ICS-20 refunded the hub for a transfer which was acknowledged with an error or timed out, it goes back to
+// its sender. Anything short of a successful ack counts as a failure, only then does the remote stream hold
+// the tokens
+pub fn transfer_complete(
+    deps: DepsMut,
+    msg: IbcLifecycleComplete,
+) -> Result<Response, ContractError> {
+    let (channel, sequence, success) = match msg {
+        IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            success,
+            ..
+        } => (channel, sequence, success),
+        IbcLifecycleComplete::IbcTimeout { channel, sequence } => (channel, sequence, false),
+    };
+    let (sender, payment) = match REMOTE_TRANSFERS.may_load(deps.storage, (&channel, sequence))? {
+        Some(transfer) => transfer,
+        None => return Ok(Response::new()),
+    };
+    REMOTE_TRANSFERS.remove(deps.storage, (&channel, sequence));
+
+    let mut response = Response::new()
+        .add_attribute("method", "transfer_complete")
+        .add_attribute("sequence", sequence.to_string())
+        .add_attribute("success", success.to_string());
+    if !success {
+        response = response
+            .add_message(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![payment],
+            })
+            .add_attribute("refunded", sender);
+    }
+    Ok(response)
+}
+
+fn validate_channel(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.order != IbcOrder::Unordered {
+        return Err(ContractError::OnlyUnorderedChannel {});
+    }
+    if channel.version != IBC_APP_VERSION {
+        return Err(ContractError::InvalidIbcVersion {
+            version: channel.version.clone(),
+        });
+    }
+    if let Some(version) = counterparty_version {
+        if version != IBC_APP_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    validate_channel(msg.channel(), msg.counterparty_version())?;
+    Ok(None)
+}
+
+// Streams are only sent over a channel once the owner set the stream contract at its other end
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    validate_channel(msg.channel(), msg.counterparty_version())?;
+    let channel = msg.channel();
+    STREAM_CHANNELS.save(
+        deps.storage,
+        &channel.endpoint.channel_id,
+        &channel.counterparty_endpoint,
+    )?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_connect")
+        .add_attribute("channel_id", &channel.endpoint.channel_id))
+}
+
+// Packets still in flight over a closed channel time out and are refunded
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel_id = &msg.channel().endpoint.channel_id;
+    STREAM_CHANNELS.remove(deps.storage, channel_id);
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_close")
+        .add_attribute("channel_id", channel_id))
+}
+
+// Stream contracts never send packets to the hub
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    Ok(
+        IbcReceiveResponse::new().set_ack(to_binary(&StreamPacketAck::Error(
+            "The hub does not take packets".to_string(),
+        ))?),
+    )
+}
+
+// Pays what the sender paid to the provider of the stream, or back to the sender if anything went wrong. A
+// provider whose address can't be paid on this chain gets nothing
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let packet: StreamPacket = from_binary(&msg.original_packet.data)?;
+    let result = match from_binary(&msg.acknowledgement.data) {
+        Ok(StreamPacketAck::Result(result)) => from_binary::<StreamPacketResult>(&result).ok(),
+        _ => None,
+    };
+    let pay_to = result
+        .as_ref()
+        .and_then(|result| deps.api.addr_validate(&result.pay_to).ok());
+
+    match (pay_to, result) {
+        (Some(pay_to), Some(result)) => Ok(IbcBasicResponse::new()
+            .add_message(BankMsg::Send {
+                to_address: pay_to.to_string(),
+                amount: coins(packet.amount.u128(), &packet.source_denom),
+            })
+            .add_attribute("method", "ibc_packet_ack")
+            .add_attribute("stream_idx", result.stream_idx.to_string())
+            .add_attribute("paid", pay_to)),
+        _ => refund_packet(packet, "ibc_packet_ack"),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let packet: StreamPacket = from_binary(&msg.packet.data)?;
+    refund_packet(packet, "ibc_packet_timeout")
+}
+
+fn refund_packet(packet: StreamPacket, method: &str) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new()
+        .add_message(BankMsg::Send {
+            to_address: packet.sender.clone(),
+            amount: coins(packet.amount.u128(), &packet.source_denom),
+        })
+        .add_attribute("method", method)
+        .add_attribute("refunded", packet.sender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{execute, instantiate, reply, sudo};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_ibc_packet_ack, mock_ibc_packet_recv,
+        mock_ibc_packet_timeout, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        from_slice, Addr, Deps, IbcAcknowledgement, IbcEndpoint, Order, OwnedDeps, Storage,
+        SubMsgResponse, SubMsgResult, Uint256, WasmMsg,
+    };
+    use cw_1620::ibc_hooks::{derive_intermediate_sender, SudoMsg};
+    use cw_1620::msg::{SenderBalanceResponse, StreamsResponse};
+    use cw_1620::state::{ChannelOffer, PaymentStream};
+    use cw_asset::AssetInfo;
+    use cw_utils::Duration;
+
+    const CHANNEL: &str = "channel-3";
+    const COUNTERPARTY_CHANNEL: &str = "channel-42";
+    const STREAM_CHANNEL: &str = "channel-9";
+    const REMOTE_STREAM_CHANNEL: &str = "channel-51";
+    // Addresses on the other chain
+    const REMOTE: &str = "osmo1wd68yetpd4esrntax3";
+    const ALICE: &str = "osmo1v9kxjcm960ycmj";
+    const BOB: &str = "osmo1vfhkyejgfd6";
+    // Denom the transferred uusdc arrives in on the other chain
+    const REMOTE_DENOM: &str = "ibc/axlusdc";
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                owner: None,
+                stream_code_id: 1,
+                fee_asset: None,
+                fees: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetRemoteContract {
+                channel_id: CHANNEL.to_string(),
+                remote: Some(RemoteContract {
+                    counterparty_channel_id: COUNTERPARTY_CHANNEL.to_string(),
+                    contract: REMOTE.to_string(),
+                }),
+            },
+        )
+        .unwrap();
+        deps
+    }
+
+    fn create_msg(recipient: &str) -> ExecuteMsg {
+        let now = mock_env().block.time;
+        ExecuteMsg::CreateRemoteStream {
+            channel_id: CHANNEL.to_string(),
+            sender: None,
+            recipient: recipient.to_string(),
+            start_time: now,
+            stop_time: now.plus_seconds(100),
+            cancel_policy: None,
+            timeout: None,
+        }
+    }
+
+    // Sends a transfer from bob and relays it up to the point it is assigned `sequence`
+    fn send_transfer(mut deps: DepsMut, msg: ExecuteMsg, sequence: u64) -> MsgTransfer {
+        let res = execute(
+            deps.branch(),
+            mock_env(),
+            mock_info("bob", &coins(400, "uusdc")),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(res.messages[0].id, TRANSFER_REPLY_ID);
+        let transfer = match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, value } => {
+                assert_eq!(type_url, "/ibc.applications.transfer.v1.MsgTransfer");
+                MsgTransfer::decode(value.as_slice()).unwrap()
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        };
+
+        let data = MsgTransferResponse { sequence }.encode_to_vec();
+        reply(
+            deps,
+            mock_env(),
+            Reply {
+                id: TRANSFER_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(Binary::from(data)),
+                }),
+            },
+        )
+        .unwrap();
+        transfer
+    }
+
+    // What ibc-hooks does with a transfer on the other chain, it executes the memo as the intermediate sender
+    // with the transferred tokens in their local denom
+    fn receive_transfer(
+        remote: DepsMut,
+        transfer: &MsgTransfer,
+    ) -> Result<Response, cw_1620::ContractError> {
+        let memo: HookMemo = from_slice(transfer.memo.as_bytes()).unwrap();
+        assert_eq!(memo.wasm.contract, transfer.receiver);
+        let sender =
+            derive_intermediate_sender(COUNTERPARTY_CHANNEL, &transfer.sender, "osmo").unwrap();
+        let amount: u128 = transfer.token.as_ref().unwrap().amount.parse().unwrap();
+        cw_1620::contract::execute(
+            remote,
+            mock_env(),
+            mock_info(&sender, &coins(amount, REMOTE_DENOM)),
+            memo.wasm.msg,
+        )
+    }
+
+    fn complete(deps: DepsMut, msg: IbcLifecycleComplete) -> Response {
+        sudo(deps, mock_env(), SudoMsg::IbcLifecycleComplete(msg)).unwrap()
+    }
+
+    fn refund() -> Vec<SubMsg> {
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "bob".to_string(),
+            amount: coins(400, "uusdc"),
+        })]
+    }
+
+    #[test]
+    fn sends_transfer_to_remote_contract() {
+        let mut deps = setup();
+        let transfer = send_transfer(deps.as_mut(), create_msg(ALICE), 1);
+        assert_eq!(transfer.source_port, "transfer");
+        assert_eq!(transfer.source_channel, CHANNEL);
+        assert_eq!(transfer.sender, mock_env().contract.address.as_str());
+        assert_eq!(transfer.receiver, REMOTE);
+        assert_eq!(
+            transfer.token,
+            Some(ProtoCoin {
+                denom: "uusdc".to_string(),
+                amount: "400".to_string()
+            })
+        );
+        assert_eq!(
+            transfer.timeout_timestamp,
+            mock_env()
+                .block
+                .time
+                .plus_seconds(DEFAULT_TIMEOUT_SECONDS)
+                .nanos()
+        );
+        let memo: HookMemo = from_slice(transfer.memo.as_bytes()).unwrap();
+        assert_eq!(memo.ibc_callback, mock_env().contract.address.as_str());
+
+        // Only channels with a remote contract can be sent over
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetRemoteContract {
+                channel_id: CHANNEL.to_string(),
+                remote: None,
+            },
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &coins(400, "uusdc")),
+            create_msg(ALICE),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NoRemoteContract {
+                channel_id: CHANNEL.to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn round_trip_creates_remote_stream() {
+        let mut deps = setup();
+        let mut remote = mock_dependencies();
+        cw_1620::contract::instantiate(
+            remote.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            cw_1620::msg::InstantiateMsg {
+                count: 0,
+                governance_asset: None,
+            },
+        )
+        .unwrap();
+
+        let transfer = send_transfer(deps.as_mut(), create_msg(ALICE), 1);
+        receive_transfer(remote.as_mut(), &transfer).unwrap();
+
+        // The stream holds exactly the tokens which were transferred
+        let res = cw_1620::contract::query(
+            remote.as_ref(),
+            mock_env(),
+            cw_1620::msg::QueryMsg::StreamsByIndex { index: 1 },
+        )
+        .unwrap();
+        let stream = from_binary::<StreamsResponse>(&res).unwrap().streams[0].clone();
+        assert_eq!(stream.recipient, Addr::unchecked(ALICE));
+        assert_eq!(stream.deposit, Uint256::from(400u128));
+        assert_eq!(
+            stream.token_addr,
+            AssetInfo::Native(REMOTE_DENOM.to_string())
+        );
+        assert_eq!(stream.cancel_policy.cancelable_by, CancelableBy::None);
+
+        // A successful ack pays nothing out of the hub
+        let res = complete(
+            deps.as_mut(),
+            IbcLifecycleComplete::IbcAck {
+                channel: CHANNEL.to_string(),
+                sequence: 1,
+                ack: "{\"result\":\"AQ==\"}".to_string(),
+                success: true,
+            },
+        );
+        assert!(res.messages.is_empty());
+        assert!(REMOTE_TRANSFERS
+            .may_load(&deps.storage, (CHANNEL, 1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn failed_or_timed_out_transfers_are_refunded() {
+        let mut deps = setup();
+        // No stream contract was instantiated at the remote address
+        let mut remote = mock_dependencies();
+
+        // The stream can't be created on the other chain, so the transfer is acknowledged with an error
+        let transfer = send_transfer(deps.as_mut(), create_msg(ALICE), 1);
+        receive_transfer(remote.as_mut(), &transfer).unwrap_err();
+        let res = complete(
+            deps.as_mut(),
+            IbcLifecycleComplete::IbcAck {
+                channel: CHANNEL.to_string(),
+                sequence: 1,
+                ack: "{\"error\":\"invalid recipient\"}".to_string(),
+                success: false,
+            },
+        );
+        assert_eq!(res.messages, refund());
+
+        // Reported twice it refunds nothing more
+        let res = complete(
+            deps.as_mut(),
+            IbcLifecycleComplete::IbcTimeout {
+                channel: CHANNEL.to_string(),
+                sequence: 1,
+            },
+        );
+        assert!(res.messages.is_empty());
+
+        send_transfer(deps.as_mut(), create_msg(ALICE), 2);
+        let res = complete(
+            deps.as_mut(),
+            IbcLifecycleComplete::IbcTimeout {
+                channel: CHANNEL.to_string(),
+                sequence: 2,
+            },
+        );
+        assert_eq!(res.messages, refund());
+    }
+
+    fn remote_stream(remote: Deps, index: u64) -> PaymentStream {
+        let res = cw_1620::contract::query(
+            remote,
+            mock_env(),
+            cw_1620::msg::QueryMsg::StreamsByIndex { index },
+        )
+        .unwrap();
+        from_binary::<StreamsResponse>(&res)
+            .unwrap()
+            .streams
+            .remove(0)
+    }
+
+    fn remote_balance(remote: Deps, sender: &str) -> (u128, u128) {
+        let res = cw_1620::contract::query(
+            remote,
+            mock_env(),
+            cw_1620::msg::QueryMsg::SenderBalance {
+                sender: sender.to_string(),
+                asset: AssetInfo::Native(REMOTE_DENOM.to_string()),
+            },
+        )
+        .unwrap();
+        let balance: SenderBalanceResponse = from_binary(&res).unwrap();
+        (balance.free.u128(), balance.committed.u128())
+    }
+
+    #[test]
+    fn validates_remote_addresses_and_cancel_policy() {
+        let mut deps = setup();
+        // Addresses of another chain, of this one or not normalized would only fail there
+        for recipient in ["alice", "juno1v9kxjcm97jag5j", "OSMO1V9KXJCM960YCMJ"] {
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &coins(400, "uusdc")),
+                create_msg(recipient),
+            )
+            .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::InvalidRemoteAddress {
+                    address: recipient.to_string()
+                }
+            );
+        }
+
+        // Only a stream with a sender on the other chain can be cancelled
+        let policy = CancelPolicy {
+            cancelable_by: CancelableBy::Sender,
+            notice_period: None,
+        };
+        let mut msg = create_msg(ALICE);
+        if let ExecuteMsg::CreateRemoteStream { cancel_policy, .. } = &mut msg {
+            *cancel_policy = Some(policy.clone());
+        }
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &coins(400, "uusdc")),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidCancelPolicy {});
+
+        // With one the stream is created for it and it gets back what a cancel returns
+        if let ExecuteMsg::CreateRemoteStream { sender, .. } = &mut msg {
+            *sender = Some(BOB.to_string());
+        }
+        let mut remote = mock_dependencies();
+        cw_1620::contract::instantiate(
+            remote.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            cw_1620::msg::InstantiateMsg {
+                count: 0,
+                governance_asset: None,
+            },
+        )
+        .unwrap();
+        let transfer = send_transfer(deps.as_mut(), msg, 1);
+        receive_transfer(remote.as_mut(), &transfer).unwrap();
+        let stream = remote_stream(remote.as_ref(), 1);
+        assert_eq!(stream.sender, Addr::unchecked(BOB));
+        assert_eq!(stream.cancel_policy, policy);
+        cw_1620::contract::execute(
+            remote.as_mut(),
+            mock_env(),
+            mock_info(BOB, &[]),
+            cw_1620::msg::ExecuteMsg::CancelStream { stream_idx: 1 },
+        )
+        .unwrap();
+        assert_eq!(remote_balance(remote.as_ref(), BOB), (400, 0));
+    }
+
+    fn endpoint(port_id: &str, channel_id: &str) -> IbcEndpoint {
+        IbcEndpoint {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        }
+    }
+
+    // Opens the stream channel between the hub and the remote contract, where a provider offers 1000 for uusdc
+    fn open_stream_channel(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetRemoteContract {
+                channel_id: STREAM_CHANNEL.to_string(),
+                remote: Some(RemoteContract {
+                    counterparty_channel_id: REMOTE_STREAM_CHANNEL.to_string(),
+                    contract: REMOTE.to_string(),
+                }),
+            },
+        )
+        .unwrap();
+        let hub_end = endpoint(
+            &format!("wasm.{}", mock_env().contract.address),
+            STREAM_CHANNEL,
+        );
+        let remote_end = endpoint(&format!("wasm.{}", REMOTE), REMOTE_STREAM_CHANNEL);
+        let channel = IbcChannel::new(
+            hub_end.clone(),
+            remote_end.clone(),
+            IbcOrder::Unordered,
+            IBC_APP_VERSION,
+            "connection-0",
+        );
+        ibc_channel_open(
+            deps.as_mut(),
+            mock_env(),
+            IbcChannelOpenMsg::new_init(channel.clone()),
+        )
+        .unwrap();
+        ibc_channel_connect(
+            deps.as_mut(),
+            mock_env(),
+            IbcChannelConnectMsg::new_ack(channel, IBC_APP_VERSION),
+        )
+        .unwrap();
+
+        let mut remote = mock_dependencies();
+        cw_1620::contract::instantiate(
+            remote.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            cw_1620::msg::InstantiateMsg {
+                count: 0,
+                governance_asset: None,
+            },
+        )
+        .unwrap();
+        let channel = IbcChannel::new(
+            remote_end,
+            hub_end,
+            IbcOrder::Unordered,
+            IBC_APP_VERSION,
+            "connection-0",
+        );
+        cw_1620::ibc::ibc_channel_open(
+            remote.as_mut(),
+            mock_env(),
+            IbcChannelOpenMsg::new_try(channel.clone(), IBC_APP_VERSION),
+        )
+        .unwrap();
+        cw_1620::ibc::ibc_channel_connect(
+            remote.as_mut(),
+            mock_env(),
+            IbcChannelConnectMsg::new_confirm(channel),
+        )
+        .unwrap();
+        cw_1620::contract::execute(
+            remote.as_mut(),
+            mock_env(),
+            mock_info("provider", &coins(1000, REMOTE_DENOM)),
+            cw_1620::msg::ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        cw_1620::contract::execute(
+            remote.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            cw_1620::msg::ExecuteMsg::OfferChannelLiquidity {
+                channel_id: REMOTE_STREAM_CHANNEL.to_string(),
+                offer: Some(ChannelOffer {
+                    denom: REMOTE_DENOM.to_string(),
+                    source_denom: "uusdc".to_string(),
+                    source_address: "provider".to_string(),
+                }),
+            },
+        )
+        .unwrap();
+        remote
+    }
+
+    fn packet_msg(sender: Option<&str>, cancel_policy: Option<CancelPolicy>) -> ExecuteMsg {
+        let now = mock_env().block.time;
+        ExecuteMsg::CreateRemoteStream {
+            channel_id: STREAM_CHANNEL.to_string(),
+            sender: sender.map(str::to_string),
+            recipient: ALICE.to_string(),
+            start_time: now,
+            stop_time: now.plus_seconds(100),
+            cancel_policy,
+            timeout: None,
+        }
+    }
+
+    // Sends a stream packet from bob, the hub holds what bob paid
+    fn send_packet(deps: DepsMut, msg: ExecuteMsg) -> StreamPacket {
+        let res = execute(
+            deps,
+            mock_env(),
+            mock_info("bob", &coins(400, "uusdc")),
+            msg,
+        )
+        .unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket {
+                channel_id,
+                data,
+                timeout,
+            }) => {
+                assert_eq!(channel_id, STREAM_CHANNEL);
+                assert_eq!(
+                    timeout.timestamp(),
+                    Some(mock_env().block.time.plus_seconds(DEFAULT_TIMEOUT_SECONDS))
+                );
+                from_binary(data).unwrap()
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+    }
+
+    // What the other chain does with the packet, the stream contract receives it and the submessage creating the
+    // stream runs. A failed submessage is reverted and its reply sets the acknowledgement
+    fn receive_packet(
+        remote: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        packet: &StreamPacket,
+    ) -> Binary {
+        let msg = mock_ibc_packet_recv(REMOTE_STREAM_CHANNEL, packet).unwrap();
+        let res = cw_1620::ibc::ibc_packet_receive(remote.as_mut(), mock_env(), msg).unwrap();
+        let submsg = match res.messages.first() {
+            Some(submsg) => submsg,
+            None => return res.acknowledgement,
+        };
+        let msg = match &submsg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => from_binary(msg).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        let env = mock_env();
+        let info = mock_info(env.contract.address.as_str(), &[]);
+        let snapshot: Vec<_> = remote.storage.range(None, None, Order::Ascending).collect();
+        match cw_1620::contract::execute(remote.as_mut(), env.clone(), info, msg) {
+            Ok(_) => res.acknowledgement,
+            Err(err) => {
+                remote.storage = MockStorage::new();
+                for (key, value) in snapshot {
+                    remote.storage.set(&key, &value);
+                }
+                let reply = Reply {
+                    id: submsg.id,
+                    result: SubMsgResult::Err(err.to_string()),
+                };
+                cw_1620::contract::reply(remote.as_mut(), env, reply)
+                    .unwrap()
+                    .data
+                    .unwrap()
+            }
+        }
+    }
+
+    fn acknowledge(deps: DepsMut, packet: &StreamPacket, ack: Binary) -> IbcBasicResponse {
+        let msg =
+            mock_ibc_packet_ack(STREAM_CHANNEL, packet, IbcAcknowledgement::new(ack)).unwrap();
+        ibc_packet_ack(deps, mock_env(), msg).unwrap()
+    }
+
+    #[test]
+    fn packet_creates_remote_stream_and_pays_the_provider() {
+        let mut deps = setup();
+        let mut remote = open_stream_channel(&mut deps);
+        let policy = CancelPolicy {
+            cancelable_by: CancelableBy::Sender,
+            notice_period: None,
+        };
+        let packet = send_packet(deps.as_mut(), packet_msg(Some(BOB), Some(policy.clone())));
+        assert_eq!(packet.sender, "bob");
+        let ack = receive_packet(&mut remote, &packet);
+
+        let stream = remote_stream(remote.as_ref(), 1);
+        assert_eq!(stream.sender, Addr::unchecked(BOB));
+        assert_eq!(stream.recipient, Addr::unchecked(ALICE));
+        assert_eq!(stream.deposit, Uint256::from(400u128));
+        assert_eq!(stream.cancel_policy, policy);
+        assert_eq!(remote_balance(remote.as_ref(), "provider"), (600, 0));
+
+        // The provider is paid what bob paid the hub
+        let res = acknowledge(deps.as_mut(), &packet, ack);
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "provider".to_string(),
+                amount: coins(400, "uusdc"),
+            })]
+        );
+
+        // Bob can take back what is left on the other chain
+        cw_1620::contract::execute(
+            remote.as_mut(),
+            mock_env(),
+            mock_info(BOB, &[]),
+            cw_1620::msg::ExecuteMsg::CancelStream { stream_idx: 1 },
+        )
+        .unwrap();
+        assert_eq!(remote_balance(remote.as_ref(), BOB), (400, 0));
+
+        // Packets only go to the stream contract set for the channel
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetRemoteContract {
+                channel_id: STREAM_CHANNEL.to_string(),
+                remote: Some(RemoteContract {
+                    counterparty_channel_id: REMOTE_STREAM_CHANNEL.to_string(),
+                    contract: "osmo1vdshymmvctd8gf".to_string(),
+                }),
+            },
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &coins(400, "uusdc")),
+            packet_msg(None, None),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UnexpectedCounterparty {
+                channel_id: STREAM_CHANNEL.to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn failed_timed_out_or_unreadable_packets_are_refunded() {
+        let mut deps = setup();
+        let mut remote = open_stream_channel(&mut deps);
+
+        // The stream can't be created on the other chain, its notice period doesn't run on the clock of the stream
+        let policy = CancelPolicy {
+            cancelable_by: CancelableBy::Sender,
+            notice_period: Some(Duration::Height(10)),
+        };
+        let packet = send_packet(deps.as_mut(), packet_msg(Some(BOB), Some(policy)));
+        let ack = receive_packet(&mut remote, &packet);
+        assert_eq!(
+            from_binary::<StreamPacketAck>(&ack).unwrap(),
+            StreamPacketAck::Error(cw_1620::ContractError::MismatchedStreamUnits {}.to_string())
+        );
+        // Nothing of the failed stream is kept there, and bob gets his payment back here
+        assert_eq!(remote_balance(remote.as_ref(), "provider"), (1000, 0));
+        assert_eq!(remote_balance(remote.as_ref(), BOB), (0, 0));
+        assert_eq!(acknowledge(deps.as_mut(), &packet, ack).messages, refund());
+
+        // An acknowledgement which can't be read refunds as well
+        let packet = send_packet(deps.as_mut(), packet_msg(None, None));
+        let res = acknowledge(deps.as_mut(), &packet, Binary::from(b"garbled".to_vec()));
+        assert_eq!(res.messages, refund());
+
+        let packet = send_packet(deps.as_mut(), packet_msg(None, None));
+        let msg = mock_ibc_packet_timeout(STREAM_CHANNEL, &packet).unwrap();
+        let res = ibc_packet_timeout(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(res.messages, refund());
+    }
+}
@@ -0,0 +1,9 @@
+pub mod contract;
+mod error;
+pub mod ibc;
+pub mod msg;
+pub mod state;
+pub use crate::error::ContractError;
+
+#[cfg(test)]
+mod tests;
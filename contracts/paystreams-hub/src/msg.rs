@@ -0,0 +1,117 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Decimal, Timestamp};
+use cw_1620::state::{CancelPolicy, PaymentStream};
+use cw_asset::AssetInfo;
+use white_whale::pool_network::asset::Asset;
+
+use crate::state::{Config, Instance, RemoteContract};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Defaults to the instantiator
+    pub owner: Option<String>,
+    pub stream_code_id: u64,
+    pub fee_asset: Option<Asset>,
+    pub fees: Option<Vec<Decimal>>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Deploy a stream contract for an organization, administered by `org_admin` or else the caller.
+    /// The hub owns the instance so it can keep its config in sync. Streams of `governance_asset`, or of
+    /// every asset without one, carry voting power in the instance
+    CreateInstance {
+        org_admin: Option<String>,
+        label: String,
+        governance_asset: Option<AssetInfo>,
+    },
+    /// Change the fee settings of one instance, callable by its organization admin or the hub owner.
+    /// The instance keeps these from then on, `PushFees` skips it
+    UpdateInstanceFees {
+        instance: String,
+        fee_asset: Option<Asset>,
+        fees: Option<Vec<Decimal>>,
+    },
+    /// Set the stream contract at the other end of the stream or transfer channel `channel_id`, owner only.
+    /// Passing none stops creating streams over the channel
+    SetRemoteContract {
+        channel_id: String,
+        remote: Option<RemoteContract>,
+    },
+    /// Create a stream on the chain at the other end of `channel_id` out of the funds sent along. Over a stream
+    /// channel the hub holds them and the stream is paid from liquidity on the other chain, over a transfer channel
+    /// they are transferred there over ICS-20. They are refunded if the stream can't be created there or the packet
+    /// times out, after `timeout` seconds. The stream is created for `sender`, an address on the other chain, which
+    /// can cancel it and gets back what a cancel returns. Without one the stream can't be cancelled
+    CreateRemoteStream {
+        channel_id: String,
+        sender: Option<String>,
+        recipient: String,
+        start_time: Timestamp,
+        stop_time: Timestamp,
+        cancel_policy: Option<CancelPolicy>,
+        timeout: Option<u64>,
+    },
+    /// Change the hub config, only the owner can do this.
+    /// New fee settings reach existing instances through `PushFees`
+    UpdateConfig {
+        owner: Option<String>,
+        stream_code_id: Option<u64>,
+        fee_asset: Option<Asset>,
+        fees: Option<Vec<Decimal>>,
+    },
+    /// Push the fee settings of the hub to up to `limit` instances after `start_after`, owner only.
+    /// Instances whose organization set its own fees keep them. Repeat from the `last_instance`
+    /// attribute until it is empty
+    PushFees {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(Config)]
+    Config {},
+    /// All instances, or only those of one organization admin
+    #[returns(InstancesResponse)]
+    Instances {
+        org_admin: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Streams paying `payee` across up to `limit` instances after `start_after`
+    #[returns(HubStreamsResponse)]
+    StreamsByRecipient {
+        payee: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Streams funded by `sender` across up to `limit` instances after `start_after`
+    #[returns(HubStreamsResponse)]
+    StreamsBySender {
+        sender: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct InstancesResponse {
+    pub instances: Vec<Instance>,
+}
+
+/// The streams found in one instance
+#[cw_serde]
+pub struct InstanceStreams {
+    pub instance: Addr,
+    pub streams: Vec<PaymentStream>,
+}
+
+#[cw_serde]
+pub struct HubStreamsResponse {
+    pub instances: Vec<InstanceStreams>,
+    /// Last instance queried, where the next page starts after. None once every instance was queried
+    pub last_instance: Option<Addr>,
+}
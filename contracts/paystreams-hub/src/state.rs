@@ -0,0 +1,54 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Empty, IbcEndpoint};
+use cw_storage_plus::{Item, Map};
+use white_whale::pool_network::asset::Asset;
+
+#[cw_serde]
+pub struct Config {
+    pub owner: Addr,
+    /// Code of the cw-1620 contract new instances are created from
+    pub stream_code_id: u64,
+    /// Fee settings every instance is given, pushed again with `PushFees` when changed
+    pub fee_asset: Option<Asset>,
+    pub fees: Option<Vec<Decimal>>,
+}
+
+/// A stream contract deployed by the hub for an organization
+#[cw_serde]
+pub struct Instance {
+    pub address: Addr,
+    pub org_admin: Addr,
+    pub label: String,
+    pub code_id: u64,
+    /// Set once the organization picks its own fees, the fees of the hub are not pushed to it anymore
+    #[serde(default)]
+    pub own_fees: bool,
+}
+
+/// Where streams paid for over a channel are created
+#[cw_serde]
+pub struct RemoteContract {
+    /// The channel transfers arrive on at the other end, ibc-hooks derives the stream sender from it.
+    /// Unused for stream channels, which know their counterparty
+    pub counterparty_channel_id: String,
+    /// The stream contract on the other chain
+    pub contract: String,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+// Every instance by its contract address
+pub const INSTANCES: Map<&Addr, Instance> = Map::new("instances");
+// The instances of each organization admin
+pub const ORG_INSTANCES: Map<(&Addr, &Addr), Empty> = Map::new("org_instances");
+// Admin and label of the instance being created, read back once its address is known in the reply
+pub const PENDING_INSTANCE: Item<(Addr, String)> = Item::new("pending_instance");
+// The stream contract at the other end of each transfer channel
+pub const REMOTE_CONTRACTS: Map<&str, RemoteContract> = Map::new("remote_contracts");
+// Sender, channel and payment of the transfer being sent, read back once its sequence is known in the reply.
+// One item is enough: the transfer is the only submessage of `CreateRemoteStream` and replies on success, so the
+// reply runs right after it before anything else executes. A transfer which fails reverts the whole transaction
+pub const PENDING_TRANSFER: Item<(Addr, String, Coin)> = Item::new("pending_transfer");
+// Channels opened with stream contracts, with the endpoint at the other end
+pub const STREAM_CHANNELS: Map<&str, IbcEndpoint> = Map::new("stream_channels");
+// Transfers in flight by channel and sequence, refunded to their sender if they fail or time out
+pub const REMOTE_TRANSFERS: Map<(&str, u64), (Addr, Coin)> = Map::new("remote_transfers");
@@ -0,0 +1,251 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128, Uint256};
+
+use crate::{tests::suite::SuiteBuilder, ContractError};
+
+const DENOM: &str = "ibc/something/axlusdc";
+
+fn fee(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+#[test]
+fn test_instances_are_registered_per_org_admin() {
+    let mut suite = SuiteBuilder::new().build();
+
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme payroll")
+        .unwrap();
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme grants")
+        .unwrap();
+    suite
+        .create_instance(Addr::unchecked("owner"), Some("globex"), "Globex payroll")
+        .unwrap();
+
+    let acme = suite.query_instances(Some("acme")).unwrap();
+    assert_eq!(acme.len(), 2);
+    assert!(acme.iter().all(|i| i.org_admin == Addr::unchecked("acme")));
+    let globex = suite.query_instances(Some("globex")).unwrap();
+    assert_eq!(globex.len(), 1);
+    assert_eq!(globex[0].label, "Globex payroll");
+    assert_eq!(suite.query_instances(None).unwrap().len(), 3);
+
+    // The hub owns every instance it deploys
+    assert_eq!(
+        suite.query_instance_owner(&globex[0].address).unwrap(),
+        suite.hub_addr
+    );
+}
+
+#[test]
+fn test_fees_are_pushed_to_instances() {
+    let mut suite = SuiteBuilder::new().with_fees(vec![fee("0.01")]).build();
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme payroll")
+        .unwrap();
+    suite
+        .create_instance(Addr::unchecked("globex"), None, "Globex payroll")
+        .unwrap();
+    let instances = suite.query_instances(None).unwrap();
+
+    // New instances start with the fees of the hub
+    for instance in &instances {
+        assert_eq!(
+            suite.query_instance_fees(&instance.address).unwrap(),
+            Some(vec![fee("0.01")])
+        );
+    }
+
+    let err = suite
+        .update_hub_fees(Addr::unchecked("acme"), vec![fee("0.02")])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    suite
+        .update_hub_fees(Addr::unchecked("owner"), vec![fee("0.02")])
+        .unwrap();
+    // Existing instances get the new fees page by page
+    let err = suite
+        .push_fees(Addr::unchecked("acme"), None, None)
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    suite
+        .push_fees(Addr::unchecked("owner"), None, Some(1))
+        .unwrap();
+    assert_eq!(
+        suite.query_instance_fees(&instances[0].address).unwrap(),
+        Some(vec![fee("0.02")])
+    );
+    assert_eq!(
+        suite.query_instance_fees(&instances[1].address).unwrap(),
+        Some(vec![fee("0.01")])
+    );
+    suite
+        .push_fees(
+            Addr::unchecked("owner"),
+            Some(&instances[0].address),
+            Some(1),
+        )
+        .unwrap();
+    for instance in &instances {
+        assert_eq!(
+            suite.query_instance_fees(&instance.address).unwrap(),
+            Some(vec![fee("0.02")])
+        );
+    }
+}
+
+#[test]
+fn test_own_instance_fees_are_not_overwritten() {
+    let mut suite = SuiteBuilder::new().with_fees(vec![fee("0.01")]).build();
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme payroll")
+        .unwrap();
+    suite
+        .create_instance(Addr::unchecked("globex"), None, "Globex payroll")
+        .unwrap();
+    let acme = suite.query_instances(Some("acme")).unwrap()[0].clone();
+    let globex = suite.query_instances(Some("globex")).unwrap()[0].clone();
+    assert!(!acme.own_fees);
+
+    suite
+        .update_instance_fees(Addr::unchecked("acme"), &acme.address, vec![fee("0.05")])
+        .unwrap();
+    assert!(suite.query_instances(Some("acme")).unwrap()[0].own_fees);
+
+    suite
+        .update_hub_fees(Addr::unchecked("owner"), vec![fee("0.02")])
+        .unwrap();
+    suite
+        .push_fees(Addr::unchecked("owner"), None, None)
+        .unwrap();
+    assert_eq!(
+        suite.query_instance_fees(&acme.address).unwrap(),
+        Some(vec![fee("0.05")])
+    );
+    assert_eq!(
+        suite.query_instance_fees(&globex.address).unwrap(),
+        Some(vec![fee("0.02")])
+    );
+}
+
+#[test]
+fn test_org_admin_updates_its_own_instance() {
+    let mut suite = SuiteBuilder::new().build();
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme payroll")
+        .unwrap();
+    let acme = suite.query_instances(Some("acme")).unwrap()[0]
+        .address
+        .clone();
+
+    let err = suite
+        .update_instance_fees(Addr::unchecked("globex"), &acme, vec![fee("0.05")])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    suite
+        .update_instance_fees(Addr::unchecked("acme"), &acme, vec![fee("0.05")])
+        .unwrap();
+    assert_eq!(
+        suite.query_instance_fees(&acme).unwrap(),
+        Some(vec![fee("0.05")])
+    );
+
+    let err = suite
+        .update_instance_fees(
+            Addr::unchecked("owner"),
+            &Addr::unchecked("elsewhere"),
+            vec![fee("0.05")],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::UnknownInstance {
+            address: "elsewhere".to_string()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_queries_are_routed_across_instances() {
+    let mut suite = SuiteBuilder::new()
+        .with_funds(
+            "acme",
+            &[Coin {
+                denom: DENOM.to_string(),
+                amount: Uint128::from(1000u128),
+            }],
+        )
+        .with_funds(
+            "globex",
+            &[Coin {
+                denom: DENOM.to_string(),
+                amount: Uint128::from(1000u128),
+            }],
+        )
+        .build();
+    suite
+        .create_instance(Addr::unchecked("acme"), None, "Acme payroll")
+        .unwrap();
+    suite
+        .create_instance(Addr::unchecked("globex"), None, "Globex payroll")
+        .unwrap();
+    let acme = suite.query_instances(Some("acme")).unwrap()[0]
+        .address
+        .clone();
+    let globex = suite.query_instances(Some("globex")).unwrap()[0]
+        .address
+        .clone();
+
+    suite
+        .create_stream_on(&acme, Addr::unchecked("acme"), "alice", 300, DENOM)
+        .unwrap();
+    suite
+        .create_stream_on(&globex, Addr::unchecked("globex"), "alice", 200, DENOM)
+        .unwrap();
+    suite
+        .create_stream_on(&globex, Addr::unchecked("globex"), "bob", 100, DENOM)
+        .unwrap();
+
+    let alice = suite.query_streams_by_recipient("alice").unwrap();
+    assert_eq!(alice.instances.len(), 2);
+    let total: Uint256 = alice
+        .instances
+        .iter()
+        .flat_map(|i| i.streams.iter())
+        .map(|stream| stream.deposit)
+        .sum();
+    assert_eq!(total, Uint256::from(500u128));
+
+    let globex_streams = suite.query_streams_by_sender("globex").unwrap();
+    assert_eq!(globex_streams.instances.len(), 1);
+    assert_eq!(globex_streams.instances[0].instance, globex);
+    assert_eq!(globex_streams.instances[0].streams.len(), 2);
+
+    assert!(suite
+        .query_streams_by_recipient("carol")
+        .unwrap()
+        .instances
+        .is_empty());
+
+    // One instance at a time
+    let first = suite
+        .query_streams_by_recipient_page("alice", None, Some(1))
+        .unwrap();
+    assert_eq!(first.instances.len(), 1);
+    let last_instance = first.last_instance.unwrap();
+    assert_eq!(first.instances[0].instance, last_instance);
+    let second = suite
+        .query_streams_by_recipient_page("alice", Some(&last_instance), Some(1))
+        .unwrap();
+    assert_eq!(second.instances.len(), 1);
+    assert_ne!(second.instances[0].instance, last_instance);
+    let done = suite
+        .query_streams_by_recipient_page("alice", second.last_instance.as_ref(), Some(1))
+        .unwrap();
+    assert!(done.instances.is_empty());
+    assert_eq!(done.last_instance, None);
+}
@@ -0,0 +1,249 @@
+use anyhow::Result as AnyResult;
+use cosmwasm_std::{Addr, Coin, Decimal, StdResult};
+use cw_1620::state::StreamTime;
+use cw_asset::Asset;
+use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+
+use crate::msg::{HubStreamsResponse, InstancesResponse};
+use crate::state::Instance;
+
+pub fn store_streaming_contract(app: &mut App) -> u64 {
+    let contract = Box::new(
+        ContractWrapper::new_with_empty(
+            cw_1620::contract::execute,
+            cw_1620::contract::instantiate,
+            cw_1620::contract::query,
+        )
+        .with_reply(cw_1620::contract::reply),
+    );
+
+    app.store_code(contract)
+}
+
+fn store_hub(app: &mut App) -> u64 {
+    let contract = Box::new(
+        ContractWrapper::new_with_empty(
+            crate::contract::execute,
+            crate::contract::instantiate,
+            crate::contract::query,
+        )
+        .with_reply(crate::contract::reply),
+    );
+
+    app.store_code(contract)
+}
+
+#[derive(Debug)]
+pub struct SuiteBuilder {
+    funds: Vec<(Addr, Vec<Coin>)>,
+    fees: Option<Vec<Decimal>>,
+}
+
+impl SuiteBuilder {
+    pub fn new() -> Self {
+        Self {
+            funds: vec![],
+            fees: None,
+        }
+    }
+
+    pub fn with_funds(mut self, addr: &str, funds: &[Coin]) -> Self {
+        self.funds.push((Addr::unchecked(addr), funds.into()));
+        self
+    }
+
+    pub fn with_fees(mut self, fees: Vec<Decimal>) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    #[track_caller]
+    pub fn build(self) -> Suite {
+        let mut app = App::default();
+        let owner = Addr::unchecked("owner");
+
+        let stream_code_id = store_streaming_contract(&mut app);
+        let hub_id = store_hub(&mut app);
+        let hub_addr = app
+            .instantiate_contract(
+                hub_id,
+                owner.clone(),
+                &crate::msg::InstantiateMsg {
+                    owner: None,
+                    stream_code_id,
+                    fee_asset: None,
+                    fees: self.fees,
+                },
+                &[],
+                "Paystreams Hub",
+                Some(owner.to_string()),
+            )
+            .unwrap();
+
+        let funds = self.funds;
+        app.init_modules(|router, _, storage| -> AnyResult<()> {
+            for (addr, coin) in funds {
+                router.bank.init_balance(storage, &addr, coin)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        Suite {
+            owner: owner.to_string(),
+            app,
+            hub_addr,
+        }
+    }
+}
+
+pub struct Suite {
+    pub owner: String,
+    app: App,
+    pub hub_addr: Addr,
+}
+
+impl Suite {
+    pub fn create_instance(
+        &mut self,
+        sender: Addr,
+        org_admin: Option<&str>,
+        label: &str,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::CreateInstance {
+            org_admin: org_admin.map(str::to_string),
+            label: label.to_string(),
+            governance_asset: None,
+        };
+
+        self.app
+            .execute_contract(sender, self.hub_addr.clone(), &msg, &[])
+    }
+
+    pub fn update_instance_fees(
+        &mut self,
+        sender: Addr,
+        instance: &Addr,
+        fees: Vec<Decimal>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::UpdateInstanceFees {
+            instance: instance.to_string(),
+            fee_asset: None,
+            fees: Some(fees),
+        };
+
+        self.app
+            .execute_contract(sender, self.hub_addr.clone(), &msg, &[])
+    }
+
+    pub fn update_hub_fees(&mut self, sender: Addr, fees: Vec<Decimal>) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            owner: None,
+            stream_code_id: None,
+            fee_asset: None,
+            fees: Some(fees),
+        };
+
+        self.app
+            .execute_contract(sender, self.hub_addr.clone(), &msg, &[])
+    }
+
+    pub fn push_fees(
+        &mut self,
+        sender: Addr,
+        start_after: Option<&Addr>,
+        limit: Option<u32>,
+    ) -> AnyResult<AppResponse> {
+        let msg = crate::msg::ExecuteMsg::PushFees {
+            start_after: start_after.map(Addr::to_string),
+            limit,
+        };
+
+        self.app
+            .execute_contract(sender, self.hub_addr.clone(), &msg, &[])
+    }
+
+    /// create a native stream running for 100 seconds directly on an instance
+    pub fn create_stream_on(
+        &mut self,
+        instance: &Addr,
+        sender: Addr,
+        recipient: &str,
+        amount: u128,
+        denom: &str,
+    ) -> AnyResult<AppResponse> {
+        let now = self.app.block_info().time;
+        let msg = cw_1620::msg::ExecuteMsg::CreateStream {
+            recipient: recipient.to_string(),
+            asset: Asset::native(denom, amount),
+            start_time: Some(StreamTime::Time(now)),
+            stop_time: Some(StreamTime::Time(now.plus_seconds(100))),
+            duration: None,
+            stream_type: None,
+            curve: None,
+            options: None,
+        };
+
+        self.app
+            .execute_contract(sender, instance.clone(), &msg, &[Coin::new(amount, denom)])
+    }
+
+    pub fn query_instances(&self, org_admin: Option<&str>) -> StdResult<Vec<Instance>> {
+        let res: InstancesResponse = self.app.wrap().query_wasm_smart(
+            &self.hub_addr,
+            &crate::msg::QueryMsg::Instances {
+                org_admin: org_admin.map(str::to_string),
+                start_after: None,
+                limit: None,
+            },
+        )?;
+        Ok(res.instances)
+    }
+
+    pub fn query_instance_fees(&self, instance: &Addr) -> StdResult<Option<Vec<Decimal>>> {
+        let res: cw_1620::msg::ConfigResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(instance, &cw_1620::msg::QueryMsg::Config {})?;
+        Ok(res.fees)
+    }
+
+    pub fn query_instance_owner(&self, instance: &Addr) -> StdResult<Addr> {
+        let res: cw_1620::msg::ConfigResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(instance, &cw_1620::msg::QueryMsg::Config {})?;
+        Ok(res.owner)
+    }
+
+    pub fn query_streams_by_recipient(&self, payee: &str) -> StdResult<HubStreamsResponse> {
+        self.query_streams_by_recipient_page(payee, None, None)
+    }
+
+    pub fn query_streams_by_recipient_page(
+        &self,
+        payee: &str,
+        start_after: Option<&Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<HubStreamsResponse> {
+        self.app.wrap().query_wasm_smart(
+            &self.hub_addr,
+            &crate::msg::QueryMsg::StreamsByRecipient {
+                payee: payee.to_string(),
+                start_after: start_after.map(Addr::to_string),
+                limit,
+            },
+        )
+    }
+
+    pub fn query_streams_by_sender(&self, sender: &str) -> StdResult<HubStreamsResponse> {
+        self.app.wrap().query_wasm_smart(
+            &self.hub_addr,
+            &crate::msg::QueryMsg::StreamsBySender {
+                sender: sender.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+    }
+}
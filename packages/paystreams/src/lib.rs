@@ -1,7 +1,116 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_binary, Binary, CosmosMsg, Decimal, StdResult, Timestamp, Uint128, WasmMsg};
+use cw_asset::AssetInfo;
+
+/// StreamReceiveMsg should be de/serialized under `StreamReceive()` variant in a ExecuteMsg.
+/// It is sent to the recipient of a stream, when they opted in, every time something happens to the stream
+#[cw_serde]
+pub struct StreamReceiveMsg {
+    pub stream_idx: u64,
+    pub sender: String,
+    pub asset: AssetInfo,
+    pub event: StreamEvent,
+}
+
+/// What happened to the stream
+#[cw_serde]
+pub enum StreamEvent {
+    /// The stream was created with `deposit` streaming to the recipient
+    Created { deposit: Uint128 },
+    /// `amount` was claimed from the stream and paid `to` an address
+    Claimed { amount: Uint128, to: String },
+    /// The stream was cancelled and its balance split between recipient and sender
+    Cancelled {
+        recipient_amount: Uint128,
+        sender_amount: Uint128,
+    },
+}
+
+impl StreamReceiveMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = ReceiverExecuteMsg::StreamReceive(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+// This is just a helper to properly serialize the above message
+#[cw_serde]
+enum ReceiverExecuteMsg {
+    StreamReceive(StreamReceiveMsg),
+}
+
+/// Interface of the vaults idle stream deposits can be routed into. A vault takes native deposits and keeps
+/// track of what each depositor can withdraw, including what the deposit earned
+#[cw_serde]
+pub enum VaultExecuteMsg {
+    /// Deposit the funds sent along for the sender
+    Deposit {},
+    /// Send `amount` of what the sender can withdraw back to it
+    Withdraw { amount: Uint128 },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum VaultQueryMsg {
+    /// What `depositor` can withdraw right now
+    #[returns(VaultBalanceResponse)]
+    Balance { depositor: String },
+}
+
+#[cw_serde]
+pub struct VaultBalanceResponse {
+    pub amount: Uint128,
+}
+
+/// Interface of the price oracles fiat denominated streams are priced with
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    /// Price of one unit of `base` in units of `quote`
+    #[returns(PriceResponse)]
+    Price { base: String, quote: String },
+}
+
+#[cw_serde]
+pub struct PriceResponse {
+    pub price: Decimal,
+    /// Uncertainty of the price, the true price is within `price` plus or minus this
+    pub confidence: Decimal,
+    /// When the price was last updated
+    pub updated_at: Timestamp,
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-  
+    #[test]
+    fn serializes_under_stream_receive() {
+        let msg = StreamReceiveMsg {
+            stream_idx: 1,
+            sender: "owner".to_string(),
+            asset: AssetInfo::Native("uluna".to_string()),
+            event: StreamEvent::Claimed {
+                amount: Uint128::new(10),
+                to: "alice".to_string(),
+            },
+        };
+        let binary = msg.into_binary().unwrap();
+        assert_eq!(
+            String::from_utf8(binary.to_vec()).unwrap(),
+            r#"{"stream_receive":{"stream_idx":1,"sender":"owner","asset":{"native":"uluna"},"event":{"claimed":{"amount":"10","to":"alice"}}}}"#
+        );
+    }
 }